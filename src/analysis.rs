@@ -0,0 +1,530 @@
+//! Post-game analysis: search every position of a finished game and compare the move that was
+//! played against the engine's own choice.
+
+use std::fmt::Write;
+
+#[cfg(feature = "sim")]
+use statrs::statistics::{Data, Median, Statistics};
+
+use crate::search::{self, SearchContext};
+use crate::transcript::Replay;
+use crate::{
+    decode_terminal, iterative_deepening_with, mate_distance, Board, Color, HeuristicFn, Move,
+    Posn, RenderStyle, SearchOptions, SearchStats, Square, COLS, POSNS, ROWS, SCORE_LOSS,
+    SCORE_WIN,
+};
+
+pub struct PlyAnalysis {
+    pub ply: usize,
+    pub mover: Color,
+    pub played: Move,
+    pub best: Move,
+    /// Score of the position with best play, White positive as with the heuristics
+    pub eval_before: i32,
+    /// Score after the move that was actually played
+    pub eval_after: i32,
+    /// How much the played move lost compared to the best move, from the mover's point of view
+    pub delta: i32,
+    /// Discs the played move flipped, and the most any legal move would have
+    pub flips: usize,
+    pub max_flips: usize,
+}
+
+/// Search every position of the game, sharing context's tables from one ply to the next
+pub fn analyze_game(
+    replay: &Replay,
+    depth: i32,
+    heuristic: fn(&crate::Board) -> i32,
+    context: &mut SearchContext,
+) -> Vec<PlyAnalysis> {
+    replay
+        .moves
+        .iter()
+        .enumerate()
+        .map(|(i, played)| {
+            let board = &replay.boards[i];
+            let after = &replay.boards[i + 1];
+            context.new_search();
+            match played {
+                Move::Pass => {
+                    let eval = search::alphabeta(
+                        after, depth, SCORE_LOSS, SCORE_WIN, heuristic, context, 0,
+                    );
+                    PlyAnalysis {
+                        ply: i + 1,
+                        mover: board.turn,
+                        played: Move::Pass,
+                        best: Move::Pass,
+                        eval_before: eval,
+                        eval_after: eval,
+                        delta: 0,
+                        flips: 0,
+                        max_flips: 0,
+                    }
+                }
+                Move::Place(posn) => {
+                    let flip_counts = board.flip_counts();
+                    let (best, eval_before) =
+                        search::alphabeta_root(board, depth, heuristic, context);
+                    let eval_after = search::alphabeta(
+                        after,
+                        depth - 1,
+                        SCORE_LOSS,
+                        SCORE_WIN,
+                        heuristic,
+                        context,
+                        1,
+                    );
+                    let delta = match board.turn {
+                        Color::White => eval_before.saturating_sub(eval_after),
+                        Color::Black => eval_after.saturating_sub(eval_before),
+                    };
+                    PlyAnalysis {
+                        ply: i + 1,
+                        mover: board.turn,
+                        played: *played,
+                        best: Move::Place(best),
+                        eval_before,
+                        eval_after,
+                        delta,
+                        flips: board.flipped_count(posn),
+                        max_flips: flip_counts
+                            .iter()
+                            .map(|(_, count)| *count)
+                            .max()
+                            .unwrap_or(0),
+                    }
+                }
+            }
+        })
+        .collect()
+}
+
+/// Show forced wins and losses by their final margin and how far off they are, rather than by
+/// their raw scores
+pub fn format_eval(eval: i32, board: &Board) -> String {
+    match (decode_terminal(eval), mate_distance(eval, board)) {
+        (Some((_, margin)), Some(distance)) => {
+            let winner = if margin > 0 { "W" } else { "B" };
+            let plural = if distance == 1 { "ply" } else { "plies" };
+            format!("{}+{} in {} {}", winner, margin.abs(), distance, plural)
+        }
+        _ => eval.to_string(),
+    }
+}
+
+/// The best move and score at each of depths, as (depth, best move, score) in the order given.
+/// It's one iterative deepening search to the deepest depth, so it costs little more than that
+/// search alone. The board must have a legal move
+pub fn evaluate_at_depths(
+    board: &Board,
+    depths: &[i32],
+    heuristic: HeuristicFn,
+) -> Vec<(i32, Posn, i32)> {
+    let options = SearchOptions {
+        max_depth: depths.iter().copied().max().unwrap_or(1),
+        heuristic,
+        ..SearchOptions::default()
+    };
+    let mut by_depth = vec![];
+    iterative_deepening_with(
+        board,
+        &options,
+        &mut SearchStats::default(),
+        |depth, (posn, score)| by_depth.push((depth, posn, score)),
+    );
+    depths
+        .iter()
+        .filter_map(|depth| by_depth.iter().find(|(d, _, _)| d == depth).copied())
+        .collect()
+}
+
+/// A table of evaluate_at_depths results, flagging each depth whose preferred move differs from
+/// the depth before it
+pub fn format_depth_table(board: &Board, rows: &[(i32, Posn, i32)]) -> String {
+    let mut table = format!("{:>5} {:>5} {:>14}\n", "depth", "move", "score");
+    let mut previous: Option<Posn> = None;
+    for (depth, posn, score) in rows {
+        let changed = match previous {
+            Some(prev) if prev.to_tuple() != posn.to_tuple() => "  <- changed",
+            _ => "",
+        };
+        table.push_str(&format!(
+            "{:>5} {:>5} {:>14}{}\n",
+            depth,
+            posn.to_string(),
+            format_eval(*score, board),
+            changed
+        ));
+        previous = Some(*posn);
+    }
+    table
+}
+
+/// One block character per ply, scaled between the lowest and highest evaluation in the game
+pub fn sparkline(evals: &[i32], style: RenderStyle) -> String {
+    const CLAMP: i32 = 100;
+    let blocks = match style {
+        RenderStyle::Unicode | RenderStyle::Ansi => ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'],
+        RenderStyle::Ascii => ['_', '.', ':', '-', '=', '+', '*', '#'],
+    };
+
+    let clamped: Vec<i32> = evals.iter().map(|e| (*e).clamp(-CLAMP, CLAMP)).collect();
+    let min = clamped.iter().copied().min().unwrap_or(0);
+    let max = clamped.iter().copied().max().unwrap_or(0);
+    let range = (max - min).max(1);
+
+    clamped
+        .iter()
+        .map(|e| blocks[((e - min) * (blocks.len() as i32 - 1) / range) as usize])
+        .collect()
+}
+
+/// The table of plies, blunders flagged, then a sparkline of the evaluation before each ply
+pub fn format_analysis(replay: &Replay, rows: &[PlyAnalysis], blunder_threshold: i32) -> String {
+    let mut table = String::new();
+    // Writing to a String can't fail
+    let _ = writeln!(
+        table,
+        "{:>4} {:>6} {:>6} {:>6} {:>14} {:>14} {:>8} {:>6}",
+        "ply", "color", "played", "best", "before", "after", "delta", "flips"
+    );
+    for row in rows {
+        let flag = if row.delta > blunder_threshold {
+            "  ?? blunder"
+        } else {
+            ""
+        };
+        let _ = writeln!(
+            table,
+            "{:>4} {:>6} {:>6} {:>6} {:>14} {:>14} {:>8} {:>6}{}",
+            row.ply,
+            row.mover.to_string(),
+            row.played.to_string(),
+            row.best.to_string(),
+            format_eval(row.eval_before, &replay.boards[row.ply - 1]),
+            format_eval(row.eval_after, &replay.boards[row.ply]),
+            row.delta,
+            format!("{}/{}", row.flips, row.max_flips),
+            flag
+        );
+    }
+
+    let evals: Vec<i32> = rows.iter().map(|row| row.eval_before).collect();
+    let _ = writeln!(
+        table,
+        "Evaluation: {}",
+        sparkline(&evals, RenderStyle::current())
+    );
+    table
+}
+
+/// Width of each bucket of final disc differences in disc_histogram
+#[cfg(feature = "sim")]
+const HISTOGRAM_BUCKET: i32 = 4;
+
+/// A text histogram of final disc differences (White minus Black) over many games, with bars
+/// scaled to fit in width columns, followed by the mean, median and standard deviation of these
+/// games alone, so a single game spreads by 0. Rows holding draws or wipeouts (a side left with no
+/// discs) are annotated, since their scores alone don't tell them apart from close or lopsided
+/// games
+#[cfg(feature = "sim")]
+pub fn disc_histogram(finals: &[Board], width: usize, style: RenderStyle) -> String {
+    if finals.is_empty() {
+        return "No games\n".to_string();
+    }
+    let scores: Vec<i32> = finals.iter().map(|board| board.score()).collect();
+    let wiped_out = |board: &Board| {
+        board.count_color_pieces(Color::Black) == 0 || board.count_color_pieces(Color::White) == 0
+    };
+
+    // -64 to 64 in buckets of 4, with 64 itself counted in the top bucket
+    let buckets = (128 / HISTOGRAM_BUCKET) as usize;
+    let bucket_of = |score: i32| (((score + 64) / HISTOGRAM_BUCKET) as usize).min(buckets - 1);
+    let mut counts = vec![0; buckets];
+    let mut draws = vec![0; buckets];
+    let mut wipeouts = vec![0; buckets];
+    for (board, score) in finals.iter().zip(&scores) {
+        let bucket = bucket_of(*score);
+        counts[bucket] += 1;
+        if *score == 0 {
+            draws[bucket] += 1;
+        }
+        if wiped_out(board) {
+            wipeouts[bucket] += 1;
+        }
+    }
+
+    // The "-64..-61 | " label takes 11 columns, and the count after the bar up to 6 more
+    let bar_room = width.saturating_sub(11 + 6).max(1);
+    let most = *counts.iter().max().unwrap();
+    let mut histogram = String::new();
+    for bucket in 0..buckets {
+        let low = bucket as i32 * HISTOGRAM_BUCKET - 64;
+        let high = if bucket == buckets - 1 {
+            64
+        } else {
+            low + HISTOGRAM_BUCKET - 1
+        };
+        let bar = style
+            .bar()
+            .to_string()
+            .repeat(counts[bucket] * bar_room / most);
+        // Writing to a String can't fail
+        let _ = write!(
+            histogram,
+            "{:>4}..{:<3} | {} {}",
+            low, high, bar, counts[bucket]
+        );
+        if draws[bucket] > 0 {
+            let _ = write!(histogram, " ({} draws)", draws[bucket]);
+        }
+        if wipeouts[bucket] > 0 {
+            let _ = write!(histogram, " ({} wipeouts)", wipeouts[bucket]);
+        }
+        histogram.push('\n');
+    }
+
+    let scores: Vec<f64> = scores.iter().map(|score| *score as f64).collect();
+    let median = Data::new(scores.clone()).median();
+    let _ = writeln!(
+        histogram,
+        "Mean {:.1}, median {:.1}, std dev {:.1}",
+        scores.iter().mean(),
+        median,
+        scores.iter().population_std_dev()
+    );
+    histogram
+}
+
+/// Who owned each square over a game: for every square, the plies at which it changed hands and
+/// its new owner. Discs on the starting board get an event at ply 0, and a pass changes nothing
+pub struct OwnershipTimeline {
+    /// Indexed by Posn::index, each square's (ply, owner) events in the order they happened
+    pub squares: Vec<Vec<(usize, Color)>>,
+}
+
+impl OwnershipTimeline {
+    /// Diff each position of the replay against the one before it
+    pub fn from_replay(replay: &Replay) -> Self {
+        let mut squares = vec![vec![]; ROWS * COLS];
+        let mut previous: Option<&Board> = None;
+        for (ply, board) in replay.boards.iter().enumerate() {
+            for posn in POSNS {
+                let owner = board.piece_at(&posn);
+                if previous.map(|before| before.piece_at(&posn)) == Some(owner) {
+                    continue;
+                }
+                if let Square::Occupied(color) = owner {
+                    squares[posn.index()].push((ply, color));
+                }
+            }
+            previous = Some(board);
+        }
+        Self { squares }
+    }
+
+    /// How often posn's disc was turned over, not counting its placement
+    pub fn times_flipped(&self, posn: Posn) -> usize {
+        self.squares[posn.index()].len().saturating_sub(1)
+    }
+
+    /// Every flip in the game, which is the sum of the discs each move flipped
+    pub fn total_flips(&self) -> usize {
+        POSNS.into_iter().map(|posn| self.times_flipped(posn)).sum()
+    }
+
+    /// The board with each square's flip count, '.' for squares never played on. Columns are as
+    /// wide as the largest count
+    pub fn flip_count_board(&self) -> String {
+        let cells: Vec<String> = POSNS
+            .into_iter()
+            .map(|posn| {
+                if self.squares[posn.index()].is_empty() {
+                    ".".to_string()
+                } else {
+                    self.times_flipped(posn).to_string()
+                }
+            })
+            .collect();
+        let width = cells.iter().map(String::len).max().unwrap_or(1);
+        let mut board = " ".to_string();
+        for col in 0..COLS {
+            let _ = write!(board, " {:>width$}", (b'a' + col as u8) as char);
+        }
+        board.push('\n');
+        for (row, cells) in cells.chunks(COLS).enumerate() {
+            let _ = write!(board, "{}", row + 1);
+            for cell in cells {
+                let _ = write!(board, " {:>width$}", cell);
+            }
+            board.push('\n');
+        }
+        board
+    }
+
+    /// One "square,ply,owner" line per event after a header, in the order they happened
+    pub fn to_csv(&self) -> String {
+        let mut events: Vec<(usize, Posn, Color)> = POSNS
+            .into_iter()
+            .flat_map(|posn| {
+                self.squares[posn.index()]
+                    .iter()
+                    .map(move |(ply, owner)| (*ply, posn, *owner))
+            })
+            .collect();
+        events.sort_by_key(|(ply, posn, _)| (*ply, posn.index()));
+        let mut csv = "square,ply,owner\n".to_string();
+        for (ply, posn, owner) in events {
+            let _ = writeln!(csv, "{},{},{}", posn, ply, owner);
+        }
+        csv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::standard_heuristic;
+    use crate::tests::PASS_GAME;
+    use crate::transcript::GameRecord;
+
+    /// The histogram of no games says so. Identical scores, one game or five, fill one row's bar to
+    /// the width, leave the other 31 rows bare, and summarize with no spread. Draws and wipeouts are
+    /// annotated on their rows
+    #[cfg(feature = "sim")]
+    #[test]
+    fn disc_histogram_bars_and_summary() {
+        const WIDTH: usize = 40;
+        let histogram = |finals: &[Board]| disc_histogram(finals, WIDTH, RenderStyle::Ascii);
+        if histogram(&[]) != "No games\n" {
+            panic!("the histogram of no games is {:?}", histogram(&[]));
+        }
+        let full = |black: usize| {
+            Board::from_position_string(&format!(
+                "{}{} X",
+                "X".repeat(black),
+                "O".repeat(64 - black)
+            ))
+            .unwrap()
+        };
+        // 11 columns of label and 6 for the count leave 23 for the bar
+        let full_bar = "#".repeat(WIDTH - 17);
+        for games in [1, 5] {
+            let text = histogram(&vec![full(27); games]);
+            let rows: Vec<&str> = text.lines().collect();
+            let bare = rows.iter().filter(|row| row.ends_with("|  0")).count();
+            let expected = format!("   8..11  | {} {}", full_bar, games);
+            if rows.len() != 33
+                || !rows.contains(&expected.as_str())
+                || bare != 31
+                || rows[32] != "Mean 10.0, median 10.0, std dev 0.0"
+            {
+                panic!("{} games winning by 10 gave the histogram\n{}", games, text);
+            }
+        }
+        let wipeout =
+            Board::from_position_string(&format!("{}{} X", "O".repeat(10), "-".repeat(54)))
+                .unwrap();
+        let text = histogram(&[full(32), full(32), wipeout, full(27)]);
+        let annotated = |label: &str, note: &str| {
+            text.lines()
+                .any(|row| row.starts_with(label) && row.ends_with(note))
+        };
+        if !annotated("   0..3", " 2 (2 draws)") || !annotated("   8..11", " 2 (1 wipeouts)") {
+            panic!("draws and a wipeout gave the histogram\n{}", text);
+        }
+    }
+
+    /// analyze_game over PASS_GAME at depth 2: one row per ply, passes included as passes, each best
+    /// move legal in its position, and a table with a line per row between its header and sparkline
+    #[test]
+    fn analyze_game_has_a_row_per_ply() {
+        let replay = GameRecord::from_transcript(PASS_GAME)
+            .and_then(|record| record.replay())
+            .unwrap_or_else(|err| panic!("pass game didn't replay: {}", err));
+        let rows = analyze_game(
+            &replay,
+            2,
+            standard_heuristic,
+            &mut SearchContext::default(),
+        );
+        if rows.len() != replay.moves.len() {
+            panic!("{} rows for {} plies", rows.len(), replay.moves.len());
+        }
+        for (i, row) in rows.iter().enumerate() {
+            let board = &replay.boards[i];
+            let best_legal = match row.best {
+                Move::Place(posn) => board.is_legal(&posn),
+                Move::Pass => board.legal_moves().is_empty(),
+            };
+            if row.ply != i + 1
+                || row.played.to_string() != replay.moves[i].to_string()
+                || !best_legal
+            {
+                panic!(
+                    "row {} is ply {} playing {} with best move {}\n{}",
+                    i, row.ply, row.played, row.best, board
+                );
+            }
+        }
+        let table = format_analysis(&replay, &rows, 10);
+        let lines: Vec<&str> = table.lines().collect();
+        if lines.len() != rows.len() + 2
+            || !lines[0].trim_start().starts_with("ply")
+            || !lines[lines.len() - 1].starts_with("Evaluation: ")
+        {
+            panic!("analysis table:\n{}", table);
+        }
+    }
+
+    /// The ownership timeline of PASS_GAME starts from the four center discs at ply 0, and counts
+    /// exactly the discs its moves flipped
+    #[test]
+    fn ownership_timeline_counts_the_flips() {
+        let replay = GameRecord::from_transcript(PASS_GAME)
+            .and_then(|record| record.replay())
+            .unwrap_or_else(|err| panic!("pass game didn't replay: {}", err));
+        let timeline = OwnershipTimeline::from_replay(&replay);
+        for (square, color) in [
+            ("d4", Color::Black),
+            ("e4", Color::White),
+            ("d5", Color::White),
+            ("e5", Color::Black),
+        ] {
+            let posn = Posn::try_from_alphanumeric(square).unwrap();
+            if timeline.squares[posn.index()].first() != Some(&(0, color)) {
+                panic!(
+                    "{} starts with {:?}",
+                    square,
+                    timeline.squares[posn.index()].first()
+                );
+            }
+        }
+        let flipped: usize = replay
+            .boards
+            .iter()
+            .zip(&replay.moves)
+            .map(|(board, mv)| match mv {
+                Move::Place(posn) => board.potential_flipped_pieces(posn).len(),
+                Move::Pass => 0,
+            })
+            .sum();
+        if timeline.total_flips() != flipped {
+            panic!(
+                "timeline counts {} flips, the moves flipped {}",
+                timeline.total_flips(),
+                flipped
+            );
+        }
+        let events: usize = timeline.squares.iter().map(Vec::len).sum();
+        if timeline.to_csv().lines().count() != events + 1 {
+            panic!("timeline CSV:\n{}", timeline.to_csv());
+        }
+        let board = timeline.flip_count_board();
+        let widths: Vec<usize> = board.lines().map(|line| line.chars().count()).collect();
+        if widths.len() != 9 || widths.iter().any(|width| *width != widths[0]) {
+            panic!("flip count board isn't aligned:\n{}", board);
+        }
+    }
+}
@@ -0,0 +1,204 @@
+//! A tournament's results cut into consecutive blocks of games, to see whether the win rate drifts
+//! over a long run, as it may when one side is learning. Each block gets its own counts and
+//! credible interval, and a chi-square test of homogeneity across the blocks flags drift too large
+//! to be chance.
+
+use statrs::distribution::{Beta, ChiSquared, ContinuousCDF};
+
+/// Fewest games a block may hold; asking for more blocks than this allows gives fewer, larger ones
+pub const MIN_BLOCK_GAMES: usize = 10;
+
+/// p-value below which the blocks are reported as drifting
+pub const DRIFT_SIGNIFICANCE: f64 = 0.05;
+
+/// One block's results
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Block {
+    /// Index of the block's first game in the tournament
+    pub first: usize,
+    pub games: usize,
+    pub minimax_wins: usize,
+    pub standard_wins: usize,
+    pub ties: usize,
+    /// 90% credible interval on the standard agent's win rate in this block, from a Beta(2, 2)
+    /// prior like the tournament's
+    pub low: f64,
+    pub high: f64,
+}
+
+impl Block {
+    fn new(first: usize, results: &[Option<bool>]) -> Self {
+        let count = |result| results.iter().filter(|r| **r == result).count();
+        let (minimax_wins, standard_wins) = (count(Some(true)), count(Some(false)));
+        let posterior = Beta::new(2.0 + standard_wins as f64, 2.0 + minimax_wins as f64)
+            .expect("shapes of at least 2 are valid");
+        Self {
+            first,
+            games: results.len(),
+            minimax_wins,
+            standard_wins,
+            ties: count(None),
+            low: posterior.inverse_cdf(0.05),
+            high: posterior.inverse_cdf(0.95),
+        }
+    }
+
+    fn counts(&self) -> [usize; 3] {
+        [self.minimax_wins, self.standard_wins, self.ties]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockSummary {
+    pub blocks: Vec<Block>,
+    /// Pearson's statistic over the blocks by minimax win, standard win and tie
+    pub chi_square: f64,
+    pub degrees_of_freedom: usize,
+    /// None when there's nothing to compare: a single block, or only one kind of result
+    pub p_value: Option<f64>,
+}
+
+impl BlockSummary {
+    /// Whether the results differ between blocks more than chance would explain
+    pub fn drifting(&self) -> bool {
+        self.p_value.is_some_and(|p| p < DRIFT_SIGNIFICANCE)
+    }
+}
+
+/// Split results (per game in the order played: whether minimax won, None for a draw) into up to
+/// blocks consecutive blocks as even as can be, with the earlier ones a game longer where they
+/// can't all match. Blocks that would hold fewer than MIN_BLOCK_GAMES are merged by using fewer
+/// of them, down to a single block holding everything
+pub fn summarize(results: &[Option<bool>], blocks: usize) -> BlockSummary {
+    let count = blocks.min(results.len() / MIN_BLOCK_GAMES).max(1);
+    let (size, extra) = (results.len() / count, results.len() % count);
+    let mut first = 0;
+    let blocks: Vec<Block> = (0..count)
+        .map(|i| {
+            let len = size + usize::from(i < extra);
+            let block = Block::new(first, &results[first..first + len]);
+            first += len;
+            block
+        })
+        .collect();
+
+    // Columns no game fell into would have an expected count of 0, so leave them out
+    let totals: Vec<usize> = (0..3)
+        .map(|column| blocks.iter().map(|block| block.counts()[column]).sum())
+        .filter(|&total| total > 0)
+        .collect();
+    let columns: Vec<usize> = (0..3)
+        .filter(|&column| blocks.iter().any(|block| block.counts()[column] > 0))
+        .collect();
+    let games = results.len() as f64;
+    let chi_square = blocks
+        .iter()
+        .flat_map(|block| {
+            columns.iter().zip(&totals).map(move |(&column, &total)| {
+                let expected = block.games as f64 * total as f64 / games;
+                let observed = block.counts()[column] as f64;
+                (observed - expected).powi(2) / expected
+            })
+        })
+        .sum();
+    let degrees_of_freedom = (blocks.len() - 1) * columns.len().saturating_sub(1);
+    let p_value = (degrees_of_freedom > 0).then(|| {
+        let distribution =
+            ChiSquared::new(degrees_of_freedom as f64).expect("positive degrees of freedom");
+        distribution.sf(chi_square)
+    });
+    BlockSummary {
+        blocks,
+        chi_square,
+        degrees_of_freedom,
+        p_value,
+    }
+}
+
+/// The blocks as a table, one row each, then the homogeneity test's verdict
+pub fn table(summary: &BlockSummary) -> String {
+    let mut table = format!(
+        "{:>11}  {:>7}  {:>8}  {:>4}  {}\n",
+        "Games", "Minimax", "Standard", "Ties", "Standard win rate"
+    );
+    for block in &summary.blocks {
+        table.push_str(&format!(
+            "{:>11}  {:>7}  {:>8}  {:>4}  {:.1}%, {:.1}%\n",
+            format!("{}..{}", block.first + 1, block.first + block.games),
+            block.minimax_wins,
+            block.standard_wins,
+            block.ties,
+            block.low * 100.0,
+            block.high * 100.0
+        ));
+    }
+    match summary.p_value {
+        Some(p) => table.push_str(&format!(
+            "Chi-square {:.2} on {} degrees of freedom, p = {:.3}: {}\n",
+            summary.chi_square,
+            summary.degrees_of_freedom,
+            p,
+            if summary.drifting() {
+                "the results drift between blocks"
+            } else {
+                "no significant drift"
+            }
+        )),
+        None => table.push_str("Too few blocks or kinds of result to test for drift\n"),
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Block summaries of made-up results: a steady mix must split evenly with no drift, a run
+    /// where minimax goes from winning to losing must be flagged, and blocks too small to hold
+    /// MIN_BLOCK_GAMES must merge into fewer
+    #[test]
+    fn blocks_split_and_flag_drift() {
+        let steady: Vec<Option<bool>> = [Some(true), Some(false), None, Some(true)]
+            .into_iter()
+            .cycle()
+            .take(200)
+            .collect();
+        let summary = summarize(&steady, 4);
+        let sizes: Vec<usize> = summary.blocks.iter().map(|block| block.games).collect();
+        if sizes != [50, 50, 50, 50] || summary.drifting() || summary.degrees_of_freedom != 6 {
+            panic!("steady results gave {:?}", summary);
+        }
+
+        let drifting: Vec<Option<bool>> = (0..200)
+            .map(|i| Some(if i < 100 { i % 5 != 0 } else { i % 5 == 0 }))
+            .collect();
+        let summary = summarize(&drifting, 4);
+        if !summary.drifting() || summary.degrees_of_freedom != 3 {
+            panic!("drifting results weren't flagged: {:?}", summary);
+        }
+        let (first, last) = (summary.blocks[0], summary.blocks[3]);
+        if first.high >= last.low {
+            panic!(
+                "the first block's interval {:?} overlaps the last's {:?}",
+                (first.low, first.high),
+                (last.low, last.high)
+            );
+        }
+
+        let short = &drifting[..25];
+        let summary = summarize(short, 5);
+        let sizes: Vec<usize> = summary.blocks.iter().map(|block| block.games).collect();
+        let counted: usize = summary
+            .blocks
+            .iter()
+            .map(|block| block.minimax_wins + block.standard_wins + block.ties)
+            .sum();
+        if sizes != [13, 12] || counted != short.len() || summary.blocks[1].first != 13 {
+            panic!("25 games in 5 blocks gave {:?}", summary.blocks);
+        }
+        let single = summarize(&drifting[..5], 3);
+        if single.blocks.len() != 1 || single.p_value.is_some() {
+            panic!("5 games in 3 blocks gave {:?}", single);
+        }
+    }
+}
@@ -0,0 +1,279 @@
+//! Opening books built by search. Starting from the opening, every position up to a ply limit is
+//! searched, and the moves scoring within a margin of the best one are kept and expanded further.
+//! Positions are stored once per symmetry class, keyed by their canonical form, with each move
+//! written as it would be played on that canonical board. The book is saved as JSON.
+
+use std::collections::{BTreeMap, HashMap};
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{OthelloError, Result};
+use crate::stats::canonical_key;
+use crate::{weights, Agent, Board, Color, MinimaxAgent, Move, Posn};
+
+#[derive(Debug, Clone, Copy)]
+pub struct BookOptions {
+    /// Positions this many plies from the opening or fewer get entries
+    pub plies: usize,
+    /// Search depth for scoring each move
+    pub depth: i32,
+    /// Keep moves scoring at most this much worse than the best, for the side to move
+    pub margin: i32,
+}
+
+impl Default for BookOptions {
+    fn default() -> Self {
+        Self {
+            plies: 6,
+            depth: 5,
+            margin: 8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BookMove {
+    /// The square on the canonical board, like "e3"
+    pub square: String,
+    /// White positive, like every other score
+    pub score: i32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Book {
+    /// Canonical position key to its acceptable moves, best first
+    pub entries: BTreeMap<String, Vec<BookMove>>,
+}
+
+/// The symmetry that takes board to its canonical form, and the key the book files it under
+fn canonical(board: &Board) -> (usize, String) {
+    let (encoding, turn) = canonical_key(board);
+    let symmetry = (0..8)
+        .find(|symmetry| board.transformed(*symmetry).packed() == encoding)
+        .unwrap();
+    let side = match turn {
+        Color::Black => 'B',
+        Color::White => 'W',
+    };
+    (symmetry, format!("{:032x}-{}", encoding, side))
+}
+
+/// Score every legal move of board and keep those within margin of the best, best first
+fn acceptable_moves(board: &Board, options: &BookOptions) -> Vec<(Posn, i32)> {
+    let mut search = MinimaxAgent::new(options.depth, weights::engine_heuristic());
+    let ranked: Vec<(Posn, i32)> = search
+        .rank_moves(board)
+        .into_iter()
+        .filter_map(|(mv, score)| match mv {
+            Move::Place(posn) => Some((posn, score)),
+            Move::Pass => None,
+        })
+        .collect();
+    let Some(&(_, best)) = ranked.first() else {
+        return vec![];
+    };
+    ranked
+        .into_iter()
+        .filter(|(_, score)| match board.turn {
+            Color::White => best - score <= options.margin,
+            Color::Black => score - best <= options.margin,
+        })
+        .collect()
+}
+
+impl Book {
+    /// Search every position reachable through acceptable moves, one ply at a time, with each
+    /// ply's positions searched in parallel
+    pub fn build(options: &BookOptions) -> Self {
+        let mut book = Self::default();
+        let mut frontier = vec![Board::new()];
+        for _ in 0..=options.plies {
+            let searched: Vec<(Board, Vec<(Posn, i32)>)> = frontier
+                .into_par_iter()
+                .map(|board| {
+                    let moves = acceptable_moves(&board, options);
+                    (board, moves)
+                })
+                .collect();
+
+            let mut next: HashMap<String, Board> = HashMap::new();
+            for (board, moves) in searched {
+                let (symmetry, key) = canonical(&board);
+                let entry = moves
+                    .iter()
+                    .map(|(posn, score)| BookMove {
+                        square: posn.transformed(symmetry).to_string(),
+                        score: *score,
+                    })
+                    .collect();
+                book.entries.insert(key, entry);
+                for (posn, _) in moves {
+                    let mut child = board.play_move(&posn);
+                    if !child.is_over() && !child.has_legal_move(child.turn) {
+                        child = child.change_turn();
+                    }
+                    if !child.is_over() {
+                        next.entry(canonical(&child).1).or_insert(child);
+                    }
+                }
+            }
+            frontier = next.into_values().collect();
+        }
+        book
+    }
+
+    /// The book's moves for board, turned back from the canonical board's orientation onto
+    /// board's, best first. Empty if the book doesn't know the position
+    pub fn moves(&self, board: &Board) -> Vec<(Posn, i32)> {
+        let (symmetry, key) = canonical(board);
+        let Some(entry) = self.entries.get(&key) else {
+            return vec![];
+        };
+        entry
+            .iter()
+            .filter_map(|book_move| {
+                board
+                    .legal_moves()
+                    .into_iter()
+                    .find(|posn| posn.transformed(symmetry).to_string() == book_move.square)
+                    .map(|posn| (posn, book_move.score))
+            })
+            .collect()
+    }
+
+    pub fn load(path: &str) -> Result<Self> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: &str) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Plays the book's best move while the game is still in the book, and searches after that
+#[derive(Debug, Clone)]
+pub struct BookAgent {
+    pub book: Book,
+    pub fallback: MinimaxAgent,
+}
+
+impl Agent for BookAgent {
+    fn choose_move(&mut self, board: &Board) -> Posn {
+        match self.book.moves(board).first() {
+            Some((posn, _)) => *posn,
+            None => self.fallback.choose_move(board),
+        }
+    }
+}
+
+/// book build <book.json> [--plies N] [--depth N] [--margin N]
+pub fn command(args: &[String]) -> Result<()> {
+    let usage = || {
+        OthelloError::Usage(
+            "book build <book.json> [--plies N] [--depth N] [--margin N]".to_string(),
+        )
+    };
+    let (Some("build"), Some(path)) = (args.first().map(String::as_str), args.get(1)) else {
+        return Err(usage());
+    };
+    let mut options = BookOptions::default();
+    let mut flags = args[2..].iter();
+    while let Some(flag) = flags.next() {
+        let value = flags.next().ok_or_else(usage)?;
+        match flag.as_str() {
+            "--plies" => options.plies = value.parse()?,
+            "--depth" => options.depth = value.parse()?,
+            "--margin" => options.margin = value.parse()?,
+            _ => return Err(usage()),
+        }
+    }
+    let start = std::time::Instant::now();
+    let book = Book::build(&options);
+    book.save(path)?;
+    println!(
+        "Wrote {} positions to {} in {:?}",
+        book.entries.len(),
+        path,
+        start.elapsed()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::standard_heuristic;
+
+    /// A ply-2 book has the opening's four moves at one score, since they're all the same move up to
+    /// symmetry, and answers a position the same way whichever way round it's turned. BookAgent plays
+    /// from it, and searches once the game leaves it
+    #[test]
+    fn book_answers_up_to_symmetry() {
+        let options = BookOptions {
+            plies: 2,
+            depth: 2,
+            margin: 0,
+        };
+        let book = Book::build(&options);
+        let opening = Board::new();
+        let root = book.moves(&opening);
+        let mut squares: Vec<String> = root.iter().map(|(posn, _)| posn.to_string()).collect();
+        squares.sort();
+        if squares != ["c5", "d6", "e3", "f4"] || root.iter().any(|(_, score)| *score != root[0].1)
+        {
+            panic!("the book's opening moves are {:?}", root);
+        }
+
+        let text = serde_json::to_string(&book).unwrap_or_else(|err| panic!("{}", err));
+        let reloaded: Book = serde_json::from_str(&text).unwrap_or_else(|err| panic!("{}", err));
+        if reloaded != book {
+            panic!("the book changed going through JSON");
+        }
+
+        let after_e3 = opening.play_move(&Posn::try_from_alphanumeric("e3").unwrap());
+        for symmetry in 0..8 {
+            let turned = after_e3.transformed(symmetry);
+            let expected: Vec<(String, i32)> = book
+                .moves(&after_e3)
+                .iter()
+                .map(|(posn, score)| (posn.transformed(symmetry).to_string(), *score))
+                .collect();
+            let actual: Vec<(String, i32)> = book
+                .moves(&turned)
+                .iter()
+                .map(|(posn, score)| (posn.to_string(), *score))
+                .collect();
+            if actual.is_empty() || actual != expected {
+                panic!(
+                    "after e3 under symmetry {} the book gives {:?}, not {:?}",
+                    symmetry, actual, expected
+                );
+            }
+        }
+
+        // The book opponent plays the book's best move while it has one, then searches
+        let fallback = MinimaxAgent::new(2, standard_heuristic);
+        let mut agent = BookAgent {
+            book: book.clone(),
+            fallback: fallback.clone(),
+        };
+        let out_of_book = after_e3
+            .play_move(&Posn::try_from_alphanumeric("f5").unwrap())
+            .play_move(&Posn::try_from_alphanumeric("f6").unwrap());
+        let (in_book, searched) = (
+            agent.choose_move(&after_e3),
+            agent.choose_move(&out_of_book),
+        );
+        if in_book.index() != book.moves(&after_e3)[0].0.index()
+            || !book.moves(&out_of_book).is_empty()
+            || searched.index() != fallback.clone().choose_move(&out_of_book).index()
+        {
+            panic!(
+                "the book agent played {} in the book and {} out of it",
+                in_book, searched
+            );
+        }
+    }
+}
@@ -0,0 +1,419 @@
+//! Per-user settings, read at startup from config.toml in the platform's config directory (or in
+//! $OTHELLO_CONFIG_DIR when that's set). Command-line flags beat the file, and the file beats the
+//! built-in defaults. Keys the program doesn't know only draw a warning, so a misspelled or retired
+//! setting doesn't stop it from starting.
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::error::{OthelloError, Result};
+use crate::{Color, RenderStyle};
+
+pub const FILE_NAME: &str = "config.toml";
+
+/// Set to a directory to read and write the config file there instead
+pub const DIR_ENV: &str = "OTHELLO_CONFIG_DIR";
+
+/// Written by "config init": every setting, commented out at its default
+pub const TEMPLATE: &str = r#"# Othello settings. Uncomment a line to change it; command-line flags still win.
+
+[play]
+# Computer opponent for the interactive game: "none", "random", "greedy", "mesh", "minimax",
+# "adaptive", which eases off when it's well ahead to keep the game close, "mobility", which
+# leaves you the fewest replies, or "book", which plays from the opening book below (--opponent)
+# opponent = "none"
+# Opening book written by "book build" for the book opponent (--book)
+# book = "book.json"
+# The computer's color when there is an opponent (--computer-color)
+# computer_color = "white"
+# How much worse than a draw the minimax opponent counts a draw, in its evaluation's units:
+# positive to play on for a win, negative to settle for a draw (--contempt)
+# contempt = 0
+# Search depth behind the hint command (--hint-depth)
+# hint_depth = 4
+# Start from an empty center under Reversi rules (--reversi)
+# reversi = false
+# Give White the first move (--white-first)
+# white_first = false
+# Mark the side to move's legal moves with * on the board (--show-moves)
+# show_moves = false
+# How far ahead by its evaluation the adaptive opponent gets before easing off
+# adaptive_threshold = 8
+# Places down its ranking of moves the adaptive opponent goes for each threshold it's ahead by
+# adaptive_aggressiveness = 1
+
+[simulate]
+# Games in the default tournament (tournament --games)
+# games = 100
+# Search depth for selfplay games
+# depth = 3
+# Seed for selfplay and tune, so runs repeat exactly
+# seed = 0
+
+[render]
+# "auto" follows OTHELLO_GLYPHS and the locale; "ascii", "unicode" or "ansi" (colored) force a
+# style (--ascii, --unicode, --ansi)
+# style = "auto"
+"#;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayConfig {
+    /// Computer opponent in the interactive game, by name, or "none"
+    pub opponent: String,
+    /// Opening book file for the book opponent
+    pub book: Option<String>,
+    /// The computer's color when there is an opponent
+    pub computer_color: Color,
+    /// How much worse than 0 the minimax opponent scores a draw
+    pub contempt: i32,
+    /// Search depth for the hint command
+    pub hint_depth: i32,
+    /// Start from an empty center under Reversi rules
+    pub reversi: bool,
+    /// Give White the first move
+    pub white_first: bool,
+    /// Mark legal moves on the board in the REPL
+    pub show_moves: bool,
+    /// How far ahead by its evaluation the adaptive opponent gets before easing off
+    pub adaptive_threshold: i32,
+    /// Places down its ranking the adaptive opponent goes for each threshold it's ahead by
+    pub adaptive_aggressiveness: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulateConfig {
+    /// Games in the default tournament
+    pub games: usize,
+    /// Search depth for selfplay
+    pub depth: i32,
+    /// Seed for selfplay and tune
+    pub seed: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderConfig {
+    /// The style to draw with, or None to detect one
+    pub style: Option<RenderStyle>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub play: PlayConfig,
+    pub simulate: SimulateConfig,
+    pub render: RenderConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            play: PlayConfig {
+                opponent: "none".to_string(),
+                book: None,
+                computer_color: Color::White,
+                contempt: 0,
+                hint_depth: 4,
+                reversi: false,
+                white_first: false,
+                show_moves: false,
+                adaptive_threshold: 8,
+                adaptive_aggressiveness: 1,
+            },
+            simulate: SimulateConfig {
+                games: 100,
+                depth: 3,
+                seed: 0,
+            },
+            render: RenderConfig { style: None },
+        }
+    }
+}
+
+/// The settings for this run, once main has read the file and flags
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// The settings for this run, or the defaults if main hasn't set them
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+/// Fix the settings for the rest of the run. Only the first call counts
+pub fn set(config: Config) {
+    let _ = CONFIG.set(config);
+}
+
+/// Where the config file lives: $OTHELLO_CONFIG_DIR, or an "othello" folder in the platform's
+/// config directory. Without the std feature only the variable is read
+pub fn dir() -> Option<PathBuf> {
+    match std::env::var_os(DIR_ENV) {
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+        #[cfg(feature = "std")]
+        _ => dirs::config_dir().map(|dir| dir.join("othello")),
+        #[cfg(not(feature = "std"))]
+        _ => None,
+    }
+}
+
+/// The file in dir(), with a warning for each key it doesn't know. Without a config directory or
+/// file, the defaults
+pub fn load() -> Result<(Config, Vec<String>)> {
+    match dir() {
+        Some(dir) => Config::load_from(&dir),
+        None => Ok((Config::default(), vec![])),
+    }
+}
+
+/// A setting's value as T, or a Parse error naming the setting and what it should have been
+fn value<'a, T>(
+    key: &str,
+    value: &'a toml::Value,
+    expected: &str,
+    read: impl Fn(&'a toml::Value) -> Option<T>,
+) -> Result<T> {
+    read(value).ok_or_else(|| OthelloError::Parse(format!("{} should be {}", key, expected)))
+}
+
+fn parse_style(name: &str) -> Result<Option<RenderStyle>> {
+    match name.to_lowercase().as_str() {
+        "auto" => Ok(None),
+        "ascii" => Ok(Some(RenderStyle::Ascii)),
+        "unicode" => Ok(Some(RenderStyle::Unicode)),
+        "ansi" => Ok(Some(RenderStyle::Ansi)),
+        _ => Err(OthelloError::Parse(format!(
+            "Unknown render style \"{}\" (expected auto, ascii, unicode or ansi)",
+            name
+        ))),
+    }
+}
+
+impl Config {
+    /// TOML text's settings over the defaults, with a warning for each key it doesn't know
+    pub fn from_toml(text: &str) -> Result<(Self, Vec<String>)> {
+        let table: toml::Table = toml::from_str(text)?;
+        let mut config = Self::default();
+        let mut warnings = vec![];
+        for (section, settings) in &table {
+            let Some(settings) = settings.as_table() else {
+                warnings.push(format!("Ignoring unknown setting \"{}\"", section));
+                continue;
+            };
+            for (name, setting) in settings {
+                let key = format!("{}.{}", section, name);
+                let integer = |expected| value(&key, setting, expected, toml::Value::as_integer);
+                let string = || value(&key, setting, "a string", toml::Value::as_str);
+                let boolean = || value(&key, setting, "true or false", toml::Value::as_bool);
+                match (section.as_str(), name.as_str()) {
+                    ("play", "opponent") => config.play.opponent = string()?.to_string(),
+                    ("play", "book") => config.play.book = Some(string()?.to_string()),
+                    ("play", "computer_color") => config.play.computer_color = string()?.parse()?,
+                    ("play", "contempt") => {
+                        config.play.contempt = integer("a whole number")? as i32
+                    }
+                    ("play", "hint_depth") => {
+                        config.play.hint_depth = integer("a whole number")? as i32
+                    }
+                    ("play", "reversi") => config.play.reversi = boolean()?,
+                    ("play", "white_first") => config.play.white_first = boolean()?,
+                    ("play", "show_moves") => config.play.show_moves = boolean()?,
+                    ("play", "adaptive_threshold") => {
+                        config.play.adaptive_threshold = integer("a whole number")? as i32
+                    }
+                    ("play", "adaptive_aggressiveness") => {
+                        config.play.adaptive_aggressiveness =
+                            integer("a whole number")?.max(0) as usize
+                    }
+                    ("simulate", "games") => {
+                        config.simulate.games = integer("a whole number")?.max(0) as usize
+                    }
+                    ("simulate", "depth") => {
+                        config.simulate.depth = integer("a whole number")? as i32
+                    }
+                    ("simulate", "seed") => {
+                        config.simulate.seed = integer("a whole number")?.max(0) as u64
+                    }
+                    ("render", "style") => config.render.style = parse_style(string()?)?,
+                    _ => warnings.push(format!("Ignoring unknown setting \"{}\"", key)),
+                }
+            }
+        }
+        Ok((config, warnings))
+    }
+
+    /// The config file in dir over the defaults, or just the defaults if there's no file
+    pub fn load_from(dir: &Path) -> Result<(Self, Vec<String>)> {
+        match std::fs::read_to_string(dir.join(FILE_NAME)) {
+            Ok(text) => Self::from_toml(&text),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok((Self::default(), vec![])),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Override settings with the flags for them, taking those flags out of args
+    pub fn apply_args(&mut self, args: &mut Vec<String>) -> Result<()> {
+        let mut rest = vec![];
+        let mut args_iter = std::mem::take(args).into_iter();
+        while let Some(arg) = args_iter.next() {
+            let mut flag_value = || {
+                args_iter
+                    .next()
+                    .ok_or_else(|| OthelloError::Usage(format!("{} <value>", arg)))
+            };
+            match arg.as_str() {
+                "--ascii" => self.render.style = Some(RenderStyle::Ascii),
+                "--unicode" => self.render.style = Some(RenderStyle::Unicode),
+                "--ansi" => self.render.style = Some(RenderStyle::Ansi),
+                "--reversi" => self.play.reversi = true,
+                "--white-first" => self.play.white_first = true,
+                "--show-moves" => self.play.show_moves = true,
+                "--opponent" => self.play.opponent = flag_value()?,
+                "--book" => self.play.book = Some(flag_value()?),
+                "--computer-color" => self.play.computer_color = flag_value()?.parse()?,
+                "--contempt" => self.play.contempt = flag_value()?.parse()?,
+                "--hint-depth" => self.play.hint_depth = flag_value()?.parse()?,
+                _ => rest.push(arg),
+            }
+        }
+        *args = rest;
+        Ok(())
+    }
+}
+
+/// config path | config init: show where the config file is read from, or write a commented
+/// template there
+pub fn command(args: &[String]) -> Result<()> {
+    let usage = || OthelloError::Usage("config path|init".to_string());
+    let path = dir()
+        .map(|dir| dir.join(FILE_NAME))
+        .ok_or_else(|| OthelloError::Parse(format!("No config directory; set {}", DIR_ENV)))?;
+    match args.first().map(String::as_str) {
+        Some("path") => {
+            let note = if path.exists() {
+                ""
+            } else {
+                " (not created yet)"
+            };
+            println!("{}{}", path.display(), note);
+            Ok(())
+        }
+        Some("init") => {
+            if path.exists() {
+                return Err(OthelloError::Usage(format!(
+                    "config init won't overwrite {}",
+                    path.display()
+                )));
+            }
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(&path, TEMPLATE)?;
+            println!("Wrote {}", path.display());
+            Ok(())
+        }
+        _ => Err(usage()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Settings layer as documented: defaults without a file, a partial file over the defaults with
+    /// unknown keys only warned about, a full file over everything, and flags over the file. Runs in
+    /// a scratch directory found through OTHELLO_CONFIG_DIR
+    #[test]
+    fn settings_layer_in_documented_order() {
+        let dir = std::env::temp_dir().join(format!("othello-config-check-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap_or_else(|err| panic!("{}", err));
+        std::env::set_var(DIR_ENV, &dir);
+        let result = check_config_layers(&dir);
+        std::env::remove_var(DIR_ENV);
+        let _ = std::fs::remove_dir_all(&dir);
+        result.unwrap_or_else(|violation| panic!("{}", violation));
+    }
+
+    fn check_config_layers(dir: &std::path::Path) -> std::result::Result<(), String> {
+        let write =
+            |text: &str| std::fs::write(dir.join(FILE_NAME), text).map_err(|err| err.to_string());
+        let load = || super::load().map_err(|err| err.to_string());
+        if super::dir().as_deref() != Some(dir) {
+            return Err(format!("{} didn't move the config directory", DIR_ENV));
+        }
+
+        if load()? != (Config::default(), vec![]) {
+            return Err("a missing config file didn't give the defaults".to_string());
+        }
+
+        write(TEMPLATE)?;
+        if load()? != (Config::default(), vec![]) {
+            return Err("the commented template didn't give the defaults".to_string());
+        }
+
+        write("[play]\nhint_depth = 6\n\n[simulate]\ngames = 10\nbogus = 1\n")?;
+        let (partial, warnings) = load()?;
+        let mut expected = Config::default();
+        expected.play.hint_depth = 6;
+        expected.simulate.games = 10;
+        if partial != expected || warnings.len() != 1 || !warnings[0].contains("simulate.bogus") {
+            return Err(format!(
+                "partial file gave {:?} with warnings {:?}",
+                partial, warnings
+            ));
+        }
+
+        write(
+            "[play]\nopponent = \"mesh\"\nbook = \"book.json\"\ncomputer_color = \"black\"\ncontempt = 5\nhint_depth = 2\nreversi = true\n\
+             white_first = true\nadaptive_threshold = 4\nadaptive_aggressiveness = 2\n[simulate]\ngames = 7\ndepth = 5\nseed = 9\n[render]\nstyle = \"ascii\"\n",
+        )?;
+        let (mut full, warnings) = load()?;
+        let mut expected = Config::default();
+        expected.play.opponent = "mesh".to_string();
+        expected.play.book = Some("book.json".to_string());
+        expected.play.computer_color = Color::Black;
+        expected.play.contempt = 5;
+        expected.play.hint_depth = 2;
+        expected.play.reversi = true;
+        expected.play.white_first = true;
+        expected.play.adaptive_threshold = 4;
+        expected.play.adaptive_aggressiveness = 2;
+        expected.simulate = SimulateConfig {
+            games: 7,
+            depth: 5,
+            seed: 9,
+        };
+        expected.render.style = Some(RenderStyle::Ascii);
+        if full != expected || !warnings.is_empty() {
+            return Err(format!(
+                "full file gave {:?} with warnings {:?}",
+                full, warnings
+            ));
+        }
+
+        let mut args: Vec<String> = [
+            "stats",
+            "--hint-depth",
+            "8",
+            "--unicode",
+            "--opponent",
+            "greedy",
+            "--book",
+            "openings.json",
+            "--contempt",
+            "-2",
+        ]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+        full.apply_args(&mut args).map_err(|err| err.to_string())?;
+        expected.play.hint_depth = 8;
+        expected.play.opponent = "greedy".to_string();
+        expected.play.book = Some("openings.json".to_string());
+        expected.play.contempt = -2;
+        expected.render.style = Some(RenderStyle::Unicode);
+        if full != expected || args != ["stats"] {
+            return Err(format!(
+                "flags over the full file gave {:?}, leaving {:?}",
+                full, args
+            ));
+        }
+        Ok(())
+    }
+}
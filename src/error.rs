@@ -0,0 +1,78 @@
+//! The error type shared by every fallible part of the program.
+
+use std::fmt::Display;
+
+pub enum OthelloError {
+    Io(std::io::Error),
+    /// A distribution was given invalid parameters: the tournament's Beta posterior or self-play's
+    /// Dirichlet noise
+    #[cfg(feature = "sim")]
+    Stats(statrs::StatsError),
+    /// Input that couldn't be understood: a move, transcript, position, or argument
+    Parse(String),
+    /// A move that isn't legal in the position it was played in
+    IllegalMove(String),
+    /// The network peer broke the protocol, reported an error, or disconnected
+    Protocol(String),
+    /// A command was run without the arguments it needs
+    Usage(String),
+    /// The rules engine broke one of its own invariants
+    InvariantViolation(String),
+}
+
+pub type Result<T> = std::result::Result<T, OthelloError>;
+
+impl Display for OthelloError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OthelloError::Io(err) => write!(f, "I/O error: {}", err),
+            #[cfg(feature = "sim")]
+            OthelloError::Stats(err) => write!(f, "Statistics error: {}", err),
+            OthelloError::Parse(msg)
+            | OthelloError::IllegalMove(msg)
+            | OthelloError::Protocol(msg) => write!(f, "{}", msg),
+            OthelloError::Usage(usage) => write!(f, "Usage: {}", usage),
+            OthelloError::InvariantViolation(msg) => write!(f, "Invariant violated: {}", msg),
+        }
+    }
+}
+
+// main returns this error, and Rust reports a failed main with Debug, so show the message
+impl std::fmt::Debug for OthelloError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl std::error::Error for OthelloError {}
+
+impl From<std::io::Error> for OthelloError {
+    fn from(err: std::io::Error) -> Self {
+        OthelloError::Io(err)
+    }
+}
+
+#[cfg(feature = "sim")]
+impl From<statrs::StatsError> for OthelloError {
+    fn from(err: statrs::StatsError) -> Self {
+        OthelloError::Stats(err)
+    }
+}
+
+impl From<std::num::ParseIntError> for OthelloError {
+    fn from(err: std::num::ParseIntError) -> Self {
+        OthelloError::Parse(format!("Expected a number: {}", err))
+    }
+}
+
+impl From<serde_json::Error> for OthelloError {
+    fn from(err: serde_json::Error) -> Self {
+        OthelloError::Parse(format!("Invalid JSON: {}", err))
+    }
+}
+
+impl From<toml::de::Error> for OthelloError {
+    fn from(err: toml::de::Error) -> Self {
+        OthelloError::Parse(format!("Invalid TOML: {}", err))
+    }
+}
@@ -0,0 +1,175 @@
+//! What happens during a game, as a stream of events. The game drivers report every turn, move,
+//! pass and ending to the sinks they're given, so a console, an animation or a log can each follow
+//! the game without working out for itself which discs a move flipped or who had to pass.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::error::Result;
+use crate::{show_board, Board, Color, Move, Posn};
+
+#[derive(Debug, Clone)]
+pub enum GameEvent {
+    /// color is to move and has at least one legal move
+    TurnStarted {
+        color: Color,
+        legal_moves: Vec<Posn>,
+    },
+    /// color placed a disc on posn, turning the flipped discs over
+    MovePlayed {
+        color: Color,
+        posn: Posn,
+        flipped: Vec<Posn>,
+    },
+    /// color had no legal move and the turn went to the opponent
+    Pass { color: Color },
+    /// Neither side can move, or early adjudication decided the game. The winner, or None for a
+    /// draw
+    GameOver { result: Option<Color> },
+}
+
+pub trait EventSink {
+    /// Called for each event, in the order they happen
+    fn on_event(&mut self, event: &GameEvent) -> Result<()>;
+}
+
+/// Send event to every sink, stopping at the first that fails
+pub fn emit(sinks: &mut [Box<dyn EventSink>], event: GameEvent) -> Result<()> {
+    for sink in sinks {
+        sink.on_event(&event)?;
+    }
+    Ok(())
+}
+
+/// The interactive game's output: each move and the board after it, and each pass. Follows the
+/// game by playing the events' moves on its own copy of the board
+#[derive(Debug, Clone)]
+pub struct ConsolePrinter {
+    board: Board,
+}
+
+impl ConsolePrinter {
+    /// A printer for a game starting from start
+    pub fn new(start: &Board) -> Self {
+        Self {
+            board: start.clone(),
+        }
+    }
+}
+
+impl EventSink for ConsolePrinter {
+    fn on_event(&mut self, event: &GameEvent) -> Result<()> {
+        match event {
+            GameEvent::MovePlayed { color, posn, .. } => {
+                println!("{} plays {}", color, posn);
+                self.board = self.board.with_turn(*color).play_move(posn);
+                show_board(&self.board);
+            }
+            GameEvent::Pass { color } => println!("{} has no legal moves and passes", color),
+            GameEvent::TurnStarted { .. } | GameEvent::GameOver { .. } => {}
+        }
+        Ok(())
+    }
+}
+
+/// Keeps the game's moves, passes included, for a transcript. Clones share one list, so hand the
+/// driver a clone and read the moves from the original afterwards
+#[derive(Debug, Clone, Default)]
+pub struct MoveLog(Rc<RefCell<Vec<Move>>>);
+
+impl MoveLog {
+    pub fn moves(&self) -> Vec<Move> {
+        self.0.borrow().clone()
+    }
+}
+
+impl EventSink for MoveLog {
+    fn on_event(&mut self, event: &GameEvent) -> Result<()> {
+        match event {
+            GameEvent::MovePlayed { posn, .. } => self.0.borrow_mut().push(Move::Place(*posn)),
+            GameEvent::Pass { .. } => self.0.borrow_mut().push(Move::Pass),
+            GameEvent::TurnStarted { .. } | GameEvent::GameOver { .. } => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{ScriptedAgent, PASS_GAME};
+    use crate::transcript::GameRecord;
+    use crate::{play_with_events, Move};
+    use std::sync::{Arc, Mutex};
+
+    /// Keeps every event it's sent, written out as text since Posn has no equality
+    struct RecordingSink(Arc<Mutex<Vec<String>>>);
+
+    impl EventSink for RecordingSink {
+        fn on_event(&mut self, event: &GameEvent) -> Result<()> {
+            let squares = |posns: &[Posn]| {
+                let names: Vec<String> = posns.iter().map(Posn::to_string).collect();
+                names.join(" ")
+            };
+            let line = match event {
+                GameEvent::TurnStarted { color, legal_moves } => {
+                    format!("{} to move: {}", color, squares(legal_moves))
+                }
+                GameEvent::MovePlayed {
+                    color,
+                    posn,
+                    flipped,
+                } => format!("{} plays {} flipping {}", color, posn, squares(flipped)),
+                GameEvent::Pass { color } => format!("{} passes", color),
+                GameEvent::GameOver { result } => format!("game over: {:?}", result),
+            };
+            self.0.lock().unwrap().push(line);
+            Ok(())
+        }
+    }
+
+    /// The last five plies of passes_write_as_dashes's game, two of them passes, played by scripted agents
+    /// must send exactly these events, ending in GameOver
+    #[test]
+    fn scripted_plies_send_their_events() {
+        let record = GameRecord::from_transcript(PASS_GAME)
+            .unwrap_or_else(|err| panic!("pass game didn't replay: {}", err));
+        let replay = record
+            .replay()
+            .unwrap_or_else(|err| panic!("pass game didn't replay: {}", err));
+        let start = replay.boards[55].clone();
+        let (mut black_moves, mut white_moves) = (vec![], vec![]);
+        for (board, mv) in replay.boards[55..].iter().zip(&replay.moves[55..]) {
+            if let Move::Place(posn) = mv {
+                match board.turn {
+                    Color::Black => black_moves.push(*posn),
+                    Color::White => white_moves.push(*posn),
+                }
+            }
+        }
+        let mut black = ScriptedAgent(black_moves.into_iter());
+        let mut white = ScriptedAgent(white_moves.into_iter());
+        let events = Arc::new(Mutex::new(vec![]));
+        let mut sinks: Vec<Box<dyn EventSink>> = vec![Box::new(RecordingSink(events.clone()))];
+        let end = play_with_events(start, &mut black, &mut white, &mut sinks)
+            .unwrap_or_else(|err| panic!("scripted game failed: {}", err));
+        if end.packed() != replay.final_board().packed() {
+            panic!("scripted game ended at\n{}", end);
+        }
+        let events = events.lock().unwrap().clone();
+        let expected = [
+            "White to move: g8",
+            "White plays g8 flipping e8 f8",
+            "Black passes",
+            "White to move: a2",
+            "White plays a2 flipping b3 c4 d5 e6 f7",
+            "Black passes",
+            "White to move: a5",
+            "White plays a5 flipping b5 c5",
+            "game over: Some(Black)",
+        ];
+        if events != expected {
+            panic!("scripted game sent events {:#?}", events);
+        }
+    }
+}
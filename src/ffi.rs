@@ -0,0 +1,305 @@
+//! A C interface to the move generator and the minimax agent, for calling the engine from other
+//! languages such as Python through ctypes. Boards are opaque pointers made by othello_new and
+//! released with othello_free; squares are (row, col) pairs counted from 0 at a1.
+//!
+//! Every function checks its pointers for null and catches panics, returning a status code
+//! instead of unwinding into the caller.
+//!
+//! `cargo build --release --features ffi` builds them into target/release/libothello.so (or
+//! othello.dll, libothello.dylib), which ctypes.CDLL loads. Without the feature the library
+//! exports nothing.
+
+use std::os::raw::c_int;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::table::ONE_SHOT_MEGABYTES;
+use crate::{Agent, Board, MinimaxAgent, Posn, COLS, HEURISTICS, ROWS};
+
+pub const OTHELLO_OK: c_int = 0;
+/// A pointer argument was null
+pub const OTHELLO_NULL: c_int = -1;
+/// A row, column, depth or heuristic id out of range
+pub const OTHELLO_OUT_OF_RANGE: c_int = -2;
+/// The move isn't legal, or there's no legal move to find or pass to make
+pub const OTHELLO_ILLEGAL: c_int = -3;
+/// The engine panicked; the board may be left as it was before the call
+pub const OTHELLO_PANIC: c_int = -4;
+
+/// Run body, turning a panic into fallback
+fn guard<T>(fallback: T, body: impl FnOnce() -> T) -> T {
+    catch_unwind(AssertUnwindSafe(body)).unwrap_or(fallback)
+}
+
+/// The square at row and col, if they're on the board
+fn posn_at(row: c_int, col: c_int) -> Option<Posn> {
+    let (row, col) = (usize::try_from(row).ok()?, usize::try_from(col).ok()?);
+    (row < ROWS && col < COLS).then_some(Posn { row, col })
+}
+
+/// A new board at the Othello opening with Black to move, or null if it couldn't be made
+#[no_mangle]
+pub extern "C" fn othello_new() -> *mut Board {
+    guard(std::ptr::null_mut(), || {
+        Box::into_raw(Box::new(Board::new()))
+    })
+}
+
+/// Release a board from othello_new. Null is ignored
+///
+/// # Safety
+/// board must be null or a pointer from othello_new not already freed
+#[no_mangle]
+pub unsafe extern "C" fn othello_free(board: *mut Board) {
+    if !board.is_null() {
+        guard((), || drop(Box::from_raw(board)));
+    }
+}
+
+/// Write the side to move's legal moves to out as row, col byte pairs, as many as fit in cap
+/// bytes, and return how many legal moves there are in all. 0 for a null board
+///
+/// # Safety
+/// board must be null or a live board; out must be null or writable for cap bytes
+#[no_mangle]
+pub unsafe extern "C" fn othello_legal_moves(
+    board: *const Board,
+    out: *mut u8,
+    cap: usize,
+) -> usize {
+    let Some(board) = board.as_ref() else {
+        return 0;
+    };
+    guard(0, || {
+        let legal_moves = board.legal_moves();
+        if !out.is_null() {
+            let out = std::slice::from_raw_parts_mut(out, cap);
+            for (pair, posn) in out.chunks_exact_mut(2).zip(&legal_moves) {
+                pair[0] = posn.row as u8;
+                pair[1] = posn.col as u8;
+            }
+        }
+        legal_moves.len()
+    })
+}
+
+/// Play the side to move's disc at row, col
+///
+/// # Safety
+/// board must be null or a live board
+#[no_mangle]
+pub unsafe extern "C" fn othello_play(board: *mut Board, row: c_int, col: c_int) -> c_int {
+    let Some(board) = board.as_mut() else {
+        return OTHELLO_NULL;
+    };
+    guard(OTHELLO_PANIC, || {
+        let Some(posn) = posn_at(row, col) else {
+            return OTHELLO_OUT_OF_RANGE;
+        };
+        if !board.is_legal(&posn) {
+            return OTHELLO_ILLEGAL;
+        }
+        *board = board.play_move(&posn);
+        OTHELLO_OK
+    })
+}
+
+/// Pass the turn, which is only allowed when the side to move has no legal move and the game
+/// isn't over
+///
+/// # Safety
+/// board must be null or a live board
+#[no_mangle]
+pub unsafe extern "C" fn othello_pass(board: *mut Board) -> c_int {
+    let Some(board) = board.as_mut() else {
+        return OTHELLO_NULL;
+    };
+    guard(OTHELLO_PANIC, || {
+        if board.is_over() || !board.legal_moves().is_empty() {
+            return OTHELLO_ILLEGAL;
+        }
+        *board = board.change_turn();
+        OTHELLO_OK
+    })
+}
+
+/// Search depth plies with the heuristic at heuristic_id in HEURISTICS (0 standard, 1
+/// edge_corner) and write the minimax agent's move to out_row and out_col
+///
+/// # Safety
+/// board must be null or a live board; out_row and out_col must be null or writable
+#[no_mangle]
+pub unsafe extern "C" fn othello_best_move(
+    board: *const Board,
+    depth: c_int,
+    heuristic_id: c_int,
+    out_row: *mut c_int,
+    out_col: *mut c_int,
+) -> c_int {
+    let (Some(board), false, false) = (board.as_ref(), out_row.is_null(), out_col.is_null()) else {
+        return OTHELLO_NULL;
+    };
+    guard(OTHELLO_PANIC, || {
+        let heuristic = usize::try_from(heuristic_id)
+            .ok()
+            .and_then(|id| HEURISTICS.get(id));
+        let (Some((_, heuristic)), true) = (heuristic, depth >= 1) else {
+            return OTHELLO_OUT_OF_RANGE;
+        };
+        if board.legal_moves().is_empty() {
+            return OTHELLO_ILLEGAL;
+        }
+        let posn =
+            MinimaxAgent::with_table(depth, *heuristic, ONE_SHOT_MEGABYTES).choose_move(board);
+        *out_row = posn.row as c_int;
+        *out_col = posn.col as c_int;
+        OTHELLO_OK
+    })
+}
+
+/// Write the board as a NUL-terminated position string (64 squares of X, O and - from a1, a
+/// space, and X or O to move) to out, cut short to fit cap bytes. Returns the full string's
+/// length without the NUL, more than cap - 1 if it was cut short, or a negative status
+///
+/// # Safety
+/// board must be null or a live board; out must be null or writable for cap bytes
+#[no_mangle]
+pub unsafe extern "C" fn othello_board_string(
+    board: *const Board,
+    out: *mut u8,
+    cap: usize,
+) -> c_int {
+    let Some(board) = board.as_ref() else {
+        return OTHELLO_NULL;
+    };
+    if out.is_null() && cap > 0 {
+        return OTHELLO_NULL;
+    }
+    guard(OTHELLO_PANIC, || {
+        let position = board.to_position_string();
+        if cap > 0 {
+            let out = std::slice::from_raw_parts_mut(out, cap);
+            let len = position.len().min(cap - 1);
+            out[..len].copy_from_slice(&position.as_bytes()[..len]);
+            out[len] = 0;
+        }
+        position.len() as c_int
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edge_corner_heuristic;
+
+    /// The C interface driven through raw pointers as a C caller would: legal moves as row, col
+    /// pairs, cut short to fit, a legal and an illegal play, the board string in full and cut
+    /// short, the minimax agent's move, and null pointers and bad arguments refused with a status
+    #[test]
+    fn c_interface_through_raw_pointers() {
+        use std::ptr::{null, null_mut};
+
+        let board = othello_new();
+        if board.is_null() {
+            panic!("othello_new returned null");
+        }
+        // SAFETY: board is live until othello_free at the end, and every buffer is as long as the
+        // capacity passed with it
+        unsafe {
+            let mut moves = [0u8; 64];
+            let count = othello_legal_moves(board, moves.as_mut_ptr(), moves.len());
+            let expected: Vec<u8> = Board::new()
+                .legal_moves()
+                .iter()
+                .flat_map(|posn| [posn.row as u8, posn.col as u8])
+                .collect();
+            if count != expected.len() / 2 || moves[..expected.len()] != expected[..] {
+                panic!("legal moves {} {:?}", count, &moves[..8]);
+            }
+            let mut one = [9u8; 3];
+            if othello_legal_moves(board, one.as_mut_ptr(), one.len()) != count
+                || one != [expected[0], expected[1], 9]
+            {
+                panic!("legal moves into 3 bytes wrote {:?}", one);
+            }
+
+            let statuses = [
+                othello_play(board, 0, 0),
+                othello_play(board, 8, 0),
+                othello_play(board, -1, 3),
+                othello_pass(board),
+                othello_play(board, 2, 4),
+            ];
+            if statuses
+                != [
+                    OTHELLO_ILLEGAL,
+                    OTHELLO_OUT_OF_RANGE,
+                    OTHELLO_OUT_OF_RANGE,
+                    OTHELLO_ILLEGAL,
+                    OTHELLO_OK,
+                ]
+            {
+                panic!(
+                    "plays of a1, a9, out of range, pass, e3 gave {:?}",
+                    statuses
+                );
+            }
+
+            let after = Board::new().play_move(&Posn::try_from_alphanumeric("e3").unwrap());
+            let position = after.to_position_string();
+            let mut text = [0u8; 80];
+            let len = othello_board_string(board, text.as_mut_ptr(), text.len());
+            if len != position.len() as i32
+                || &text[..position.len() + 1] != format!("{}\0", position).as_bytes()
+            {
+                panic!("board string {} {:?}", len, &text[..]);
+            }
+            let mut short = [b'#'; 10];
+            let len = othello_board_string(board, short.as_mut_ptr(), short.len());
+            if len != position.len() as i32
+                || short[..9] != position.as_bytes()[..9]
+                || short[9] != 0
+            {
+                panic!("board string into 10 bytes {} {:?}", len, short);
+            }
+
+            let (mut row, mut col) = (-1, -1);
+            let status = othello_best_move(board, 3, 1, &mut row, &mut col);
+            let chosen = MinimaxAgent::new(3, edge_corner_heuristic).choose_move(&after);
+            if status != OTHELLO_OK || (row, col) != (chosen.row as i32, chosen.col as i32) {
+                panic!(
+                    "best move status {} at ({}, {}), but the agent plays {}",
+                    status, row, col, chosen
+                );
+            }
+            let refused = [
+                othello_best_move(board, 3, 2, &mut row, &mut col),
+                othello_best_move(board, 0, 0, &mut row, &mut col),
+                othello_best_move(board, 3, 0, null_mut(), &mut col),
+                othello_best_move(null(), 3, 0, &mut row, &mut col),
+                othello_play(null_mut(), 2, 3),
+                othello_pass(null_mut()),
+                othello_board_string(null(), text.as_mut_ptr(), text.len()),
+                othello_board_string(board, null_mut(), 4),
+            ];
+            if refused
+                != [
+                    OTHELLO_OUT_OF_RANGE,
+                    OTHELLO_OUT_OF_RANGE,
+                    OTHELLO_NULL,
+                    OTHELLO_NULL,
+                    OTHELLO_NULL,
+                    OTHELLO_NULL,
+                    OTHELLO_NULL,
+                    OTHELLO_NULL,
+                ]
+                || othello_legal_moves(null(), moves.as_mut_ptr(), moves.len()) != 0
+                || othello_legal_moves(board, null_mut(), 0) != after.legal_moves().len()
+            {
+                panic!("bad arguments gave {:?}", refused);
+            }
+
+            othello_free(board);
+            othello_free(null_mut());
+        }
+    }
+}
@@ -0,0 +1,596 @@
+//! Randomized rule checking: play many seeded random games and check the board's invariants after
+//! every ply. A failure reports the game's transcript so it can be replayed with --replay.
+
+use crate::error::{OthelloError, Result};
+use crate::rng::{MoveRng, SeededRng};
+use crate::transcript::{GameRecord, TranscriptStyle};
+use crate::weights::term_values;
+use crate::{
+    decode_terminal, random_agent_with, terminal_score, Board, Color, HeuristicFn, InferError,
+    Move, Posn, RenderStyle, Square, COLS, DIRS, HEURISTICS, POSNS, ROWS,
+};
+
+/// Games to play when OTHELLO_FUZZ_GAMES isn't set
+const DEFAULT_GAMES: usize = 1000;
+
+/// A heuristic must score the color-swapped board (discs and turn) as the exact negation
+pub fn check_eval_antisymmetric(
+    name: &str,
+    heuristic: HeuristicFn,
+    board: &Board,
+) -> std::result::Result<(), String> {
+    let (score, swapped) = (heuristic(board), heuristic(&board.color_swapped()));
+    if swapped != -score {
+        return Err(format!(
+            "{} heuristic scores {} but {} with colors swapped\n{}",
+            name, score, swapped, board
+        ));
+    }
+    Ok(())
+}
+
+/// A board with every square independently empty, Black or White and either side to move. Most
+/// can't arise in a game, which is the point: evaluation symmetry shouldn't depend on that
+pub(crate) fn arbitrary_board(rng: &mut SeededRng) -> Board {
+    let mut board = Board::new();
+    for posn in POSNS {
+        let square = match rng.index(3) {
+            0 => Square::Unoccupied,
+            1 => Color::Black.into(),
+            _ => Color::White.into(),
+        };
+        board.set_piece_at(&posn, square);
+    }
+    if rng.index(2) == 1 {
+        board = board.change_turn();
+    }
+    board
+}
+
+/// Random games the move-generation checks alone play, per game of the full checks. They're
+/// cheap, so they cover many more games
+const MOVEGEN_GAMES_PER_GAME: usize = 5;
+
+/// No game lasts longer: each placement fills a square, and passes never come twice in a row
+const MAX_PLIES: usize = 2 * ROWS * COLS;
+
+/// The discs a placement on posn must flip, found without the engine's own move generation: in
+/// each direction, the run of the opponent's discs next to posn if the mover's disc closes it
+fn bracketed_flips(board: &Board, posn: Posn) -> Vec<usize> {
+    let mut flips = vec![];
+    for dir in DIRS {
+        let mut run = vec![];
+        let mut next = posn.neighbor(dir);
+        while let Some(square) = next {
+            match board.piece_at(&square) {
+                Square::Occupied(color) if color == board.turn.opponent() => run.push(square),
+                Square::Occupied(_) => {
+                    flips.extend(run.iter().map(|square| square.index()));
+                    break;
+                }
+                Square::Unoccupied => break,
+            }
+            next = square.neighbor(dir);
+        }
+    }
+    flips.sort_unstable();
+    flips
+}
+
+/// Making each legal move in place and unmaking it, and then a move onto an occupied square,
+/// leaves the board exactly as it was, down to the start rule
+fn check_make_unmake(board: &Board) -> std::result::Result<(), String> {
+    let occupied = POSNS
+        .into_iter()
+        .find(|posn| board.piece_at(posn).is_occupied());
+    for posn in board.legal_moves().into_iter().chain(occupied) {
+        let mut made = board.clone();
+        let undo = made.apply_in_place(&posn);
+        if made.packed() != board.play_move(&posn).packed() || made.turn == board.turn {
+            return Err(format!(
+                "{} applied in place isn't play_move's position\n{}",
+                posn, board
+            ));
+        }
+        made.undo_move(&undo);
+        if made != *board || made.start_rule != board.start_rule {
+            return Err(format!(
+                "making and unmaking {} left\n{}\ninstead of\n{}",
+                posn, made, board
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// A placement adds one disc of the mover's and flips exactly the opponent's discs it brackets,
+/// so the mover gains one more than the opponent loses; a pass changes nothing but the turn. Every
+/// move also has to make and unmake cleanly
+fn check_move_generation(
+    before: &Board,
+    after: &Board,
+    mv: &Move,
+) -> std::result::Result<(), String> {
+    check_make_unmake(before)?;
+    let (mover, opponent) = (before.turn, before.turn.opponent());
+    let Move::Place(posn) = mv else {
+        if !before.legal_moves().is_empty() {
+            return Err(format!("{} passed with moves to play\n{}", mover, before));
+        }
+        if !before.diff(after).is_empty() {
+            return Err("a pass changed the board".to_string());
+        }
+        return Ok(());
+    };
+    let diff = before.diff(after);
+    if !diff.removed.is_empty() {
+        return Err(format!(
+            "{} playing {} removed {:?}",
+            mover, posn, diff.removed
+        ));
+    }
+    let mut flipped: Vec<usize> = diff
+        .flipped
+        .iter()
+        .map(|(square, _)| square.index())
+        .collect();
+    flipped.sort_unstable();
+    if flipped != bracketed_flips(before, *posn)
+        || diff
+            .flipped
+            .iter()
+            .any(|(square, color)| *color != mover || !before.piece_at(square).is_color(opponent))
+    {
+        return Err(format!(
+            "{} playing {} flipped {:?}, but it brackets {:?}\n{}",
+            mover,
+            posn,
+            flipped,
+            bracketed_flips(before, *posn),
+            before
+        ));
+    }
+    let count = |board: &Board, color| board.count_color_pieces(color);
+    if count(after, mover) != count(before, mover) + 1 + flipped.len()
+        || count(after, opponent) + flipped.len() != count(before, opponent)
+    {
+        return Err(format!(
+            "{} playing {} and flipping {} went from {}-{} to {}-{} discs",
+            mover,
+            posn,
+            flipped.len(),
+            count(before, mover),
+            count(before, opponent),
+            count(after, mover),
+            count(after, opponent)
+        ));
+    }
+    Ok(())
+}
+
+/// A random game from the opening with only the move-generation checks at each ply
+fn movegen_game(rng: &mut SeededRng) -> (Vec<Move>, std::result::Result<(), String>) {
+    let mut board = Board::new();
+    let mut moves = vec![];
+    while !board.is_over() {
+        if moves.len() == MAX_PLIES {
+            return (moves, Err(format!("no end after {} plies", MAX_PLIES)));
+        }
+        let mv = if board.legal_moves().is_empty() {
+            Move::Pass
+        } else {
+            Move::Place(random_agent_with(&board, rng))
+        };
+        let next = match mv {
+            Move::Place(posn) => board.play_move(&posn),
+            Move::Pass => board.change_turn(),
+        };
+        moves.push(mv);
+        if let Err(violation) = check_move_generation(&board, &next, &mv) {
+            return (moves, Err(violation));
+        }
+        board = next;
+    }
+    (moves, Ok(()))
+}
+
+/// Play games random games with movegen_game, stopping at the first that breaks an invariant
+fn check_movegen_games(games: usize, rng: &mut SeededRng) -> std::result::Result<(), String> {
+    for game in 0..games {
+        let (moves, result) = movegen_game(rng);
+        if let Err(violation) = result {
+            let transcript: Vec<String> = moves.iter().map(|mv| mv.to_string()).collect();
+            return Err(format!(
+                "Move generation game {}: {}\nTranscript: {}",
+                game,
+                violation,
+                transcript.join(" ")
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn check_ply(before: &Board, after: &Board, mv: &Move) -> std::result::Result<(), String> {
+    let mover = before.turn;
+    let discs = |board: &Board| {
+        board.count_color_pieces(Color::Black) + board.count_color_pieces(Color::White)
+    };
+
+    if let Some(posn) = before
+        .legal_moves()
+        .iter()
+        .find(|p| before.piece_at(p).is_occupied())
+    {
+        return Err(format!("legal_moves contains occupied square {}", posn));
+    }
+
+    check_move_generation(before, after, mv)?;
+    match mv {
+        Move::Pass => {
+            if !before.diff(after).is_empty() {
+                return Err("a pass changed the board".to_string());
+            }
+        }
+        Move::Place(posn) => {
+            if before
+                .dead_empties()
+                .iter()
+                .any(|dead| dead.to_tuple() == posn.to_tuple())
+            {
+                return Err(format!(
+                    "{} was played after dead_empties reported it",
+                    posn
+                ));
+            }
+            if discs(after) != discs(before) + 1 {
+                return Err(format!(
+                    "disc count went from {} to {}",
+                    discs(before),
+                    discs(after)
+                ));
+            }
+            let diff = before.diff(after);
+            let placed = matches!(
+                diff.added.as_slice(),
+                [(square, color)] if square.to_tuple() == posn.to_tuple() && *color == mover
+            );
+            if !placed || !diff.removed.is_empty() {
+                return Err(format!(
+                    "{} played {} but the board changed by {:?}",
+                    mover, posn, diff
+                ));
+            }
+            if let Some((square, color)) = diff.flipped.iter().find(|(_, color)| *color != mover) {
+                return Err(format!(
+                    "{} flipped to {} when {} played {}",
+                    square, color, mover, posn
+                ));
+            }
+        }
+    }
+
+    match before.infer_move(after) {
+        Ok(inferred) if inferred.to_string() == mv.to_string() => {}
+        Ok(inferred) => return Err(format!("{} was played but {} was inferred", mv, inferred)),
+        Err(err) => return Err(format!("{} was played but inference failed: {}", mv, err)),
+    }
+    // Discs never leave the board, and a pass hands the move to a side that can move, so no
+    // move leads back to the position before
+    match after.infer_move(before) {
+        Err(InferError::NoMove { .. }) => {}
+        Ok(inferred) => {
+            return Err(format!(
+                "{} was inferred from a position back to its past",
+                inferred
+            ))
+        }
+        Err(err) => return Err(format!("going back to the past gave {}", err)),
+    }
+
+    // The legal-move grid marks exactly the legal moves; row r + 1 of it is board row r
+    let grid = after.legal_move_grid(RenderStyle::Ascii);
+    let marked: Vec<String> = grid
+        .lines()
+        .skip(1)
+        .enumerate()
+        .flat_map(|(row, line)| {
+            line.chars()
+                .skip(1)
+                .enumerate()
+                .filter(|(_, ch)| *ch == '*')
+                .map(move |(col, _)| Posn { row, col }.to_string())
+        })
+        .collect();
+    let legal: Vec<String> = after.legal_moves().iter().map(Posn::to_string).collect();
+    if marked != legal {
+        return Err(format!(
+            "the legal-move grid marks {:?}, but the legal moves are {:?}",
+            marked, legal
+        ));
+    }
+
+    check_is_over(after)?;
+    check_flipped_pieces(after)?;
+
+    let (black, white) = after.mobilities();
+    let count = |color: Color| {
+        let board = if after.turn == color {
+            after.clone()
+        } else {
+            after.change_turn()
+        };
+        board.legal_moves().len()
+    };
+    if (black, white) != (count(Color::Black), count(Color::White)) {
+        return Err(format!(
+            "mobilities gave {:?}, but legal_moves gave {:?}",
+            (black, white),
+            (count(Color::Black), count(Color::White))
+        ));
+    }
+
+    for (name, heuristic) in HEURISTICS {
+        check_eval_antisymmetric(name, heuristic, after)?;
+    }
+
+    if after.is_over() {
+        let (score, swapped) = (
+            terminal_score(after),
+            terminal_score(&after.color_swapped()),
+        );
+        if swapped != -score {
+            return Err(format!(
+                "terminal score {} but {} with colors swapped",
+                score, swapped
+            ));
+        }
+        if score != 0 && decode_terminal(score).map(|(_, margin)| margin) != Some(after.score()) {
+            return Err(format!(
+                "terminal score {} decodes to {:?}, not the final margin {}",
+                score,
+                decode_terminal(score),
+                after.score()
+            ));
+        }
+        let wiped_out = after.count_color_pieces(Color::Black) == 0
+            || after.count_color_pieces(Color::White) == 0;
+        let no_moves =
+            after.legal_moves().is_empty() && after.change_turn().legal_moves().is_empty();
+        if !(wiped_out || no_moves) {
+            return Err("is_over with moves remaining".to_string());
+        }
+        let expected_winner = match after.score().cmp(&0) {
+            std::cmp::Ordering::Greater => Some(Color::White),
+            std::cmp::Ordering::Less => Some(Color::Black),
+            std::cmp::Ordering::Equal => None,
+        };
+        if after.winner() != expected_winner {
+            return Err(format!(
+                "winner {:?} disagrees with score {}",
+                after.winner(),
+                after.score()
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The short-circuiting is_over must agree with checking both sides' full move lists
+fn check_is_over(board: &Board) -> std::result::Result<(), String> {
+    let by_move_lists =
+        board.legal_moves().is_empty() && board.change_turn().legal_moves().is_empty();
+    if board.is_over() != by_move_lists {
+        return Err(format!(
+            "is_over says {} but the move lists say {}\n{}",
+            board.is_over(),
+            by_move_lists,
+            board
+        ));
+    }
+    Ok(())
+}
+
+/// Every square's flipped discs come in index order without repeats, match flip_count, and are
+/// empty just where the square isn't a legal move that flips something
+fn check_flipped_pieces(board: &Board) -> std::result::Result<(), String> {
+    for posn in POSNS {
+        let flipped = board.potential_flipped_pieces(&posn);
+        let should_flip = board.is_legal(&posn) && !board.is_placing_center();
+        if flipped.is_empty() == should_flip {
+            return Err(format!(
+                "{} flips {} discs but is_legal says {}\n{}",
+                posn,
+                flipped.len(),
+                board.is_legal(&posn),
+                board
+            ));
+        }
+        if flipped
+            .windows(2)
+            .any(|pair| pair[0].index() >= pair[1].index())
+        {
+            return Err(format!(
+                "{} flips {:?} out of order\n{}",
+                posn, flipped, board
+            ));
+        }
+        if flipped.len() != board.flipped_count(&posn) {
+            return Err(format!(
+                "{} flips {} discs but flip_count says {}\n{}",
+                posn,
+                flipped.len(),
+                board.flipped_count(&posn),
+                board
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Play one random game, returning the transcript and the first violation if there is one
+fn fuzz_game(rng: &mut SeededRng) -> (Vec<Move>, std::result::Result<(), String>) {
+    let mut board = Board::new();
+    let mut moves = vec![];
+    while !board.is_over() {
+        if moves.len() == MAX_PLIES {
+            return (moves, Err(format!("no end after {} plies", MAX_PLIES)));
+        }
+        let legal_moves = board.legal_moves();
+        let mv = if legal_moves.is_empty() {
+            Move::Pass
+        } else {
+            Move::Place(random_agent_with(&board, rng))
+        };
+        let next = match mv {
+            Move::Place(posn) => board.play_move(&posn),
+            Move::Pass => board.change_turn(),
+        };
+        moves.push(mv);
+        if let Err(violation) = check_ply(&board, &next, &mv) {
+            return (moves, Err(violation));
+        }
+        board = next;
+    }
+    (moves.clone(), check_transcripts(moves))
+}
+
+/// Both transcript styles and the notation must read back as the same game, passes included
+fn check_transcripts(moves: Vec<Move>) -> std::result::Result<(), String> {
+    let record = GameRecord::from_moves(moves);
+    for style in [TranscriptStyle::Dense, TranscriptStyle::Explicit] {
+        let text = record.to_transcript(style);
+        let parsed = GameRecord::from_transcript(&text)
+            .map_err(|err| format!("{:?} transcript didn't replay: {}", style, err))?;
+        if parsed.to_transcript(TranscriptStyle::Explicit)
+            != record.to_transcript(TranscriptStyle::Explicit)
+        {
+            return Err(format!(
+                "{:?} transcript {} read back differently",
+                style, text
+            ));
+        }
+    }
+    let notation = record.to_notation();
+    let tokens: Vec<&str> = notation.split(' ').collect();
+    let lined_up = tokens.len() == record.moves.len()
+        && tokens
+            .iter()
+            .zip(&record.moves)
+            .all(|(token, mv)| match mv {
+                Move::Place(posn) => *token == posn.to_string(),
+                Move::Pass => *token == "--",
+            });
+    if !lined_up {
+        return Err(format!("notation {} doesn't match the moves", notation));
+    }
+    let parsed = GameRecord::from_transcript(&notation)
+        .map_err(|err| format!("notation didn't replay: {}", err))?;
+    if parsed.to_notation() != notation {
+        return Err(format!("notation {} read back differently", notation));
+    }
+    Ok(())
+}
+
+/// Check OTHELLO_FUZZ_GAMES random set ups, arbitrary boards and random games (default 1000 each)
+/// seeded from OTHELLO_FUZZ_SEED (default 0)
+pub fn run() -> Result<()> {
+    let games = match std::env::var("OTHELLO_FUZZ_GAMES") {
+        Ok(games) => games.parse()?,
+        Err(_) => DEFAULT_GAMES,
+    };
+    let seed = match std::env::var("OTHELLO_FUZZ_SEED") {
+        Ok(seed) => seed.parse()?,
+        Err(_) => 0,
+    };
+    fuzz(games, seed)
+}
+
+/// games random set ups, arbitrary boards and random games from seed, each checked against the
+/// board's invariants
+fn fuzz(games: usize, seed: u64) -> Result<()> {
+    let mut rng = SeededRng::new(seed);
+    for game in 0..games {
+        let board = Board::random_set_up_with(&mut rng);
+        if let Err(violation) = board.check_set_up() {
+            return Err(OthelloError::InvariantViolation(format!(
+                "Set up {} (seed {}): {}\n{}",
+                game, seed, violation, board
+            )));
+        }
+    }
+    for i in 0..games {
+        let board = arbitrary_board(&mut rng);
+        for (name, heuristic) in HEURISTICS {
+            if let Err(violation) = check_eval_antisymmetric(name, heuristic, &board) {
+                return Err(OthelloError::InvariantViolation(format!(
+                    "Arbitrary board {} (seed {}): {}",
+                    i, seed, violation
+                )));
+            }
+        }
+        if let Err(violation) = check_is_over(&board).and(check_flipped_pieces(&board)) {
+            return Err(OthelloError::InvariantViolation(format!(
+                "Arbitrary board {} (seed {}): {}",
+                i, seed, violation
+            )));
+        }
+        let (terms, swapped) = (term_values(&board), term_values(&board.color_swapped()));
+        if terms
+            .iter()
+            .zip(swapped)
+            .any(|(term, swapped)| swapped != -term)
+        {
+            return Err(OthelloError::InvariantViolation(format!(
+                "Arbitrary board {} (seed {}): weighted terms {:?} but {:?} with colors swapped\n{}",
+                i, seed, terms, swapped, board
+            )));
+        }
+    }
+    for game in 0..games {
+        let (moves, result) = fuzz_game(&mut rng);
+        if let Err(violation) = result {
+            let transcript: Vec<String> = moves.iter().map(|mv| mv.to_string()).collect();
+            return Err(OthelloError::InvariantViolation(format!(
+                "Game {} (seed {}): {}\nTranscript: {}",
+                game,
+                seed,
+                violation,
+                transcript.join(" ")
+            )));
+        }
+    }
+    check_movegen_games(games * MOVEGEN_GAMES_PER_GAME, &mut rng)
+        .map_err(|violation| format!("{} (seed {})", violation, seed))
+        .map_err(OthelloError::InvariantViolation)?;
+    println!(
+        "{} random set ups, arbitrary boards and games, and {} move generation games passed \
+         every invariant",
+        games,
+        games * MOVEGEN_GAMES_PER_GAME
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Enough games to reach every invariant while keeping cargo test quick; othello fuzz plays
+    /// DEFAULT_GAMES
+    const TEST_GAMES: usize = 20;
+
+    /// Random games whose every ply goes through check_move_generation
+    const MOVEGEN_TEST_GAMES: usize = 3000;
+
+    #[test]
+    fn invariants_hold() {
+        fuzz(TEST_GAMES, 0).unwrap_or_else(|err| panic!("{}", err));
+    }
+
+    #[test]
+    fn move_generation_over_random_games() {
+        check_movegen_games(MOVEGEN_TEST_GAMES, &mut SeededRng::new(1))
+            .unwrap_or_else(|violation| panic!("{}", violation));
+    }
+}
@@ -0,0 +1,148 @@
+//! Where the interactive game reads its commands. Files, pipes and scripted input are read line by
+//! line as they come; a terminal gets line editing, history and tab completion of commands and
+//! legal moves when built with the readline feature.
+
+use std::io::{self, BufRead};
+
+use crate::Board;
+
+/// The REPL's commands, offered by tab completion
+#[cfg(feature = "readline")]
+pub const COMMANDS: [&str; 13] = [
+    "moves",
+    "turn",
+    "setturn black",
+    "setturn white",
+    "counts",
+    "info",
+    "hint",
+    "hint deep",
+    "hint quick",
+    "hash",
+    "export ",
+    "save ",
+    "quit",
+];
+
+pub trait LineInput {
+    /// Read the next line, asked for while board is on screen, into buf as BufRead::read_line
+    /// does: 0 means the input has run out
+    fn read_line_for(&mut self, board: &Board, buf: &mut String) -> io::Result<usize>;
+}
+
+impl<R: BufRead> LineInput for R {
+    fn read_line_for(&mut self, _board: &Board, buf: &mut String) -> io::Result<usize> {
+        self.read_line(buf)
+    }
+}
+
+/// Standard input: the line editor when it's a terminal and the readline feature is on, or plain
+/// lines otherwise
+pub fn stdin() -> Box<dyn LineInput> {
+    #[cfg(feature = "readline")]
+    if std::io::IsTerminal::is_terminal(&std::io::stdin()) {
+        if let Ok(terminal) = terminal::Terminal::new() {
+            return Box::new(terminal);
+        }
+    }
+    Box::new(std::io::stdin().lock())
+}
+
+#[cfg(feature = "readline")]
+mod terminal {
+    use std::io;
+
+    use rustyline::completion::Completer;
+    use rustyline::error::ReadlineError;
+    use rustyline::highlight::Highlighter;
+    use rustyline::hint::Hinter;
+    use rustyline::history::DefaultHistory;
+    use rustyline::validate::Validator;
+    use rustyline::{CompletionType, Config, Context, Editor, Helper};
+
+    use super::{LineInput, COMMANDS};
+    use crate::{Board, Posn};
+
+    /// Completes the start of a line to a command or one of the current position's legal moves
+    #[derive(Default)]
+    struct Completions {
+        moves: Vec<String>,
+    }
+
+    impl Completer for Completions {
+        type Candidate = String;
+
+        fn complete(
+            &self,
+            line: &str,
+            pos: usize,
+            _ctx: &Context<'_>,
+        ) -> rustyline::Result<(usize, Vec<String>)> {
+            let typed = line[..pos].to_lowercase();
+            let candidates = COMMANDS
+                .iter()
+                .map(|command| command.to_string())
+                .chain(self.moves.iter().cloned())
+                .filter(|candidate| candidate.starts_with(&typed))
+                .collect();
+            Ok((0, candidates))
+        }
+    }
+
+    impl Hinter for Completions {
+        type Hint = String;
+    }
+
+    impl Highlighter for Completions {}
+
+    impl Validator for Completions {}
+
+    impl Helper for Completions {}
+
+    /// A terminal with line editing, up and down through earlier lines, and tab completion.
+    /// Ctrl-C asks before quitting instead of killing the game
+    pub struct Terminal {
+        editor: Editor<Completions, DefaultHistory>,
+    }
+
+    impl Terminal {
+        pub fn new() -> rustyline::Result<Self> {
+            // Tab completes as far as the candidates agree, and a second tab lists them
+            let config = Config::builder()
+                .completion_type(CompletionType::List)
+                .build();
+            let mut editor = Editor::with_config(config)?;
+            editor.set_helper(Some(Completions::default()));
+            Ok(Self { editor })
+        }
+    }
+
+    impl LineInput for Terminal {
+        fn read_line_for(&mut self, board: &Board, buf: &mut String) -> io::Result<usize> {
+            if let Some(completions) = self.editor.helper_mut() {
+                completions.moves = board.legal_moves().iter().map(Posn::to_string).collect();
+            }
+            loop {
+                let line = match self.editor.readline("> ") {
+                    Ok(line) => line,
+                    Err(ReadlineError::Interrupted) => match self.editor.readline("Quit? (y/n) ") {
+                        Ok(answer) if !answer.trim().to_lowercase().starts_with('y') => continue,
+                        // A second Ctrl-C is as good as yes
+                        Ok(_) | Err(ReadlineError::Interrupted) => "quit".to_string(),
+                        Err(ReadlineError::Eof) => return Ok(0),
+                        Err(err) => return Err(io::Error::other(err)),
+                    },
+                    Err(ReadlineError::Eof) => return Ok(0),
+                    Err(err) => return Err(io::Error::other(err)),
+                };
+                if !line.trim().is_empty() {
+                    // Failing to remember a line only costs the history
+                    let _ = self.editor.add_history_entry(line.as_str());
+                }
+                buf.push_str(&line);
+                buf.push('\n');
+                return Ok(line.len() + 1);
+            }
+        }
+    }
+}
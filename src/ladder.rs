@@ -0,0 +1,286 @@
+//! Ladder mode: play against progressively stronger computer opponents, moving up a level after
+//! a streak of wins and down after a streak of losses. Progress is kept in a JSON file.
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{OthelloError, Result};
+use crate::events::{ConsolePrinter, EventSink};
+use crate::input;
+use crate::{
+    edge_corner_heuristic, heuristic_agent, mesh_agent, minimax_agent, play_interactive,
+    random_agent, standard_heuristic, AgentFn, Board, Color,
+};
+
+/// Opponents from weakest to strongest
+const LEVELS: [(&str, AgentFn); 6] = [
+    ("random", random_agent),
+    ("greedy (standard)", |board| {
+        heuristic_agent(board, standard_heuristic)
+    }),
+    ("greedy (edge/corner)", |board| {
+        heuristic_agent(board, edge_corner_heuristic)
+    }),
+    ("mesh", mesh_agent),
+    ("minimax depth 2", |board| {
+        minimax_agent(board, 2, edge_corner_heuristic)
+    }),
+    ("minimax depth 4", |board| {
+        minimax_agent(board, 4, edge_corner_heuristic)
+    }),
+];
+
+const DEFAULT_STATE_PATH: &str = "ladder.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Win,
+    Loss,
+    Draw,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LadderConfig {
+    /// Consecutive wins needed to move up a level
+    pub promote_after: u32,
+    /// Consecutive losses that move you down a level
+    pub demote_after: u32,
+}
+
+impl Default for LadderConfig {
+    fn default() -> Self {
+        Self {
+            promote_after: 2,
+            demote_after: 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct LevelRecord {
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LadderState {
+    pub level: usize,
+    pub win_streak: u32,
+    pub loss_streak: u32,
+    /// Your record against each level, indexed like LEVELS
+    pub records: Vec<LevelRecord>,
+}
+
+impl Default for LadderState {
+    fn default() -> Self {
+        Self {
+            level: 0,
+            win_streak: 0,
+            loss_streak: 0,
+            records: vec![LevelRecord::default(); LEVELS.len()],
+        }
+    }
+}
+
+impl LadderState {
+    /// Load the saved ladder, starting over (with a warning) if it's missing or unreadable
+    pub fn load(path: &str) -> Self {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(_) => {
+                println!(
+                    "Warning: no ladder state at {}, starting from level 1",
+                    path
+                );
+                return Self::default();
+            }
+        };
+        match serde_json::from_str::<LadderState>(&text) {
+            Ok(state) if state.level < LEVELS.len() && state.records.len() == LEVELS.len() => state,
+            _ => {
+                println!(
+                    "Warning: ladder state at {} is corrupt, starting over",
+                    path
+                );
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn games_played(&self) -> u32 {
+        self.records
+            .iter()
+            .map(|record| record.wins + record.losses + record.draws)
+            .sum()
+    }
+
+    /// Record a game against the current level, then promote or demote if a streak is long enough
+    pub fn record(&mut self, outcome: Outcome, config: &LadderConfig) {
+        let record = &mut self.records[self.level];
+        match outcome {
+            Outcome::Win => {
+                record.wins += 1;
+                self.win_streak += 1;
+                self.loss_streak = 0;
+            }
+            Outcome::Loss => {
+                record.losses += 1;
+                self.loss_streak += 1;
+                self.win_streak = 0;
+            }
+            Outcome::Draw => {
+                record.draws += 1;
+                self.win_streak = 0;
+                self.loss_streak = 0;
+            }
+        }
+
+        if self.win_streak >= config.promote_after && self.level + 1 < LEVELS.len() {
+            self.level += 1;
+            self.win_streak = 0;
+        } else if self.loss_streak >= config.demote_after && self.level > 0 {
+            self.level -= 1;
+            self.loss_streak = 0;
+        }
+    }
+}
+
+/// ladder [--state path] [--promote N] [--demote N]
+pub fn run(args: &[String]) -> Result<()> {
+    let mut path = DEFAULT_STATE_PATH.to_string();
+    let mut config = LadderConfig::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or_else(|| {
+            OthelloError::Usage("ladder [--state path] [--promote N] [--demote N]".to_string())
+        })?;
+        match arg.as_str() {
+            "--state" => path = value.clone(),
+            "--promote" => config.promote_after = value.parse()?,
+            "--demote" => config.demote_after = value.parse()?,
+            other => return Err(OthelloError::Parse(format!("Unknown option \"{}\"", other))),
+        }
+    }
+
+    let mut state = LadderState::load(&path);
+    let (name, agent) = LEVELS[state.level];
+    // Alternate colors so you face each opponent from both sides
+    let human = if state.games_played().is_multiple_of(2) {
+        Color::Black
+    } else {
+        Color::White
+    };
+    println!(
+        "Level {} of {}: you play {} against {}",
+        state.level + 1,
+        LEVELS.len(),
+        human,
+        name
+    );
+
+    let start = Board::new();
+    let mut sinks: Vec<Box<dyn EventSink>> = vec![Box::new(ConsolePrinter::new(&start))];
+    let board = play_interactive(
+        start,
+        Some((human.opponent(), Box::new(agent))),
+        &mut *input::stdin(),
+        false,
+        &mut sinks,
+    )?;
+    if !board.is_over() {
+        println!("Game abandoned, ladder unchanged");
+        return Ok(());
+    }
+
+    let outcome = match board.winner() {
+        Some(color) if color == human => Outcome::Win,
+        Some(_) => Outcome::Loss,
+        None => Outcome::Draw,
+    };
+    let level = state.level;
+    state.record(outcome, &config);
+    println!("Result: {:?}", outcome);
+    match state.level.cmp(&level) {
+        std::cmp::Ordering::Greater => println!("Promoted to level {}!", state.level + 1),
+        std::cmp::Ordering::Less => println!("Demoted to level {}", state.level + 1),
+        std::cmp::Ordering::Equal => {}
+    }
+    let record = state.records[level];
+    println!(
+        "Record against {}: {}W/{}L/{}D",
+        name, record.wins, record.losses, record.draws
+    );
+    state.save(&path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Synthetic results drive the ladder: a streak of wins promotes and one of losses demotes, a
+    /// draw breaks either streak, the ends of the ladder hold, and each game lands in the record of
+    /// the level it was played at
+    #[test]
+    fn ladder_promotes_and_demotes_on_streaks() {
+        use Outcome::{Draw, Loss, Win};
+        let play = |state: &mut LadderState, config: &LadderConfig, outcomes: &[Outcome]| {
+            outcomes
+                .iter()
+                .map(|outcome| {
+                    state.record(*outcome, config);
+                    state.level
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let config = LadderConfig::default();
+        let mut state = LadderState::default();
+        let levels = play(
+            &mut state,
+            &config,
+            &[Loss, Loss, Win, Win, Win, Draw, Win, Loss, Draw, Loss, Loss],
+        );
+        if levels != [0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 0] {
+            panic!(
+                "promoting and demoting after 2 went through levels {:?}",
+                levels
+            );
+        }
+        let counts = |record: LevelRecord| (record.wins, record.losses, record.draws);
+        if counts(state.records[0]) != (2, 2, 0) || counts(state.records[1]) != (2, 3, 2) {
+            panic!("the games were recorded as {:?}", state.records);
+        }
+        if state.games_played() != 11 {
+            panic!("11 games counted as {}", state.games_played());
+        }
+
+        let config = LadderConfig {
+            promote_after: 3,
+            demote_after: 1,
+        };
+        let top = state.records.len() - 1;
+        let mut state = LadderState {
+            level: top,
+            ..LadderState::default()
+        };
+        let levels = play(&mut state, &config, &[Win, Win, Win, Loss, Loss]);
+        if levels != [top, top, top, top - 1, top - 2] {
+            panic!(
+                "promoting after 3 and demoting after 1 from the top went through levels {:?}",
+                levels
+            );
+        }
+        let levels = play(&mut state, &config, &[Win, Win, Draw, Win, Win, Win]);
+        if levels != [top - 2, top - 2, top - 2, top - 2, top - 2, top - 1] {
+            panic!(
+                "a draw after two wins didn't restart the streak: levels {:?}",
+                levels
+            );
+        }
+    }
+}
@@ -0,0 +1,4397 @@
+//! The Othello engine: boards and move generation, agents and search, and the subcommands the
+//! othello binary runs through [`run`]. [`Board`], [`Posn`], [`Move`], the agents and the search,
+//! table, heuristic, analysis and puzzle modules are public so another program can embed the
+//! engine; only the subcommands are private. With the ffi feature the library is also built as a C shared library;
+//! see the ffi module.
+
+use core::cmp::Ordering;
+#[cfg(feature = "sim")]
+use rayon::prelude::*;
+#[cfg(feature = "rand")]
+use rng::MoveRng;
+use std::fmt::Display;
+use std::io::IsTerminal;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::sync::OnceLock;
+
+pub use error::OthelloError;
+use events::GameEvent;
+
+pub mod analysis;
+#[cfg(feature = "sim")]
+mod blocks;
+#[cfg(feature = "sim")]
+mod book;
+mod config;
+mod error;
+pub mod events;
+#[cfg(feature = "ffi")]
+mod ffi;
+#[cfg(feature = "rand")]
+mod fuzz;
+mod input;
+#[cfg(feature = "rand")]
+mod ladder;
+mod logging;
+#[cfg(feature = "sim")]
+pub mod mcts;
+#[cfg(feature = "std")]
+mod network;
+#[cfg(feature = "sim")]
+mod observer;
+#[cfg(feature = "rand")]
+pub mod puzzle;
+#[cfg(feature = "sim")]
+mod report;
+#[cfg(feature = "rand")]
+pub mod rng;
+pub mod search;
+#[cfg(feature = "sim")]
+mod selfplay;
+mod stats;
+pub mod svg;
+pub mod table;
+pub mod transcript;
+pub mod tree;
+#[cfg(feature = "sim")]
+mod tuning;
+pub mod weights;
+
+/// coz::progress! when built with the profiling feature, and nothing otherwise. It marks
+/// throughput for the profiler and runs alongside the tracing events in the logging module
+macro_rules! progress {
+    ($name:expr) => {
+        #[cfg(feature = "profiling")]
+        coz::progress!($name);
+    };
+}
+
+#[cfg(feature = "sim")]
+use statrs::distribution::Beta;
+#[cfg(feature = "sim")]
+use statrs::distribution::ContinuousCDF;
+
+pub const ROWS: usize = 8;
+pub const COLS: usize = 8;
+pub const DIRS: [Dir; 8] = [
+    Dir::Up,
+    Dir::Down,
+    Dir::Left,
+    Dir::Right,
+    Dir::UpLeft,
+    Dir::UpRight,
+    Dir::DownLeft,
+    Dir::DownRight,
+];
+pub const POSNS: [Posn; ROWS * COLS] = generate_positions();
+/// For each square's index and each direction (in DIRS order), the index of the neighbor that
+/// way, or None off the edge
+const NEIGHBORS: [[Option<usize>; DIRS.len()]; ROWS * COLS] = generate_neighbors();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum Color {
+    Black,
+    White,
+}
+
+impl Color {
+    pub fn opponent(self) -> Color {
+        match self {
+            Color::Black => Color::White,
+            Color::White => Color::Black,
+        }
+    }
+}
+
+impl std::str::FromStr for Color {
+    type Err = OthelloError;
+
+    /// "black" or "white", in any case
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_lowercase().as_str() {
+            "black" => Ok(Color::Black),
+            "white" => Ok(Color::White),
+            _ => Err(OthelloError::Parse(format!("Unknown color \"{}\"", name))),
+        }
+    }
+}
+
+impl Display for Color {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Color::Black => write!(f, "Black"),
+            Color::White => write!(f, "White"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Dir {
+    Up,
+    Down,
+    Left,
+    Right,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
+}
+
+impl Dir {
+    pub const fn dir_to_offset(dir: &Dir) -> (i32, i32) {
+        match dir {
+            Dir::Up => (0, 1),
+            Dir::Down => (0, -1),
+            Dir::Left => (-1, 0),
+            Dir::Right => (1, 0),
+            Dir::UpLeft => (-1, 1),
+            Dir::UpRight => (1, 1),
+            Dir::DownLeft => (-1, -1),
+            Dir::DownRight => (1, -1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Square {
+    Unoccupied,
+    Occupied(Color),
+}
+
+impl Square {
+    pub fn color(self) -> Option<Color> {
+        match self {
+            Square::Unoccupied => None,
+            Square::Occupied(color) => Some(color),
+        }
+    }
+
+    pub fn is_occupied(self) -> bool {
+        self != Square::Unoccupied
+    }
+
+    pub fn is_color(self, color: Color) -> bool {
+        self == Square::Occupied(color)
+    }
+}
+
+impl From<Color> for Square {
+    fn from(color: Color) -> Self {
+        Square::Occupied(color)
+    }
+}
+
+/// A square on the board, row and column counted from 0 at a1
+#[derive(Debug, Copy, Clone)]
+pub struct Posn {
+    row: usize,
+    col: usize,
+}
+
+impl TryFrom<usize> for Posn {
+    type Error = OthelloError;
+
+    fn try_from(index: usize) -> Result<Self, Self::Error> {
+        Posn::from_index(index)
+            .ok_or_else(|| OthelloError::Parse(format!("Square index {} is off the board", index)))
+    }
+}
+
+impl From<Posn> for usize {
+    fn from(posn: Posn) -> Self {
+        posn.index()
+    }
+}
+
+impl Display for Posn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", (b'a' + self.col as u8) as char, self.row + 1)
+    }
+}
+
+/// The 0-indexed (row, col) that a column letter and a row number of any length name ("j10" is
+/// (9, 9)), whether or not that square is on this board
+fn alphanumeric_coords(s: &str) -> Option<(usize, usize)> {
+    let mut chars = s.chars();
+    let col = chars.next()?.to_ascii_lowercase();
+    let digits = chars.as_str();
+    if !col.is_ascii_lowercase()
+        || digits.starts_with('0')
+        || !digits.chars().all(|ch| ch.is_ascii_digit())
+    {
+        return None;
+    }
+    let row: usize = digits.parse().ok()?;
+    Some((row - 1, col as usize - 'a' as usize))
+}
+
+/// Lay out a grid of any size up to 26 columns with letters along the top and row numbers down the
+/// left, right-aligned so two-digit rows line up with one-digit ones. Each cell should show as one
+/// column, though it may carry terminal escape codes
+fn format_grid(cells: &[Vec<impl Display>]) -> String {
+    let width = cells.len().to_string().len();
+    let cols = cells.first().map_or(0, Vec::len);
+    let mut text = " ".repeat(width);
+    text.extend((0..cols).map(|col| (b'a' + col as u8) as char));
+    text.push('\n');
+    for (row, squares) in cells.iter().enumerate() {
+        text.push_str(&format!("{:>width$}", row + 1, width = width));
+        for square in squares {
+            text.push_str(&square.to_string());
+        }
+        text.push('\n');
+    }
+    text
+}
+
+// (row, col) are 0-indexed positions on the board
+impl Posn {
+    // "a1" -> Posn { row: 0, col: 0 }
+    // "e3" -> Posn { row: 2, col: 4 }
+
+    /// None for anything that isn't a square on the board
+    pub fn try_from_alphanumeric(s: &str) -> Option<Posn> {
+        let (row, col) = alphanumeric_coords(s)?;
+        Posn::try_from_coords(row, col)
+    }
+
+    /// The square at row and col, if they're on the board. Checked before any conversion, so a
+    /// huge row can't wrap around onto the board
+    pub fn try_from_coords(row: usize, col: usize) -> Option<Posn> {
+        (row < ROWS && col < COLS).then_some(Posn { row, col })
+    }
+
+    pub fn try_from_tuple(coords: (i32, i32)) -> Option<Self> {
+        if (0..ROWS as i32).contains(&coords.0) && (0..COLS as i32).contains(&coords.1) {
+            Some(Posn {
+                row: coords.0 as usize,
+                col: coords.1 as usize,
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn to_tuple(self) -> (usize, usize) {
+        (self.row, self.col)
+    }
+
+    /// Row-major square number: a1 is 0, h1 is 7, a2 is 8 and h8 is 63. POSNS is in this order
+    pub const fn index(self) -> usize {
+        self.row * COLS + self.col
+    }
+
+    /// The inverse of index, or None past the last square
+    pub const fn from_index(index: usize) -> Option<Posn> {
+        if index < ROWS * COLS {
+            Some(Posn {
+                row: index / COLS,
+                col: index % COLS,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Find the neighbor in the given direction, if it exists
+    pub fn neighbor_in_dir(&self, dir: &Dir) -> Option<Self> {
+        let (offset_row, offset_col) = Dir::dir_to_offset(dir);
+        Posn::try_from_tuple((self.row as i32 + offset_row, self.col as i32 + offset_col))
+    }
+
+    /// neighbor_in_dir by table lookup, for the scans in move generation. Dir's declaration order
+    /// matches DIRS, so it indexes NEIGHBORS directly
+    pub fn neighbor(&self, dir: Dir) -> Option<Self> {
+        NEIGHBORS[self.index()][dir as usize].map(|index| POSNS[index])
+    }
+
+    pub const fn is_row_edge(&self) -> bool {
+        self.row == 0 || self.row == ROWS - 1
+    }
+
+    pub const fn is_col_edge(&self) -> bool {
+        self.col == 0 || self.col == COLS - 1
+    }
+
+    pub const fn is_edge(&self) -> bool {
+        self.is_row_edge() || self.is_col_edge()
+    }
+
+    pub const fn is_corner(&self) -> bool {
+        self.is_row_edge() && self.is_col_edge()
+    }
+
+    /// An edge square next to a corner (like b1 or a2)
+    pub fn is_c_square(&self) -> bool {
+        let next_to_edge = |index: usize, len: usize| index == 1 || index == len - 2;
+        (self.is_row_edge() && next_to_edge(self.col, COLS))
+            || (self.is_col_edge() && next_to_edge(self.row, ROWS))
+    }
+
+    /// A square diagonally next to a corner (like b2 or g7)
+    pub fn is_x_square(&self) -> bool {
+        let next_to_edge = |index: usize, len: usize| index == 1 || index == len - 2;
+        next_to_edge(self.row, ROWS) && next_to_edge(self.col, COLS)
+    }
+
+    /// Apply one of the eight symmetries of the (square) board: 0-3 rotate by 0/90/180/270
+    /// degrees, 4-7 do the same after reflecting across the main diagonal
+    pub fn transformed(&self, symmetry: usize) -> Posn {
+        let (row, col) = if symmetry >= 4 {
+            (self.col, self.row)
+        } else {
+            (self.row, self.col)
+        };
+        let (row, col) = match symmetry % 4 {
+            0 => (row, col),
+            1 => (col, COLS - 1 - row),
+            2 => (ROWS - 1 - row, COLS - 1 - col),
+            _ => (ROWS - 1 - col, row),
+        };
+        Posn { row, col }
+    }
+}
+
+/// A single ply: either a disc placed on a square, or a pass when no placement is legal
+#[derive(Debug, Clone, Copy)]
+pub enum Move {
+    Place(Posn),
+    Pass,
+}
+
+impl Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Move::Place(posn) => write!(f, "{}", posn),
+            Move::Pass => write!(f, "pass"),
+        }
+    }
+}
+
+/// Read a move written any of the ways people and programs write them:
+///
+/// - a square, in either case: "f5" or "F5"
+/// - a square's index, 0 for a1 through 63 for h8, row by row as in Posn::index: "37" is f5
+/// - a pass: "pass", "--" or "pa", in any case
+///
+/// Surrounding whitespace is ignored. Text made only of digits is always an index, so "11" is d2
+/// and never a malformed square; a square always starts with its column letter
+pub fn parse_move(text: &str) -> Result<Move, OthelloError> {
+    let text = text.trim();
+    let lower = text.to_lowercase();
+    if matches!(lower.as_str(), "pass" | "--" | "pa") {
+        return Ok(Move::Pass);
+    }
+    if text.is_empty() {
+        return Err(OthelloError::Parse("Empty move".to_string()));
+    }
+    if text.chars().all(|ch| ch.is_ascii_digit()) {
+        let index: usize = text.parse().map_err(|_| {
+            OthelloError::Parse(format!("Square index {} is off the board (0-63)", text))
+        })?;
+        return Posn::from_index(index).map(Move::Place).ok_or_else(|| {
+            OthelloError::Parse(format!(
+                "Square index {} is off the board (0-{})",
+                index,
+                ROWS * COLS - 1
+            ))
+        });
+    }
+    let Some((row, col)) = alphanumeric_coords(&lower) else {
+        return Err(OthelloError::Parse(format!(
+            "Unrecognized move \"{}\" (expected a square like f5, an index from 0 to {}, or pass)",
+            text,
+            ROWS * COLS - 1
+        )));
+    };
+    Posn::try_from_coords(row, col)
+        .map(Move::Place)
+        .ok_or_else(|| OthelloError::Parse(format!("{} is off the board", lower)))
+}
+
+const fn generate_positions() -> [Posn; ROWS * COLS] {
+    let mut posns = [Posn { row: 0, col: 0 }; ROWS * COLS];
+    let mut i = 0;
+    while i < ROWS * COLS {
+        posns[i] = match Posn::from_index(i) {
+            Some(posn) => posn,
+            None => panic!("index out of range"),
+        };
+        i += 1;
+    }
+    posns
+}
+
+const fn generate_neighbors() -> [[Option<usize>; DIRS.len()]; ROWS * COLS] {
+    let mut neighbors = [[None; DIRS.len()]; ROWS * COLS];
+    let mut square = 0;
+    while square < ROWS * COLS {
+        let mut dir = 0;
+        while dir < DIRS.len() {
+            let (offset_row, offset_col) = Dir::dir_to_offset(&DIRS[dir]);
+            let row = (square / COLS) as i32 + offset_row;
+            let col = (square % COLS) as i32 + offset_col;
+            if row >= 0 && row < ROWS as i32 && col >= 0 && col < COLS as i32 {
+                neighbors[square][dir] = Some(row as usize * COLS + col as usize);
+            }
+            dir += 1;
+        }
+        square += 1;
+    }
+    neighbors
+}
+
+/// The central 2x2 squares, which are pre-filled in Othello and filled by hand in Reversi
+const CENTER_POSNS: [Posn; 4] = [
+    Posn {
+        row: ROWS / 2 - 1,
+        col: COLS / 2 - 1,
+    },
+    Posn {
+        row: ROWS / 2 - 1,
+        col: COLS / 2,
+    },
+    Posn {
+        row: ROWS / 2,
+        col: COLS / 2 - 1,
+    },
+    Posn {
+        row: ROWS / 2,
+        col: COLS / 2,
+    },
+];
+
+/// Board::new().fingerprint(): White on d4 and e5, Black on e4 and d5, Black to move
+pub const OPENING_FINGERPRINT: &str = "00000000000001800240000000000000-B";
+
+/// How the game begins: Othello starts with the four center discs already placed, while classic
+/// Reversi starts empty and the first four moves fill the center without flipping anything
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StartRule {
+    Othello,
+    Reversi,
+}
+
+/// How one position differs from another, square by square
+#[derive(Debug, Clone, Default)]
+pub struct BoardDiff {
+    /// Squares empty before and occupied after, with the color placed
+    pub added: Vec<(Posn, Color)>,
+    /// Squares that changed color, with the color they changed to
+    pub flipped: Vec<(Posn, Color)>,
+    /// Squares occupied before and empty after, which no move can do
+    pub removed: Vec<Posn>,
+}
+
+impl BoardDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.flipped.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Why Board::infer_move found no single move between two positions
+#[derive(Debug, Clone)]
+pub enum InferError {
+    /// No move leads from the first position to the second; the diff is how far apart they are
+    NoMove { turn: Color, diff: BoardDiff },
+    /// More than one move leads there, which a consistent rules engine never allows
+    Ambiguous(Vec<Move>),
+}
+
+impl Display for InferError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InferError::NoMove { turn, diff } => write!(
+                f,
+                "No move for {} leads to that position ({} added, {} flipped, {} removed)",
+                turn,
+                diff.added.len(),
+                diff.flipped.len(),
+                diff.removed.len()
+            ),
+            InferError::Ambiguous(moves) => {
+                write!(f, "{} moves lead to that position", moves.len())
+            }
+        }
+    }
+}
+
+impl std::error::Error for InferError {}
+
+/// What Board::apply_in_place changed, which is all undo_move needs to put it back
+#[derive(Debug, Clone, Copy)]
+pub struct MoveUndo {
+    posn: Posn,
+    /// What was at posn before, so even a move onto an occupied square undoes exactly
+    replaced: Square,
+    /// The flipped discs, one bit per Posn::index
+    flipped: u64,
+}
+
+/// A position: the discs on every square, whose turn it is, and how the game began
+#[derive(Debug, Clone)]
+pub struct Board {
+    squares: [[Square; COLS]; ROWS],
+    turn: Color,
+    start_rule: StartRule,
+}
+
+/// Boards are equal when they have the same discs and the same side to move; the same discs with
+/// the other side to move is a different position. The start rule is left out, since it only
+/// matters while the center is filling and no Othello board has an unfilled center
+impl PartialEq for Board {
+    fn eq(&self, other: &Self) -> bool {
+        self.squares == other.squares && self.turn == other.turn
+    }
+}
+
+impl Eq for Board {}
+
+impl std::hash::Hash for Board {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.squares.hash(state);
+        self.turn.hash(state);
+    }
+}
+
+/// The standard Othello opening, Black to move
+impl Default for Board {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Board {
+    pub fn new() -> Self {
+        Self::with_rules(StartRule::Othello, Color::Black)
+    }
+
+    /// An empty Reversi board, with Black to place the first center disc
+    pub fn new_reversi() -> Self {
+        Self::with_rules(StartRule::Reversi, Color::Black)
+    }
+
+    pub fn with_rules(start_rule: StartRule, first_player: Color) -> Self {
+        let mut board = [[Square::Unoccupied; COLS]; ROWS];
+        if start_rule == StartRule::Othello {
+            board[ROWS / 2 - 1][COLS / 2 - 1] = Color::Black.into();
+            board[ROWS / 2 - 1][COLS / 2] = Color::White.into();
+            board[ROWS / 2][COLS / 2 - 1] = Color::White.into();
+            board[ROWS / 2][COLS / 2] = Color::Black.into();
+        }
+
+        Self {
+            squares: board,
+            turn: first_player,
+            start_rule,
+        }
+    }
+}
+
+/// Characters to draw each kind of square with
+#[derive(Debug, Clone, Copy)]
+pub struct Glyphs {
+    pub empty: char,
+    pub black: char,
+    pub white: char,
+}
+
+/// How text output draws discs and bars. Ascii keeps every character one column wide, for
+/// terminals that draw the Unicode discs double width or can't draw them at all, and uses the
+/// X and O of position strings. Ansi colors the board with terminal escape codes, so it's only
+/// used when asked for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderStyle {
+    Unicode,
+    Ascii,
+    Ansi,
+}
+
+/// The style for this run, set once at startup or detected on first use
+static RENDER_STYLE: OnceLock<RenderStyle> = OnceLock::new();
+
+impl RenderStyle {
+    /// The style picked by --ascii, --unicode or --ansi or the config file, or else detected
+    pub fn current() -> Self {
+        *RENDER_STYLE.get_or_init(Self::detect)
+    }
+
+    /// Use style for the rest of the run. Only the first choice, before any output, counts
+    pub fn choose(style: Self) {
+        let _ = RENDER_STYLE.set(style);
+    }
+
+    /// OTHELLO_GLYPHS=ascii, unicode or ansi if set. Otherwise Unicode when the locale (LC_ALL,
+    /// LC_CTYPE or LANG, whichever is set first) names UTF-8, and Ascii when it doesn't
+    pub fn detect() -> Self {
+        match std::env::var("OTHELLO_GLYPHS").map(|style| style.to_lowercase()) {
+            Ok(style) if style == "ascii" => return RenderStyle::Ascii,
+            Ok(style) if style == "unicode" => return RenderStyle::Unicode,
+            Ok(style) if style == "ansi" => return RenderStyle::Ansi,
+            _ => {}
+        }
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .iter()
+            .find_map(|name| std::env::var(name).ok().filter(|value| !value.is_empty()))
+            .unwrap_or_default()
+            .to_lowercase();
+        if locale.contains("utf-8") || locale.contains("utf8") {
+            RenderStyle::Unicode
+        } else {
+            RenderStyle::Ascii
+        }
+    }
+
+    pub fn glyphs(self) -> Glyphs {
+        match self {
+            RenderStyle::Unicode => Glyphs {
+                empty: '_',
+                black: '○',
+                white: '●',
+            },
+            RenderStyle::Ascii => Glyphs {
+                empty: '.',
+                black: 'X',
+                white: 'O',
+            },
+            RenderStyle::Ansi => Glyphs {
+                empty: '·',
+                black: '●',
+                white: '●',
+            },
+        }
+    }
+
+    /// A board cell showing ch over square. Ansi paints the cell green, with a black or white
+    /// disc or a yellow mark on an empty square; the other styles leave ch as it is
+    pub fn paint(self, ch: char, square: Square) -> String {
+        if self != RenderStyle::Ansi {
+            return ch.to_string();
+        }
+        let color = match square {
+            Square::Occupied(Color::Black) => 30,
+            Square::Occupied(Color::White) => 97,
+            Square::Unoccupied => 33,
+        };
+        format!("\x1b[42;{}m{}\x1b[0m", color, ch)
+    }
+
+    /// The character bar charts are drawn with
+    pub fn bar(self) -> char {
+        match self {
+            RenderStyle::Unicode | RenderStyle::Ansi => '█',
+            RenderStyle::Ascii => '#',
+        }
+    }
+}
+
+impl Display for Board {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.render(RenderStyle::current()))
+    }
+}
+
+impl Board {
+    /// The squares alone, row by row from a1, drawn with glyphs
+    pub fn to_char_grid(&self, glyphs: &Glyphs) -> Vec<Vec<char>> {
+        POSNS
+            .chunks(COLS)
+            .map(|row| {
+                row.iter()
+                    .map(|posn| match self.piece_at(posn) {
+                        Square::Unoccupied => glyphs.empty,
+                        Square::Occupied(Color::Black) => glyphs.black,
+                        Square::Occupied(Color::White) => glyphs.white,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// The board with coordinates along the top and left, as Display shows it
+    pub fn render(&self, style: RenderStyle) -> String {
+        let mut grid = String::new();
+        // Writing to a String can't fail
+        let _ = self.write_grid(&mut grid, style, |_| None);
+        grid
+    }
+
+    /// Draw the board with coordinates along the top and left. mark can put a character on an
+    /// empty square in place of the style's empty glyph
+    pub fn write_grid(
+        &self,
+        f: &mut impl std::fmt::Write,
+        style: RenderStyle,
+        mark: impl Fn(&Posn) -> Option<char>,
+    ) -> std::fmt::Result {
+        let cells: Vec<Vec<String>> = self
+            .to_char_grid(&style.glyphs())
+            .into_iter()
+            .zip(POSNS.chunks(COLS))
+            .map(|(chars, row)| {
+                chars
+                    .into_iter()
+                    .zip(row)
+                    .map(|(ch, posn)| {
+                        let square = self.piece_at(posn);
+                        let ch = match square {
+                            Square::Unoccupied => mark(posn).unwrap_or(ch),
+                            Square::Occupied(_) => ch,
+                        };
+                        style.paint(ch, square)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        write!(f, "{}", format_grid(&cells))
+    }
+
+    /// The board with a '*' on each legal move for the side to move
+    pub fn legal_move_grid(&self, style: RenderStyle) -> String {
+        let mut legal = [false; ROWS * COLS];
+        for posn in self.legal_moves() {
+            legal[posn.index()] = true;
+        }
+        let mut grid = String::new();
+        // Writing to a String can't fail
+        let _ = self.write_grid(&mut grid, style, |posn| legal[posn.index()].then_some('*'));
+        grid
+    }
+
+    /// The board with each legal move marked by how many discs it would flip, or "+" for more
+    /// than 9
+    pub fn flip_count_grid(&self, style: RenderStyle) -> String {
+        let counts = self.flip_potential_map();
+        let mut grid = String::new();
+        // Writing to a String can't fail
+        let _ = self.write_grid(&mut grid, style, |posn| {
+            let count = counts[posn.index()];
+            self.is_legal(posn)
+                .then_some(count)
+                .map(|count| match count {
+                    10.. => '+',
+                    count => char::from_digit(count as u32, 10).unwrap(),
+                })
+        });
+        grid
+    }
+
+    /// Two bits per square (0 empty, 1 Black, 2 White) from a1 to h8, for use as a compact key
+    pub fn packed(&self) -> u128 {
+        POSNS.into_iter().fold(0, |acc, posn| {
+            let bits = match self.piece_at(&posn) {
+                Square::Unoccupied => 0,
+                Square::Occupied(Color::Black) => 1,
+                Square::Occupied(Color::White) => 2,
+            };
+            (acc << 2) | bits
+        })
+    }
+
+    /// A short identifier for bug reports: the packed squares as 32 hex digits, a dash, and 'B' or
+    /// 'W' for the side to move. The standard opening is OPENING_FINGERPRINT
+    pub fn fingerprint(&self) -> String {
+        let turn = match self.turn {
+            Color::Black => 'B',
+            Color::White => 'W',
+        };
+        format!("{:032x}-{}", self.packed(), turn)
+    }
+
+    /// 64 characters from a1 to h8 row by row ('X' Black, 'O' White, '-' empty), a space, and
+    /// 'X' or 'O' for the side to move
+    pub fn to_position_string(&self) -> String {
+        let mut position: String = POSNS
+            .iter()
+            .map(|posn| match self.piece_at(posn) {
+                Square::Unoccupied => '-',
+                Square::Occupied(Color::Black) => 'X',
+                Square::Occupied(Color::White) => 'O',
+            })
+            .collect();
+        position.push(' ');
+        position.push(match self.turn {
+            Color::Black => 'X',
+            Color::White => 'O',
+        });
+        position
+    }
+
+    pub fn from_position_string(position: &str) -> Option<Board> {
+        let (squares, turn) = position.trim().split_once(' ')?;
+        let squares: Vec<char> = squares.chars().collect();
+        if squares.len() != ROWS * COLS {
+            return None;
+        }
+
+        let mut board = Board::new();
+        board.turn = match turn.trim() {
+            "X" => Color::Black,
+            "O" => Color::White,
+            _ => return None,
+        };
+        for (posn, ch) in POSNS.iter().zip(squares) {
+            let square = match ch {
+                '-' => Square::Unoccupied,
+                'X' => Color::Black.into(),
+                'O' => Color::White.into(),
+                _ => return None,
+            };
+            board.set_piece_at(posn, square);
+        }
+        Some(board)
+    }
+
+    #[cfg(feature = "rand")]
+    pub fn random_set_up() -> Self {
+        Self::random_set_up_with(&mut rng::ThreadRng)
+    }
+
+    /// Drop four discs on random legal squares, alternating sides, without flipping anything. A
+    /// side with no legal square passes, and setup stops early if neither side has one
+    #[cfg(feature = "rand")]
+    pub fn random_set_up_with(rng: &mut impl MoveRng) -> Self {
+        let mut board = Self::new();
+        let mut placed = 0;
+        while placed < 4 {
+            let mut legal_moves = board.legal_moves();
+            if legal_moves.is_empty() {
+                board = board.change_turn();
+                legal_moves = board.legal_moves();
+                if legal_moves.is_empty() {
+                    break;
+                }
+            }
+            let posn = legal_moves[rng.index(legal_moves.len())];
+            board.set_piece_at(&posn, board.turn.into());
+            board = board.change_turn();
+            placed += 1;
+        }
+
+        debug_assert!(board.check_set_up().is_ok(), "{:?}", board.check_set_up());
+        board
+    }
+
+    /// The invariants of a board from random_set_up: between 6 and 8 discs (four to start, plus
+    /// at least two placed) and, when all four were placed, Black to move again
+    pub fn check_set_up(&self) -> Result<(), String> {
+        let discs = self.count_color_pieces(Color::Black) + self.count_color_pieces(Color::White);
+        if !(6..=8).contains(&discs) {
+            return Err(format!("set up with {} discs", discs));
+        }
+        if discs == 8 && self.turn != Color::Black {
+            return Err(format!("set up with {} to move", self.turn));
+        }
+        Ok(())
+    }
+
+    pub fn piece_at(&self, posn: &Posn) -> Square {
+        self.squares[posn.row][posn.col]
+    }
+
+    pub fn set_piece_at(&mut self, posn: &Posn, square: Square) {
+        self.squares[posn.row][posn.col] = square;
+    }
+
+    pub fn count_color_pieces(&self, color: Color) -> usize {
+        POSNS
+            .into_iter()
+            .filter(|posn| self.piece_at(posn).is_color(color))
+            .count()
+    }
+
+    /// How many of color's discs sit on the four X-squares, diagonally next to the corners
+    pub fn x_square_count(&self, color: Color) -> usize {
+        POSNS
+            .into_iter()
+            .filter(|posn| posn.is_x_square() && self.piece_at(posn).is_color(color))
+            .count()
+    }
+
+    /// How many of color's discs sit on the eight C-squares, the edge squares next to the corners
+    pub fn c_square_count(&self, color: Color) -> usize {
+        POSNS
+            .into_iter()
+            .filter(|posn| posn.is_c_square() && self.piece_at(posn).is_color(color))
+            .count()
+    }
+
+    /// Potential mobility: how many empty squares touch at least one of the opponent's discs.
+    /// A cheaper, smoother stand-in for counting color's legal moves
+    pub fn potential_mobility(&self, color: Color) -> usize {
+        POSNS
+            .into_iter()
+            .filter(|posn| {
+                !self.piece_at(posn).is_occupied()
+                    && DIRS
+                        .into_iter()
+                        .filter_map(|dir| posn.neighbor(dir))
+                        .any(|next| self.piece_at(&next).is_color(color.opponent()))
+            })
+            .count()
+    }
+
+    pub fn count_empty(&self) -> usize {
+        POSNS
+            .into_iter()
+            .filter(|posn| !self.piece_at(posn).is_occupied())
+            .count()
+    }
+
+    /// The same discs with color to move, whether or not a real game could reach that
+    pub fn with_turn(&self, color: Color) -> Self {
+        Self {
+            squares: self.squares,
+            turn: color,
+            start_rule: self.start_rule,
+        }
+    }
+
+    /// Return a new board with the turn changed
+    pub fn change_turn(&self) -> Self {
+        Self {
+            squares: self.squares,
+            turn: self.turn.opponent(),
+            start_rule: self.start_rule,
+        }
+    }
+
+    /// True while a Reversi game is still filling the center, where moves don't flip anything
+    pub fn is_placing_center(&self) -> bool {
+        self.start_rule == StartRule::Reversi
+            && CENTER_POSNS
+                .iter()
+                .any(|posn| !self.piece_at(posn).is_occupied())
+    }
+
+    /// Whether color could move here, stopping at the first legal move found
+    pub fn has_legal_move(&self, color: Color) -> bool {
+        let board = self.with_turn(color);
+        POSNS.iter().any(|posn| board.is_legal(posn))
+    }
+
+    /// Returns true if current player and opponent player have no legal moves
+    pub fn is_over(&self) -> bool {
+        !self.has_legal_move(self.turn) && !self.has_legal_move(self.turn.opponent())
+    }
+
+    /// The winner of a finished game. None both for a draw and for a game still going; is_draw
+    /// tells the two apart
+    pub fn winner(&self) -> Option<Color> {
+        if self.is_over() {
+            // Positive score means white won, negative means black won, zero means tie
+            match self.score().cmp(&0) {
+                Ordering::Greater => Some(Color::White),
+                Ordering::Less => Some(Color::Black),
+                Ordering::Equal => None,
+            }
+        } else {
+            None // No winner if the game isn't over yet
+        }
+    }
+
+    /// Whether the game is over with equal discs. A level position with moves left isn't a draw
+    pub fn is_draw(&self) -> bool {
+        self.is_over() && self.score() == 0
+    }
+
+    /// The side that has won however the rest of the game goes: it holds more than half the board
+    /// in discs that can never be flipped, or its opponent has no discs left
+    pub fn decided_winner(&self) -> Option<Color> {
+        let stable = weights::stable_discs(self);
+        [Color::Black, Color::White].into_iter().find(|&color| {
+            let stable_count = POSNS
+                .iter()
+                .filter(|posn| stable[posn.index()] && self.piece_at(posn).is_color(color))
+                .count();
+            stable_count > ROWS * COLS / 2
+                || (self.count_color_pieces(color) > 0
+                    && self.count_color_pieces(color.opponent()) == 0)
+        })
+    }
+
+    /// Board → # of White pieces - # of Black pieces
+    pub fn score(&self) -> i32 {
+        self.count_color_pieces(Color::White) as i32 - self.count_color_pieces(Color::Black) as i32
+    }
+
+    pub fn play_move(&self, posn: &Posn) -> Board {
+        let mut board = self.clone();
+        board.apply_in_place(posn);
+        board
+    }
+
+    /// Play posn on this board, as play_move does on a copy, and return what undo_move needs to
+    /// take it back. Lets a search make and unmake moves on one board instead of copying it
+    pub fn apply_in_place(&mut self, posn: &Posn) -> MoveUndo {
+        let mut flipped = 0u64;
+        if !self.is_placing_center() && !self.piece_at(posn).is_occupied() {
+            for dir in DIRS {
+                let mut curr = posn.index();
+                for _ in 0..self.flip_count_in_dir(posn, dir) {
+                    curr = NEIGHBORS[curr][dir as usize].expect("flips stay on the board");
+                    flipped |= 1 << curr;
+                }
+            }
+        }
+        let undo = MoveUndo {
+            posn: *posn,
+            replaced: self.piece_at(posn),
+            flipped,
+        };
+        let mover = Square::from(self.turn);
+        self.set_piece_at(posn, mover);
+        self.set_flipped(flipped, mover);
+        self.turn = self.turn.opponent();
+        undo
+    }
+
+    /// Take back the move apply_in_place returned undo for, which must be the last move applied
+    pub fn undo_move(&mut self, undo: &MoveUndo) {
+        self.turn = self.turn.opponent();
+        self.set_flipped(undo.flipped, Square::from(self.turn.opponent()));
+        self.set_piece_at(&undo.posn, undo.replaced);
+    }
+
+    /// Set every square with its bit set in flipped to square
+    pub fn set_flipped(&mut self, mut flipped: u64, square: Square) {
+        while flipped != 0 {
+            self.set_piece_at(&POSNS[flipped.trailing_zeros() as usize], square);
+            flipped &= flipped - 1;
+        }
+    }
+
+    pub fn is_legal(&self, posn: &Posn) -> bool {
+        if self.is_placing_center() {
+            return !self.piece_at(posn).is_occupied()
+                && CENTER_POSNS
+                    .iter()
+                    .any(|center| center.to_tuple() == posn.to_tuple());
+        }
+        !self.piece_at(posn).is_occupied() && self.would_flip(posn)
+    }
+
+    /// True if playing at posn would flip at least one piece. Unlike potential_flipped_pieces this
+    /// doesn't allocate, and stops at the first direction with a flip
+    pub fn would_flip(&self, posn: &Posn) -> bool {
+        DIRS.into_iter()
+            .any(|dir| self.flip_count_in_dir(posn, dir) > 0)
+    }
+
+    /// Number of pieces that playing at posn would flip in the given direction
+    pub fn flip_count_in_dir(&self, posn: &Posn, dir: Dir) -> usize {
+        let mut count = 0;
+        let mut curr_neighbor = NEIGHBORS[posn.index()][dir as usize];
+
+        while let Some(curr) = curr_neighbor {
+            match self.piece_at(&POSNS[curr]).color() {
+                Some(color) if color == self.turn => return count,
+                Some(_other_color) => count += 1,
+                None => return 0,
+            }
+            curr_neighbor = NEIGHBORS[curr][dir as usize];
+        }
+        0
+    }
+
+    /// potential_flipped_pieces(posn).len(), counted without collecting them
+    pub fn flipped_count(&self, posn: &Posn) -> usize {
+        if self.is_placing_center() || self.piece_at(posn).is_occupied() {
+            return 0;
+        }
+        DIRS.into_iter()
+            .map(|dir| self.flip_count_in_dir(posn, dir))
+            .sum()
+    }
+
+    /// Every legal move with the number of pieces it would flip
+    pub fn flip_counts(&self) -> Vec<(Posn, usize)> {
+        self.legal_moves()
+            .into_iter()
+            .map(|posn| (posn, self.flipped_count(&posn)))
+            .collect()
+    }
+
+    /// Every legal move with the number of pieces it would flip, most first. Moves that flip as
+    /// many keep legal_moves order, so greedy_agent plays the first for Black and the last of those
+    /// tied with it for White
+    pub fn moves_by_capture(&self) -> Vec<(Posn, usize)> {
+        let mut moves = self.flip_counts();
+        moves.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        moves
+    }
+
+    /// The empty corner the opponent could take right after the side to move plays posn, if any.
+    /// posn must be a legal move
+    pub fn concedes_corner(&self, posn: &Posn) -> Option<Posn> {
+        let next = self.play_move(posn);
+        POSNS
+            .into_iter()
+            .find(|corner| corner.is_corner() && next.is_legal(corner))
+    }
+
+    pub fn legal_moves(&self) -> Vec<Posn> {
+        POSNS
+            .into_iter()
+            .filter(|posn| self.is_legal(posn))
+            .collect()
+    }
+
+    /// How many pieces the side to move would flip by playing on each square (0 where illegal)
+    pub fn flip_potential_map(&self) -> [usize; ROWS * COLS] {
+        POSNS.map(|posn| match self.piece_at(&posn) {
+            Square::Unoccupied => self.flipped_count(&posn),
+            Square::Occupied(_) => 0,
+        })
+    }
+
+    /// (Black's legal move count, White's legal move count), whoever is to move. Each direction
+    /// from an empty square is walked once: a run of one color ended by the other is a move for
+    /// the other color
+    pub fn mobilities(&self) -> (usize, usize) {
+        if self.is_placing_center() {
+            let empty_centers = CENTER_POSNS
+                .iter()
+                .filter(|posn| !self.piece_at(posn).is_occupied())
+                .count();
+            return (empty_centers, empty_centers);
+        }
+
+        let (mut black, mut white) = (0, 0);
+        for posn in POSNS {
+            if self.piece_at(&posn).is_occupied() {
+                continue;
+            }
+            let (mut black_legal, mut white_legal) = (false, false);
+            for dir in DIRS {
+                let Some(first) = posn.neighbor(dir) else {
+                    continue;
+                };
+                let Some(run_color) = self.piece_at(&first).color() else {
+                    continue;
+                };
+                let mut curr = first.neighbor(dir);
+                while let Some(square) = curr {
+                    match self.piece_at(&square).color() {
+                        Some(color) if color == run_color => curr = square.neighbor(dir),
+                        Some(Color::Black) => {
+                            black_legal = true;
+                            break;
+                        }
+                        Some(Color::White) => {
+                            white_legal = true;
+                            break;
+                        }
+                        None => break,
+                    }
+                }
+                if black_legal && white_legal {
+                    break;
+                }
+            }
+            black += usize::from(black_legal);
+            white += usize::from(white_legal);
+        }
+        (black, white)
+    }
+
+    /// (own mobility, opponent mobility) in the position that playing posn would lead to
+    pub fn mobility_after(&self, posn: &Posn) -> (usize, usize) {
+        let (black, white) = self.play_move(posn).mobilities();
+        match self.turn {
+            Color::Black => (black, white),
+            Color::White => (white, black),
+        }
+    }
+
+    /// The same position with every disc and the side to move switched to the other color. Every
+    /// heuristic should score it as the negation of the original
+    pub fn color_swapped(&self) -> Board {
+        let mut board = self.clone();
+        for posn in POSNS {
+            if let Some(color) = self.piece_at(&posn).color() {
+                board.set_piece_at(&posn, color.opponent().into());
+            }
+        }
+        board.turn = self.turn.opponent();
+        board
+    }
+
+    /// The board with every square moved by Posn::transformed
+    pub fn transformed(&self, symmetry: usize) -> Board {
+        let mut board = self.clone();
+        for posn in POSNS {
+            board.set_piece_at(&posn.transformed(symmetry), self.piece_at(&posn));
+        }
+        board
+    }
+
+    /// Every square that differs between this board and other, sorted by what happened to it
+    pub fn diff(&self, other: &Board) -> BoardDiff {
+        let mut diff = BoardDiff::default();
+        for posn in POSNS {
+            match (self.piece_at(&posn), other.piece_at(&posn)) {
+                (before, after) if before == after => {}
+                (Square::Unoccupied, Square::Occupied(color)) => diff.added.push((posn, color)),
+                (Square::Occupied(_), Square::Occupied(color)) => diff.flipped.push((posn, color)),
+                (Square::Occupied(_), Square::Unoccupied) => diff.removed.push(posn),
+                (Square::Unoccupied, Square::Unoccupied) => unreachable!(),
+            }
+        }
+        diff
+    }
+
+    /// The move that turns this board into next: the one legal move, or the pass when there
+    /// isn't one, after which the boards match square for square and agree on whose turn it is
+    pub fn infer_move(&self, next: &Board) -> Result<Move, InferError> {
+        let same = |board: &Board| board.turn == next.turn && board.diff(next).is_empty();
+        let legal_moves = self.legal_moves();
+        let candidates: Vec<Move> = if legal_moves.is_empty() {
+            if same(&self.change_turn()) {
+                vec![Move::Pass]
+            } else {
+                vec![]
+            }
+        } else {
+            legal_moves
+                .into_iter()
+                .filter(|posn| same(&self.play_move(posn)))
+                .map(Move::Place)
+                .collect()
+        };
+        match candidates.as_slice() {
+            [mv] => Ok(*mv),
+            [] => Err(InferError::NoMove {
+                turn: self.turn,
+                diff: self.diff(next),
+            }),
+            _ => Err(InferError::Ambiguous(candidates)),
+        }
+    }
+
+    /// Empty squares that neither player can currently move into. These tend to bound the
+    /// independent regions that decide parity in the endgame
+    pub fn quiet_empties(&self) -> Vec<Posn> {
+        let opponent = self.change_turn();
+        POSNS
+            .into_iter()
+            .filter(|posn| {
+                !self.piece_at(posn).is_occupied()
+                    && !self.is_legal(posn)
+                    && !opponent.is_legal(posn)
+            })
+            .collect()
+    }
+
+    /// The empty squares split into groups that touch in any of the eight directions. Regions
+    /// come in the order of their first square and list their squares in board order
+    pub fn empty_regions(&self) -> Vec<Vec<Posn>> {
+        let mut seen = [false; ROWS * COLS];
+        let mut regions = vec![];
+        for start in POSNS {
+            if seen[start.index()] || self.piece_at(&start).is_occupied() {
+                continue;
+            }
+            seen[start.index()] = true;
+            let mut region = vec![];
+            let mut stack = vec![start];
+            while let Some(posn) = stack.pop() {
+                region.push(posn);
+                for next in DIRS.into_iter().filter_map(|dir| posn.neighbor(dir)) {
+                    if !seen[next.index()] && !self.piece_at(&next).is_occupied() {
+                        seen[next.index()] = true;
+                        stack.push(next);
+                    }
+                }
+            }
+            region.sort_by_key(|posn| posn.index());
+            regions.push(region);
+        }
+        regions
+    }
+
+    /// Empty squares that will stay empty for the rest of the game, whatever either player does.
+    /// Filling a square needs two occupied squares in a row next to it in some direction, so a
+    /// square is dead if every direction runs into the edge or another dead square within two
+    /// steps. Discs can change color, so colors aren't relied on; the answer is sound but may miss
+    /// some squares, except at the end of the game where every empty square is dead
+    pub fn dead_empties(&self) -> Vec<Posn> {
+        let empties = POSNS
+            .into_iter()
+            .filter(|posn| !self.piece_at(posn).is_occupied());
+        if self.is_over() {
+            return empties.collect();
+        }
+
+        // Start by supposing every empty square is dead, then revive any that could be filled
+        // assuming the revived ones might be
+        let mut dead = [false; ROWS * COLS];
+        for posn in empties {
+            dead[posn.index()] = true;
+        }
+        let could_fill = |dead: &[bool; ROWS * COLS], posn: &Posn| {
+            DIRS.into_iter().any(|dir| {
+                let Some(first) = posn.neighbor(dir) else {
+                    return false;
+                };
+                let Some(second) = first.neighbor(dir) else {
+                    return false;
+                };
+                !dead[first.index()] && !dead[second.index()]
+            })
+        };
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for posn in POSNS {
+                if dead[posn.index()] && could_fill(&dead, &posn) {
+                    dead[posn.index()] = false;
+                    changed = true;
+                }
+            }
+        }
+        POSNS
+            .into_iter()
+            .filter(|posn| dead[posn.index()])
+            .collect()
+    }
+
+    /// Legal moves that change who can take a corner: corner captures, and moves that flip an
+    /// edge disc next to a corner. Quiescence search keeps playing these past the horizon
+    pub fn loud_moves(&self) -> Vec<Posn> {
+        self.legal_moves()
+            .into_iter()
+            .filter(|posn| {
+                posn.is_corner()
+                    || self
+                        .potential_flipped_pieces(posn)
+                        .iter()
+                        .any(|flipped| flipped.is_c_square())
+            })
+            .collect()
+    }
+
+    pub fn potential_flipped_pieces_in_dir(&self, posn: &Posn, dir: Dir) -> Vec<Posn> {
+        let mut line: Vec<Posn> = vec![];
+        let mut curr_neighbor = posn.neighbor(dir);
+
+        // Keep going until we run off the board or find an unoccupied square (no pieces to flip),
+        // or find a piece of the same color (we've found a flip)
+        while let Some(curr) = curr_neighbor {
+            match self.piece_at(&curr).color() {
+                Some(color) if color == self.turn => {
+                    return line;
+                }
+                Some(_other_color) => {
+                    line.push(curr);
+                }
+                None => {
+                    return vec![];
+                }
+            }
+            curr_neighbor = curr.neighbor(dir);
+        }
+        progress!("Potential flipped pieces in dir");
+        // We've run off the board: if we haven't already returned, then there's no second tile to
+        // surround any of the current line, and there's no flips in this direction
+        vec![]
+    }
+
+    /// The discs the side to move would flip by playing posn, in order of Posn::index with no
+    /// repeats. Empty exactly when posn isn't a legal move: for occupied squares, squares that
+    /// flip nothing, and while the center is still being placed, when no move flips anything
+    pub fn potential_flipped_pieces(&self, posn: &Posn) -> Vec<Posn> {
+        if self.is_placing_center() || self.piece_at(posn).is_occupied() {
+            return vec![];
+        }
+        let mut flipped: Vec<Posn> = DIRS
+            .into_iter()
+            .flat_map(|dir| self.potential_flipped_pieces_in_dir(posn, dir))
+            .collect();
+        flipped.sort_by_key(|posn| posn.index());
+        flipped
+    }
+}
+
+pub fn standard_heuristic(board: &Board) -> i32 {
+    board.score()
+}
+
+/// edge_corner_heuristic's weight for each square, indexed by Posn::index
+const EDGE_CORNER_WEIGHTS: [i32; ROWS * COLS] = generate_edge_corner_weights();
+
+const fn generate_edge_corner_weights() -> [i32; ROWS * COLS] {
+    let mut weights = [0; ROWS * COLS];
+    let mut i = 0;
+    while i < ROWS * COLS {
+        let posn = POSNS[i];
+        weights[i] = if posn.is_corner() {
+            4
+        } else if posn.is_edge() {
+            2
+        } else {
+            1
+        };
+        i += 1;
+    }
+    weights
+}
+
+/// Heuristic that favors edge and corner positions (corners/edges/else = 4/2/1)
+pub fn edge_corner_heuristic(board: &Board) -> i32 {
+    POSNS
+        .into_iter()
+        .map(|posn| match board.piece_at(&posn) {
+            Square::Occupied(Color::White) => EDGE_CORNER_WEIGHTS[posn.index()],
+            Square::Occupied(Color::Black) => -EDGE_CORNER_WEIGHTS[posn.index()],
+            Square::Unoccupied => 0,
+        })
+        .sum()
+}
+
+/// edge_corner_heuristic split into its corner, edge and interior parts, which sum to it
+fn edge_corner_components(board: &Board) -> [(&'static str, i32); 3] {
+    let mut components = [("corner", 0), ("edge", 0), ("interior", 0)];
+    for posn in POSNS {
+        let weight = EDGE_CORNER_WEIGHTS[posn.index()];
+        let index = if posn.is_corner() {
+            0
+        } else if posn.is_edge() {
+            1
+        } else {
+            2
+        };
+        match board.piece_at(&posn).color() {
+            Some(Color::White) => components[index].1 += weight,
+            Some(Color::Black) => components[index].1 -= weight,
+            None => {}
+        }
+    }
+    components
+}
+
+/// A heuristic scores a position, positive when White is ahead
+pub type HeuristicFn = fn(&Board) -> i32;
+
+/// Every built-in heuristic by name
+pub const HEURISTICS: [(&str, HeuristicFn); 2] = [
+    ("standard", standard_heuristic),
+    ("edge_corner", edge_corner_heuristic),
+];
+
+/// An agent picks a move for the side to move
+pub type AgentFn = fn(&Board) -> Posn;
+
+/// A player that may keep state between moves, such as search tables
+pub trait Agent {
+    /// Only called when the side to move has a legal move
+    fn choose_move(&mut self, board: &Board) -> Posn;
+}
+
+impl Agent for AgentFn {
+    fn choose_move(&mut self, board: &Board) -> Posn {
+        self(board)
+    }
+}
+
+/// How a game played out by play_from ended
+#[derive(Debug, Clone)]
+pub struct Outcome {
+    pub final_board: Board,
+    /// None for a draw
+    pub winner: Option<Color>,
+}
+
+impl Outcome {
+    /// 1 if color won, 0.5 for a draw and 0 if it lost
+    pub fn points(&self, color: Color) -> f64 {
+        match self.winner {
+            Some(winner) if winner == color => 1.0,
+            Some(_) => 0.0,
+            None => 0.5,
+        }
+    }
+}
+
+/// Play out the game from start, with start's side to move first and passing for whichever side
+/// has no legal move
+pub fn play_from(start: Board, black: &mut dyn Agent, white: &mut dyn Agent) -> Outcome {
+    let final_board =
+        play_with_events(start, black, white, &mut []).expect("no event sinks to fail");
+    Outcome {
+        winner: final_board.winner(),
+        final_board,
+    }
+}
+
+/// play_from, reporting each turn, move, pass and the end of the game to sinks
+pub fn play_with_events(
+    start: Board,
+    black: &mut dyn Agent,
+    white: &mut dyn Agent,
+    sinks: &mut [Box<dyn events::EventSink>],
+) -> Result<Board, OthelloError> {
+    Ok(drive_game(start, black, white, sinks, false)?.0)
+}
+
+/// play_with_events, but stopping as soon as the position has a decided_winner, which GameOver
+/// then names. Returns the last position reached and how many empty squares were left unplayed
+pub fn play_until_decided(
+    start: Board,
+    black: &mut dyn Agent,
+    white: &mut dyn Agent,
+    sinks: &mut [Box<dyn events::EventSink>],
+) -> Result<(Board, usize), OthelloError> {
+    drive_game(start, black, white, sinks, true)
+}
+
+/// The game loop behind play_with_events and play_until_decided
+fn drive_game(
+    start: Board,
+    black: &mut dyn Agent,
+    white: &mut dyn Agent,
+    sinks: &mut [Box<dyn events::EventSink>],
+    stop_when_decided: bool,
+) -> Result<(Board, usize), OthelloError> {
+    let _game = tracing::debug_span!("game", start = %start.to_position_string()).entered();
+    let mut board = start;
+    let mut ply = 0;
+    while !board.is_over() {
+        ply += 1;
+        let _ply = tracing::debug_span!("move", ply).entered();
+        if let (true, Some(winner)) = (stop_when_decided, board.decided_winner()) {
+            events::emit(
+                sinks,
+                GameEvent::GameOver {
+                    result: Some(winner),
+                },
+            )?;
+            let empties = board.count_empty();
+            return Ok((board, empties));
+        }
+        let color = board.turn;
+        let legal_moves = board.legal_moves();
+        if legal_moves.is_empty() {
+            tracing::debug!(target: logging::GAME_TARGET, %color, "pass");
+            events::emit(sinks, GameEvent::Pass { color })?;
+            board = board.change_turn();
+            continue;
+        }
+        events::emit(sinks, GameEvent::TurnStarted { color, legal_moves })?;
+        let posn = match color {
+            Color::Black => black.choose_move(&board),
+            Color::White => white.choose_move(&board),
+        };
+        let flipped = board.potential_flipped_pieces(&posn);
+        board = board.play_move(&posn);
+        tracing::debug!(
+            target: logging::GAME_TARGET,
+            %color,
+            %posn,
+            flipped = flipped.len(),
+            score = board.score(),
+            "move"
+        );
+        events::emit(
+            sinks,
+            GameEvent::MovePlayed {
+                color,
+                posn,
+                flipped,
+            },
+        )?;
+    }
+    events::emit(
+        sinks,
+        GameEvent::GameOver {
+            result: board.winner(),
+        },
+    )?;
+    Ok((board, 0))
+}
+
+// Random agent that chooses a random legal move
+#[cfg(feature = "rand")]
+pub fn random_agent(board: &Board) -> Posn {
+    random_agent_with(board, &mut rng::ThreadRng)
+}
+
+/// random_agent, drawing from rng
+#[cfg(feature = "rand")]
+pub fn random_agent_with(board: &Board, rng: &mut impl MoveRng) -> Posn {
+    let legal_moves = board.legal_moves();
+    legal_moves[rng.index(legal_moves.len())]
+}
+
+/// Agent that always plays the move flipping the most pieces this turn, a common beginner strategy.
+/// Ties go to the last such move for White and the first for Black, like best_move_by. Passes
+/// when it must
+pub fn greedy_agent(board: &Board) -> Move {
+    let moves = board.moves_by_capture();
+    let Some(&(_, most)) = moves.first() else {
+        return Move::Pass;
+    };
+    let mut tied = moves.into_iter().take_while(|(_, count)| *count == most);
+    let choice = match board.turn {
+        Color::Black => tied.next(),
+        Color::White => tied.last(),
+    };
+    choice.map_or(Move::Pass, |(posn, _)| Move::Place(posn))
+}
+
+/// Heuristics and search scores are positive when White is ahead. This is the score from color's
+/// point of view instead, so higher is always better for color
+fn evaluate_for(board: &Board, color: Color, heuristic: impl Fn(&Board) -> i32) -> i32 {
+    match color {
+        Color::White => heuristic(board),
+        Color::Black => -heuristic(board),
+    }
+}
+
+/// The side to move's move when it has exactly one, which agents play without searching
+pub fn forced_move(board: &Board) -> Option<Posn> {
+    match board.legal_moves()[..] {
+        [posn] => Some(posn),
+        _ => None,
+    }
+}
+
+/// Search depth of quick_hint
+pub const QUICK_HINT_DEPTH: i32 = 2;
+
+/// A suggestion that's always fast, whatever the engine is configured to play: a fixed
+/// QUICK_HINT_DEPTH search with edge_corner_heuristic, or the pass when there's no placement
+pub fn quick_hint(board: &Board) -> Move {
+    if board.legal_moves().is_empty() {
+        return Move::Pass;
+    }
+    if let Some(posn) = forced_move(board) {
+        return Move::Place(posn);
+    }
+    let (posn, _) = alphabeta_root(
+        board,
+        QUICK_HINT_DEPTH,
+        SCORE_LOSS,
+        SCORE_WIN,
+        edge_corner_heuristic,
+        0,
+    );
+    Move::Place(posn)
+}
+
+/// The legal move leading to the position that score, taken from the mover's point of view, rates
+/// highest. Ties go to the last such move for White and the first for Black, as they always have.
+/// A forced move is returned without scoring it
+fn best_move_by(board: &Board, score: impl Fn(&Board) -> i32) -> Posn {
+    if let Some(posn) = forced_move(board) {
+        return posn;
+    }
+    let tiebreak = |index: usize| match board.turn {
+        Color::White => index as isize,
+        Color::Black => -(index as isize),
+    };
+    board
+        .legal_moves()
+        .into_iter()
+        .enumerate()
+        .max_by_key(|(index, posn)| (score(&board.play_move(posn)), tiebreak(*index)))
+        .map(|(_, posn)| posn)
+        .unwrap()
+}
+
+/// Agent that chooses the move that optimizes the heuristic.
+/// Heuristic is positive if white is winning, negative if black is winning
+pub fn heuristic_agent(board: &Board, heuristic: fn(&Board) -> i32) -> Posn {
+    best_move_by(board, |next| evaluate_for(next, board.turn, heuristic))
+}
+
+/// Agent that leaves the opponent the fewest legal replies, preferring the move that leaves it
+/// the most of its own among those, then the first in legal_moves order. Passes when it must
+pub fn minimize_opponent_mobility_agent(board: &Board) -> Move {
+    board
+        .legal_moves()
+        .into_iter()
+        .min_by_key(|posn| {
+            let (own, opponent) = board.mobility_after(posn);
+            (opponent, std::cmp::Reverse(own))
+        })
+        .map_or(Move::Pass, Move::Place)
+}
+
+/// Use edge/corner heuristic until board is 4/5 full, then standard heuristic
+pub fn mesh_agent(board: &Board) -> Posn {
+    let total_pieces =
+        board.count_color_pieces(Color::Black) + board.count_color_pieces(Color::White);
+    if total_pieces > ((4 * ROWS * COLS) / 5) {
+        heuristic_agent(board, standard_heuristic)
+    } else {
+        heuristic_agent(board, edge_corner_heuristic)
+    }
+}
+
+/// Minimax, where white is maximizing and black is minimizing
+pub fn minimax(board: &Board, depth: i32, heuristic: fn(&Board) -> i32) -> i32 {
+    if board.is_over() {
+        return terminal_score(board);
+    }
+    if depth == 0 {
+        return heuristic(board);
+    }
+    let legal_moves = board.legal_moves();
+
+    let scores = legal_moves.iter().map(|legal_move| {
+        let new_board = board.play_move(legal_move);
+        minimax(&new_board, depth - 1, heuristic)
+    });
+
+    match board.turn {
+        Color::White => scores.max().unwrap_or(SCORE_LOSS),
+        Color::Black => scores.min().unwrap_or(SCORE_WIN),
+    }
+}
+
+pub fn minimax_agent(board: &Board, depth: i32, heuristic: fn(&Board) -> i32) -> Posn {
+    best_move_by(board, |next| {
+        evaluate_for(next, board.turn, |next| minimax(next, depth - 1, heuristic))
+    })
+}
+
+/// Predicts the opponent's move, or None when it can't tell which move the opponent would pick
+pub type OpponentModel = fn(&Board) -> Option<Posn>;
+
+/// greedy_agent's move when a single move flips the most discs, and None when several tie
+pub fn greedy_model(board: &Board) -> Option<Posn> {
+    let counts = board.flip_counts();
+    let most = counts.iter().map(|(_, count)| *count).max()?;
+    match counts
+        .iter()
+        .filter(|(_, count)| *count == most)
+        .collect::<Vec<_>>()[..]
+    {
+        [(posn, _)] => Some(*posn),
+        _ => None,
+    }
+}
+
+/// Minimax that expects the opponent to play the move model predicts rather than its best, so
+/// it can set up lines that only work against that player. Where the model can't tell, the
+/// opponent's node is searched like plain minimax. White positive, like minimax
+pub fn model_minimax(
+    board: &Board,
+    depth: i32,
+    heuristic: HeuristicFn,
+    me: Color,
+    model: OpponentModel,
+) -> i32 {
+    if board.is_over() {
+        return terminal_score(board);
+    }
+    if depth == 0 {
+        return heuristic(board);
+    }
+    if board.legal_moves().is_empty() {
+        return model_minimax(&board.change_turn(), depth, heuristic, me, model);
+    }
+
+    let moves = match model(board) {
+        Some(posn) if board.turn != me => vec![posn],
+        _ => board.legal_moves(),
+    };
+    let scores = moves
+        .into_iter()
+        .map(|posn| model_minimax(&board.play_move(&posn), depth - 1, heuristic, me, model));
+    match board.turn {
+        Color::White => scores.max().unwrap(),
+        Color::Black => scores.min().unwrap(),
+    }
+}
+
+/// The best move over depth plies against an opponent who plays as opponent_model predicts,
+/// scoring positions with own_heuristic
+pub fn exploit_agent(
+    board: &Board,
+    depth: i32,
+    own_heuristic: HeuristicFn,
+    opponent_model: OpponentModel,
+) -> Posn {
+    let me = board.turn;
+    best_move_by(board, |next| {
+        evaluate_for(next, me, |next| {
+            model_minimax(next, depth - 1, own_heuristic, me, opponent_model)
+        })
+    })
+}
+
+/// Minimax agent for playing weak opponents: it plays exploit_agent against its model of them
+#[derive(Debug, Clone, Copy)]
+pub struct BiasedMinimaxAgent {
+    pub depth: i32,
+    pub heuristic: HeuristicFn,
+    pub model: OpponentModel,
+}
+
+impl Agent for BiasedMinimaxAgent {
+    fn choose_move(&mut self, board: &Board) -> Posn {
+        exploit_agent(board, self.depth, self.heuristic, self.model)
+    }
+}
+
+/// Minimax agent that can also report its evaluation of every legal move, not just its pick.
+/// It keeps its search tables between moves, so positions it already looked at are cheaper
+#[derive(Debug, Clone)]
+pub struct MinimaxAgent {
+    pub depth: i32,
+    pub heuristic: fn(&Board) -> i32,
+    /// With this many empty squares or fewer, solve the rest of the game exactly instead
+    pub endgame_threshold: usize,
+    context: search::SearchContext,
+    /// Stop each search after this many positions and play the best move found so far, so runs
+    /// repeat exactly however fast the machine is. Exact endgame solves aren't limited
+    pub max_nodes: Option<usize>,
+    /// How much worse than 0 a draw is for the agent, as in SearchOptions. Exact endgame solves
+    /// score draws as 0 regardless
+    pub contempt: i32,
+}
+
+impl MinimaxAgent {
+    pub fn new(depth: i32, heuristic: fn(&Board) -> i32) -> Self {
+        Self::with_table(depth, heuristic, table::DEFAULT_MEGABYTES)
+    }
+
+    /// An agent whose transposition table takes at most megabytes. Searches made once and thrown
+    /// away should ask for table::ONE_SHOT_MEGABYTES rather than fill the default table
+    pub fn with_table(depth: i32, heuristic: fn(&Board) -> i32, megabytes: usize) -> Self {
+        Self {
+            depth,
+            heuristic,
+            endgame_threshold: 0,
+            context: search::SearchContext::with_megabytes(megabytes),
+            max_nodes: None,
+            contempt: 0,
+        }
+    }
+
+    /// Forget everything learned, so every game starts on equal terms
+    pub fn new_game(&mut self) {
+        self.context.clear();
+    }
+
+    /// Stop searching whenever flag is set, from any thread. The agent then deepens one ply at a
+    /// time, so it can fall back on the last depth it finished
+    pub fn cancel_with(&mut self, flag: Arc<AtomicBool>) {
+        self.context.cancel = Some(flag);
+    }
+
+    /// Every legal move with its minimax score, best first for the side to move. Within the
+    /// endgame threshold the scores are exact final disc differences. If the search is
+    /// cancelled or runs out of nodes, this is the ranking from the deepest completed depth, or
+    /// failing that the root moves finished at depth 1, or failing that just the first legal move
+    pub fn rank_moves(&mut self, board: &Board) -> Vec<(Move, i32)> {
+        self.context.node_limit = self.max_nodes.map(|nodes| nodes as u64);
+        self.context
+            .set_draw_score(draw_score(board, self.contempt));
+        self.context.new_search();
+        // Deepening gains nothing for an exact solve, which isn't cancellable anyway
+        let interruptible = self.context.cancel.is_some() || self.max_nodes.is_some();
+        if !interruptible || board.count_empty() <= self.endgame_threshold {
+            return self.rank_at_depth(board, self.depth);
+        }
+        let mut ranked = vec![];
+        for depth in 1..=self.depth.max(1) {
+            let ranking = self.rank_at_depth(board, depth);
+            if self.context.is_cancelled() {
+                if ranked.is_empty() {
+                    ranked = ranking;
+                }
+                break;
+            }
+            ranked = ranking;
+        }
+        if ranked.is_empty() {
+            if let Some(posn) = board.legal_moves().first() {
+                ranked.push((Move::Place(*posn), (self.heuristic)(&board.play_move(posn))));
+            }
+        }
+        ranked
+    }
+
+    /// Rank the root moves searched to depth, leaving out any whose search was cancelled
+    pub fn rank_at_depth(&mut self, board: &Board, depth: i32) -> Vec<(Move, i32)> {
+        let solve_exactly = board.count_empty() <= self.endgame_threshold;
+        let mut ranked = vec![];
+        for posn in board.legal_moves() {
+            let next = board.play_move(&posn);
+            let score = if solve_exactly {
+                solve_exact(&next, SCORE_LOSS, SCORE_WIN)
+            } else {
+                search::alphabeta(
+                    &next,
+                    depth - 1,
+                    SCORE_LOSS,
+                    SCORE_WIN,
+                    self.heuristic,
+                    &mut self.context,
+                    1,
+                )
+            };
+            if self.context.is_cancelled() {
+                break;
+            }
+            ranked.push((Move::Place(posn), score));
+        }
+        match board.turn {
+            Color::White => ranked.sort_by_key(|(_, score)| std::cmp::Reverse(*score)),
+            Color::Black => ranked.sort_by_key(|(_, score)| *score),
+        }
+        ranked
+    }
+
+    /// The agent's move followed by the best replies its search found, out to its depth. Passes
+    /// appear where a side has no move; the line is shorter where the search didn't reach
+    pub fn principal_variation(&mut self, board: &Board) -> Vec<Move> {
+        let Some((Move::Place(posn), _)) = self.rank_moves(board).first().copied() else {
+            return vec![];
+        };
+        let mut line = vec![Move::Place(posn)];
+        let rest = (self.depth - 1).max(0) as usize;
+        line.extend(
+            self.context
+                .principal_variation(&board.play_move(&posn), rest),
+        );
+        line
+    }
+
+    /// The value, best move, principal variation and every move's score from one ranking, so
+    /// they all agree with each other and with choose_move
+    pub fn analyze(&mut self, board: &Board) -> Analysis {
+        let ranked_moves = self.rank_moves(board);
+        let Some(&(best_move, value)) = ranked_moves.first() else {
+            // No placement: the pass, and whatever the search makes of the position after it
+            let value = search::alphabeta(
+                board,
+                self.depth,
+                SCORE_LOSS,
+                SCORE_WIN,
+                self.heuristic,
+                &mut self.context,
+                0,
+            );
+            let principal_variation = self
+                .context
+                .principal_variation(board, self.depth.max(0) as usize);
+            return Analysis {
+                value,
+                best_move: Move::Pass,
+                principal_variation,
+                ranked_moves,
+            };
+        };
+        let mut principal_variation = vec![best_move];
+        if let Move::Place(posn) = best_move {
+            let rest = (self.depth - 1).max(0) as usize;
+            principal_variation.extend(
+                self.context
+                    .principal_variation(&board.play_move(&posn), rest),
+            );
+        }
+        Analysis {
+            value,
+            best_move,
+            principal_variation,
+            ranked_moves,
+        }
+    }
+
+    /// A sentence on why the agent's move is good, from how it changes each part of the
+    /// edge/corner evaluation and the opponent's mobility
+    pub fn explain(&mut self, board: &Board) -> String {
+        Self::explain_ranked(board, &self.rank_moves(board))
+    }
+
+    /// explain, for a ranking of board's moves the caller already has
+    pub fn explain_ranked(board: &Board, ranked: &[(Move, i32)]) -> String {
+        let Some((Move::Place(posn), score)) = ranked.first().copied() else {
+            return format!("{} has no legal moves and must pass", board.turn);
+        };
+        let mover = board.turn;
+        let next = board.play_move(&posn);
+        // The components are White positive; flip them to the mover's point of view
+        let sign = match mover {
+            Color::White => 1,
+            Color::Black => -1,
+        };
+        let gains: Vec<(&str, i32)> = edge_corner_components(board)
+            .iter()
+            .zip(edge_corner_components(&next))
+            .map(|((name, before), (_, after))| (*name, sign * (after - before)))
+            .collect();
+        let total_gain: i32 = gains.iter().map(|(_, gain)| gain).sum();
+        let (main_part, main_gain) = gains.iter().max_by_key(|(_, gain)| *gain).unwrap();
+
+        let purpose = if posn.is_corner() {
+            " to secure the corner"
+        } else if posn.is_edge() {
+            " to take an edge"
+        } else {
+            ""
+        };
+        let flips = board.potential_flipped_pieces(&posn).len();
+        let mut explanation = format!(
+            "{} plays {}{}, flipping {} disc{} and gaining {:+} positional value ({:+} from {} squares)",
+            mover,
+            posn,
+            purpose,
+            flips,
+            if flips == 1 { "" } else { "s" },
+            total_gain,
+            main_gain,
+            main_part
+        );
+
+        let mobility_before = board.change_turn().legal_moves().len() as i32;
+        let mobility_after = next.legal_moves().len() as i32;
+        match mobility_after.cmp(&mobility_before) {
+            Ordering::Less => explanation.push_str(&format!(
+                ", reducing {}'s mobility by {}",
+                mover.opponent(),
+                mobility_before - mobility_after
+            )),
+            Ordering::Greater => explanation.push_str(&format!(
+                ", though {} gains {} moves",
+                mover.opponent(),
+                mobility_after - mobility_before
+            )),
+            Ordering::Equal => {}
+        }
+
+        let corner_open = |position: &Board| position.legal_moves().iter().any(Posn::is_corner);
+        if corner_open(&next) && !corner_open(&board.change_turn()) {
+            explanation.push_str(&format!(", but it opens a corner for {}", mover.opponent()));
+        }
+        explanation.push_str(&format!(" (search score {:+} for {})", sign * score, mover));
+        explanation
+    }
+}
+
+/// Everything a minimax search says about a position at once. Scores are White positive, like
+/// the heuristics
+#[derive(Debug, Clone)]
+pub struct Analysis {
+    /// The position's minimax value
+    pub value: i32,
+    /// What the agent plays, or the pass when the side to move has no placement
+    pub best_move: Move,
+    /// The best move and the replies the search expects, as MinimaxAgent::principal_variation
+    pub principal_variation: Vec<Move>,
+    /// Every legal move with its score, best first for the side to move
+    pub ranked_moves: Vec<(Move, i32)>,
+}
+
+impl Analysis {
+    /// The value from color's side, so that higher is better for color
+    pub fn value_for(&self, color: Color) -> i32 {
+        match color {
+            Color::White => self.value,
+            Color::Black => -self.value,
+        }
+    }
+}
+
+/// Analyze board with a depth-limited search using the engine's heuristic
+pub fn analyze(board: &Board, depth: i32) -> Analysis {
+    MinimaxAgent::with_table(
+        depth,
+        weights::engine_heuristic(),
+        table::ONE_SHOT_MEGABYTES,
+    )
+    .analyze(board)
+}
+
+/// Empty squares up to which solve plays the game out exactly instead of searching to a depth
+pub const SOLVE_EXACT_EMPTIES: usize = 14;
+
+/// Search depth for solve when the position has too many empty squares to solve exactly
+pub const SOLVE_DEPTH: i32 = 8;
+
+/// The best move and value for board: exact final disc differences with SOLVE_EXACT_EMPTIES
+/// empty squares or fewer, or else a depth-limited search with the engine's heuristic
+pub fn solve_position(board: &Board, depth: i32) -> Analysis {
+    let mut agent = MinimaxAgent::with_table(
+        depth,
+        weights::engine_heuristic(),
+        table::ONE_SHOT_MEGABYTES,
+    );
+    agent.endgame_threshold = SOLVE_EXACT_EMPTIES;
+    let mut analysis = agent.analyze(board);
+    if analysis.ranked_moves.is_empty() && board.count_empty() <= SOLVE_EXACT_EMPTIES {
+        // A pass or a finished game, which analyze only searches to depth
+        analysis.value = solve_exact(board, SCORE_LOSS, SCORE_WIN);
+    }
+    analysis
+}
+
+/// A position as FFO test positions write it: 64 squares of X (Black), O (White) and - or .
+/// (empty) row by row from a1, and the side to move as black, white, X or O
+pub fn parse_ffo_position(squares: &str, side: &str) -> Result<Board, OthelloError> {
+    let squares: String = squares
+        .trim()
+        .chars()
+        .map(|square| match square {
+            '.' => '-',
+            other => other.to_ascii_uppercase(),
+        })
+        .collect();
+    let turn = match side.trim().to_lowercase().as_str() {
+        "x" | "b" | "black" => 'X',
+        "o" | "w" | "white" => 'O',
+        other => {
+            return Err(OthelloError::Parse(format!(
+                "Unknown side to move \"{}\"",
+                other
+            )))
+        }
+    };
+    Board::from_position_string(&format!("{} {}", squares, turn)).ok_or_else(|| {
+        OthelloError::Parse(format!(
+            "Expected 64 squares of X, O and -, not \"{}\"",
+            squares
+        ))
+    })
+}
+
+/// solve <64 squares> <side to move> [--depth n]: print the best move, its score and the line
+/// the search expects, for use from scripts
+fn solve_command(args: &[String]) -> Result<(), OthelloError> {
+    let usage = || {
+        OthelloError::Usage(
+            "solve \"<64 squares of X, O and ->\" <black|white> [--depth n, at least 1]"
+                .to_string(),
+        )
+    };
+    let mut depth = SOLVE_DEPTH;
+    let mut positional = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--depth" => depth = args.next().ok_or_else(usage)?.parse()?,
+            _ => positional.push(arg),
+        }
+    }
+    // A depth below 1 never reaches the search's leaf cutoff and would search to the game's end
+    let ([squares, side], true) = (&positional[..], depth >= 1) else {
+        return Err(usage());
+    };
+    let board = parse_ffo_position(squares, side)?;
+    let exact = board.count_empty() <= SOLVE_EXACT_EMPTIES;
+    let analysis = solve_position(&board, depth);
+
+    println!("{}", board);
+    if board.is_over() {
+        println!("Game over");
+    } else {
+        println!("Best move: {}", analysis.best_move);
+    }
+    println!(
+        "Score: {:+} for {} ({})",
+        analysis.value_for(board.turn),
+        board.turn,
+        if exact {
+            "exact final disc difference".to_string()
+        } else {
+            format!("depth {} evaluation", depth)
+        }
+    );
+    if !analysis.principal_variation.is_empty() {
+        let line: Vec<String> = analysis
+            .principal_variation
+            .iter()
+            .map(Move::to_string)
+            .collect();
+        println!("Line: {}", line.join(" "));
+    }
+    Ok(())
+}
+
+impl Agent for MinimaxAgent {
+    fn choose_move(&mut self, board: &Board) -> Posn {
+        if let Some(posn) = forced_move(board) {
+            tracing::debug!(%posn, "minimax plays its only move");
+            return posn;
+        }
+        match self.rank_moves(board).first() {
+            Some((Move::Place(posn), score)) => {
+                tracing::debug!(
+                    %posn,
+                    score,
+                    depth = self.depth,
+                    nodes = self.context.search_nodes,
+                    "minimax chose"
+                );
+                *posn
+            }
+            _ => panic!("choose_move called with no legal moves"),
+        }
+    }
+}
+
+/// Search depth behind the adaptive opponent's ranking of its moves
+pub const ADAPTIVE_DEPTH: i32 = 4;
+
+/// Minimax for playing people: once its search says it's ahead by more than threshold, it
+/// plays a worse move than its best, further down the ranking the further ahead it is, to keep
+/// the game close. It never eases off with a move that hands over a corner or that its search
+/// says loses, and plays its best whenever it isn't comfortably ahead
+#[derive(Debug, Clone)]
+pub struct AdaptiveAgent {
+    pub minimax: MinimaxAgent,
+    /// How far ahead, in the heuristic's units from the agent's side, before it eases off
+    pub threshold: i32,
+    /// Places down the ranking it goes for each threshold it's ahead by
+    pub aggressiveness: usize,
+}
+
+impl AdaptiveAgent {
+    pub fn new(depth: i32, heuristic: HeuristicFn, threshold: i32, aggressiveness: usize) -> Self {
+        Self {
+            minimax: MinimaxAgent::new(depth, heuristic),
+            threshold: threshold.max(1),
+            aggressiveness,
+        }
+    }
+
+    /// The adaptive opponent the config describes
+    pub fn from_config() -> Self {
+        let play = &config::get().play;
+        Self::new(
+            ADAPTIVE_DEPTH,
+            weights::engine_heuristic(),
+            play.adaptive_threshold,
+            play.adaptive_aggressiveness,
+        )
+    }
+}
+
+impl Agent for AdaptiveAgent {
+    fn choose_move(&mut self, board: &Board) -> Posn {
+        let for_me = |score: i32| match board.turn {
+            Color::White => score,
+            Color::Black => -score,
+        };
+        let ranked: Vec<(Posn, i32)> = self
+            .minimax
+            .rank_moves(board)
+            .into_iter()
+            .filter_map(|(mv, score)| match mv {
+                Move::Place(posn) => Some((posn, for_me(score))),
+                Move::Pass => None,
+            })
+            .collect();
+        let Some(&(best, lead)) = ranked.first() else {
+            panic!("choose_move called with no legal moves");
+        };
+        if lead <= self.threshold {
+            return best;
+        }
+        let steps = self.aggressiveness * (lead / self.threshold) as usize;
+        let safe: Vec<Posn> = ranked
+            .iter()
+            .filter(|(posn, score)| *score >= 0 && board.concedes_corner(posn).is_none())
+            .map(|(posn, _)| *posn)
+            .collect();
+        let posn = safe
+            .get(steps.min(safe.len().saturating_sub(1)))
+            .copied()
+            .unwrap_or(best);
+        tracing::debug!(%posn, %best, lead, steps, "adaptive agent eased off");
+        posn
+    }
+}
+
+/// Minimax with alpha-beta pruning. The result matches minimax whenever it lies strictly inside
+/// (alpha, beta); otherwise it's only a bound on the true value. With a nonzero quiescence_depth,
+/// leaves are resolved by quiescence search instead of being evaluated directly
+pub fn alphabeta(
+    board: &Board,
+    depth: i32,
+    alpha: i32,
+    beta: i32,
+    heuristic: impl Fn(&Board) -> i32 + Copy,
+    quiescence_depth: i32,
+) -> i32 {
+    alphabeta_with_draw(board, depth, alpha, beta, heuristic, quiescence_depth, 0)
+}
+
+/// alphabeta, scoring every drawn finish as draw_score instead of 0 (see contempt in
+/// SearchOptions)
+pub fn alphabeta_with_draw(
+    board: &Board,
+    depth: i32,
+    mut alpha: i32,
+    mut beta: i32,
+    heuristic: impl Fn(&Board) -> i32 + Copy,
+    quiescence_depth: i32,
+    draw_score: i32,
+) -> i32 {
+    if board.is_over() {
+        return match board.winner() {
+            Some(_) => terminal_score(board),
+            None => draw_score,
+        };
+    }
+    if depth == 0 {
+        return quiescence(board, quiescence_depth, alpha, beta, heuristic, draw_score);
+    }
+    let legal_moves = board.legal_moves();
+    if legal_moves.is_empty() {
+        // Forced pass: the opponent moves next without using up any depth
+        return alphabeta_with_draw(
+            &board.change_turn(),
+            depth,
+            alpha,
+            beta,
+            heuristic,
+            quiescence_depth,
+            draw_score,
+        );
+    }
+
+    match board.turn {
+        Color::White => {
+            let mut best = SCORE_LOSS;
+            for legal_move in &legal_moves {
+                let score = alphabeta_with_draw(
+                    &board.play_move(legal_move),
+                    depth - 1,
+                    alpha,
+                    beta,
+                    heuristic,
+                    quiescence_depth,
+                    draw_score,
+                );
+                best = best.max(score);
+                alpha = alpha.max(score);
+                if alpha >= beta {
+                    break;
+                }
+            }
+            best
+        }
+        Color::Black => {
+            let mut best = SCORE_WIN;
+            for legal_move in &legal_moves {
+                let score = alphabeta_with_draw(
+                    &board.play_move(legal_move),
+                    depth - 1,
+                    alpha,
+                    beta,
+                    heuristic,
+                    quiescence_depth,
+                    draw_score,
+                );
+                best = best.min(score);
+                beta = beta.min(score);
+                if alpha >= beta {
+                    break;
+                }
+            }
+            best
+        }
+    }
+}
+
+/// Resolve corner fights past the search horizon. The side to move may either accept the
+/// heuristic score as it stands or play a loud move, for up to depth more plies. Standing pat on
+/// a drawn finish adds draw_score to the heuristic
+pub fn quiescence(
+    board: &Board,
+    depth: i32,
+    mut alpha: i32,
+    mut beta: i32,
+    heuristic: impl Fn(&Board) -> i32 + Copy,
+    draw_score: i32,
+) -> i32 {
+    let mut best = heuristic(board);
+    if draw_score != 0 && board.is_draw() {
+        best += draw_score;
+    }
+    if depth == 0 {
+        return best;
+    }
+
+    for loud_move in board.loud_moves() {
+        match board.turn {
+            Color::White => alpha = alpha.max(best),
+            Color::Black => beta = beta.min(best),
+        }
+        if alpha >= beta {
+            break;
+        }
+        let score = quiescence(
+            &board.play_move(&loud_move),
+            depth - 1,
+            alpha,
+            beta,
+            heuristic,
+            draw_score,
+        );
+        best = match board.turn {
+            Color::White => best.max(score),
+            Color::Black => best.min(score),
+        };
+    }
+    best
+}
+
+/// Search to the end of the game with alpha-beta, returning the final score (White discs minus
+/// Black discs) under perfect play
+pub fn solve_exact(board: &Board, mut alpha: i32, mut beta: i32) -> i32 {
+    let legal_moves = board.legal_moves();
+    if legal_moves.is_empty() {
+        let opponent = board.change_turn();
+        if opponent.legal_moves().is_empty() {
+            return board.score();
+        }
+        return solve_exact(&opponent, alpha, beta);
+    }
+
+    match board.turn {
+        Color::White => {
+            let mut best = SCORE_LOSS;
+            for legal_move in &legal_moves {
+                let score = solve_exact(&board.play_move(legal_move), alpha, beta);
+                best = best.max(score);
+                alpha = alpha.max(score);
+                if alpha >= beta {
+                    break;
+                }
+            }
+            best
+        }
+        Color::Black => {
+            let mut best = SCORE_WIN;
+            for legal_move in &legal_moves {
+                let score = solve_exact(&board.play_move(legal_move), alpha, beta);
+                best = best.min(score);
+                beta = beta.min(score);
+                if alpha >= beta {
+                    break;
+                }
+            }
+            best
+        }
+    }
+}
+
+/// Alpha-beta over the root moves, returning the first move that achieves the best score
+pub fn alphabeta_root(
+    board: &Board,
+    depth: i32,
+    alpha: i32,
+    beta: i32,
+    heuristic: impl Fn(&Board) -> i32 + Copy,
+    quiescence_depth: i32,
+) -> (Posn, i32) {
+    alphabeta_root_with_draw(board, depth, alpha, beta, heuristic, quiescence_depth, 0)
+}
+
+/// alphabeta_root, scoring drawn finishes as draw_score
+pub fn alphabeta_root_with_draw(
+    board: &Board,
+    depth: i32,
+    mut alpha: i32,
+    mut beta: i32,
+    heuristic: impl Fn(&Board) -> i32 + Copy,
+    quiescence_depth: i32,
+    draw_score: i32,
+) -> (Posn, i32) {
+    let legal_moves = board.legal_moves();
+    let mut best_move = legal_moves[0];
+    let mut best_score = match board.turn {
+        Color::White => SCORE_LOSS,
+        Color::Black => SCORE_WIN,
+    };
+
+    for legal_move in &legal_moves {
+        let score = alphabeta_with_draw(
+            &board.play_move(legal_move),
+            depth - 1,
+            alpha,
+            beta,
+            heuristic,
+            quiescence_depth,
+            draw_score,
+        );
+        match board.turn {
+            Color::White if score > best_score => {
+                best_score = score;
+                best_move = *legal_move;
+                alpha = alpha.max(score);
+            }
+            Color::Black if score < best_score => {
+                best_score = score;
+                best_move = *legal_move;
+                beta = beta.min(score);
+            }
+            _ => {}
+        }
+        if alpha >= beta {
+            break;
+        }
+    }
+    (best_move, best_score)
+}
+
+/// Bound on every score, won for White at the top and for Black at the bottom (SCORE_LOSS). Kept
+/// well inside i32 so that scores can be negated, and windows around them widened, without
+/// overflowing
+pub const SCORE_WIN: i32 = 1_000_000;
+pub const SCORE_LOSS: i32 = -SCORE_WIN;
+
+/// What each filled square costs a win. It's more than any final margin, so a sooner win always
+/// beats a bigger one, and a bigger win breaks ties between equally soon ones
+const FILLED_SQUARE_COST: i32 = (ROWS * COLS) as i32 + 1;
+
+/// Score of a finished game: SCORE_WIN, less FILLED_SQUARE_COST for each filled square, plus the
+/// final margin for a White win, its mirror image for a Black win, and 0 for a draw. Winning
+/// sooner, or losing later, leaves fewer squares filled and so scores better for the winner
+pub fn terminal_score(board: &Board) -> i32 {
+    let filled = (ROWS * COLS - board.count_empty()) as i32;
+    match board.winner() {
+        Some(Color::White) => SCORE_WIN - FILLED_SQUARE_COST * filled + board.score(),
+        Some(Color::Black) => SCORE_LOSS + FILLED_SQUARE_COST * filled + board.score(),
+        None => 0,
+    }
+}
+
+/// Whether score is a forced win for White, as opposed to a heuristic estimate
+pub fn is_win_score(score: i32) -> bool {
+    score > SCORE_WIN - FILLED_SQUARE_COST * (ROWS * COLS) as i32
+}
+
+/// Whether score is a forced win for Black
+pub fn is_loss_score(score: i32) -> bool {
+    is_win_score(-score)
+}
+
+/// For a forced win or loss, the squares filled when the game ends and the final margin (White
+/// positive), undoing terminal_score
+pub fn decode_terminal(score: i32) -> Option<(usize, i32)> {
+    if !is_win_score(score) && !is_loss_score(score) {
+        return None;
+    }
+    let below_win = SCORE_WIN - score.abs();
+    // below_win is FILLED_SQUARE_COST * filled - margin, with the margin between 1 and 64
+    let filled = (below_win + FILLED_SQUARE_COST - 1) / FILLED_SQUARE_COST;
+    let margin = FILLED_SQUARE_COST * filled - below_win;
+    Some((filled as usize, margin * score.signum()))
+}
+
+/// For a forced win or loss found from board, how many more discs are placed before the game
+/// ends. Passes don't fill squares, so they aren't counted
+pub fn mate_distance(score: i32, board: &Board) -> Option<usize> {
+    let filled_now = ROWS * COLS - board.count_empty();
+    decode_terminal(score).map(|(filled, _)| filled.saturating_sub(filled_now))
+}
+
+/// Half-width of the window placed around the previous depth's score
+pub const ASPIRATION_DELTA: i32 = 16;
+
+/// Loud moves quiescence search may play past the horizon before it must stop
+pub const QUIESCENCE_DEPTH: i32 = 4;
+
+#[derive(Debug, Clone, Copy)]
+pub struct SearchOptions {
+    pub max_depth: i32,
+    pub heuristic: fn(&Board) -> i32,
+    /// Half-width of the aspiration window, or None to always search with a full window
+    pub aspiration_delta: Option<i32>,
+    /// Plies of loud moves to search past max_depth, or None to evaluate leaves directly
+    pub quiescence_depth: Option<i32>,
+    /// How much worse than 0 a draw is for the side searching, in the heuristic's units. Positive
+    /// steers a stronger agent away from draws, negative steers a weaker one toward them
+    pub contempt: i32,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: 4,
+            heuristic: edge_corner_heuristic,
+            aspiration_delta: Some(ASPIRATION_DELTA),
+            quiescence_depth: None,
+            contempt: 0,
+        }
+    }
+}
+
+impl SearchOptions {
+    pub fn draw_score(&self, root: &Board) -> i32 {
+        draw_score(root, self.contempt)
+    }
+}
+
+/// The White-positive score of a drawn finish when the side to move at root searches with
+/// contempt
+pub fn draw_score(root: &Board, contempt: i32) -> i32 {
+    match root.turn {
+        Color::White => -contempt,
+        Color::Black => contempt,
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SearchStats {
+    /// Aspiration searches whose score landed at or above the window
+    pub fail_highs: u32,
+    /// Aspiration searches whose score landed at or below the window
+    pub fail_lows: u32,
+}
+
+/// Search with a narrow window around the expected score. Each time the true score lands outside
+/// it, the failing side is widened by twice as much, until it reaches the full window
+pub fn aspiration_search(
+    board: &Board,
+    depth: i32,
+    expected: i32,
+    delta: i32,
+    options: &SearchOptions,
+    stats: &mut SearchStats,
+) -> (Posn, i32) {
+    let (mut low_delta, mut high_delta) = (delta, delta);
+    let mut alpha = expected.saturating_sub(low_delta).max(SCORE_LOSS);
+    let mut beta = expected.saturating_add(high_delta).min(SCORE_WIN);
+    loop {
+        let (best_move, score) = alphabeta_root_with_draw(
+            board,
+            depth,
+            alpha,
+            beta,
+            options.heuristic,
+            options.quiescence_depth.unwrap_or(0),
+            options.draw_score(board),
+        );
+        if score <= alpha && alpha > SCORE_LOSS {
+            stats.fail_lows += 1;
+            low_delta = low_delta.saturating_mul(2);
+            alpha = expected.saturating_sub(low_delta).max(SCORE_LOSS);
+        } else if score >= beta && beta < SCORE_WIN {
+            stats.fail_highs += 1;
+            high_delta = high_delta.saturating_mul(2);
+            beta = expected.saturating_add(high_delta).min(SCORE_WIN);
+        } else {
+            return (best_move, score);
+        }
+    }
+}
+
+/// Search depths 1 through max_depth in turn, seeding each depth's aspiration window with the
+/// previous depth's score
+pub fn iterative_deepening(
+    board: &Board,
+    options: &SearchOptions,
+    stats: &mut SearchStats,
+) -> (Posn, i32) {
+    iterative_deepening_with(board, options, stats, |_, _| {})
+}
+
+/// iterative_deepening, also handing each depth's best move and score to on_depth as it finishes
+pub fn iterative_deepening_with(
+    board: &Board,
+    options: &SearchOptions,
+    stats: &mut SearchStats,
+    mut on_depth: impl FnMut(i32, (Posn, i32)),
+) -> (Posn, i32) {
+    let full_window = |depth| {
+        alphabeta_root_with_draw(
+            board,
+            depth,
+            SCORE_LOSS,
+            SCORE_WIN,
+            options.heuristic,
+            options.quiescence_depth.unwrap_or(0),
+            options.draw_score(board),
+        )
+    };
+    let mut result = full_window(1);
+    on_depth(1, result);
+    for depth in 2..=options.max_depth {
+        result = match options.aspiration_delta {
+            Some(delta) => aspiration_search(board, depth, result.1, delta, options, stats),
+            None => full_window(depth),
+        };
+        on_depth(depth, result);
+    }
+    result
+}
+
+/// Random positions reached by random play from a random opening, for benchmarking
+#[cfg(feature = "rand")]
+fn random_positions(count: usize) -> Vec<Board> {
+    (0..count)
+        .map(|_| {
+            let mut board = Board::random_set_up();
+            for _ in 0..rng::ThreadRng.index(40) {
+                if board.is_over() {
+                    break;
+                }
+                if board.legal_moves().is_empty() {
+                    board = board.change_turn();
+                }
+                board = board.play_move(&random_agent(&board));
+            }
+            board
+        })
+        .collect()
+}
+
+/// Time iterative deepening with and without aspiration windows. That both pick the same move is
+/// the aspiration_search_matches_full_window test
+#[cfg(feature = "rand")]
+fn bench_aspiration() {
+    let positions: Vec<Board> = random_positions(50)
+        .into_iter()
+        .filter(|board| !board.legal_moves().is_empty())
+        .collect();
+    let full_width_options = SearchOptions {
+        max_depth: 5,
+        aspiration_delta: None,
+        ..SearchOptions::default()
+    };
+    let aspiration_options = SearchOptions {
+        max_depth: 5,
+        ..SearchOptions::default()
+    };
+    let mut stats = SearchStats::default();
+
+    let start = std::time::Instant::now();
+    for board in &positions {
+        iterative_deepening(board, &full_width_options, &mut stats);
+    }
+    let full_width_time = start.elapsed();
+
+    let start = std::time::Instant::now();
+    for board in &positions {
+        iterative_deepening(board, &aspiration_options, &mut stats);
+    }
+    let aspiration_time = start.elapsed();
+
+    println!("Full-width iterative deepening: {:?}", full_width_time);
+    println!(
+        "Aspiration iterative deepening: {:?} ({} fail highs, {} fail lows)",
+        aspiration_time, stats.fail_highs, stats.fail_lows
+    );
+}
+
+/// Count how often shallow search picks a move that hands the opponent a corner when it didn't
+/// have to, with and without quiescence search at the leaves
+#[cfg(feature = "rand")]
+fn bench_quiescence() {
+    let gives_up_corner = |board: &Board, posn: &Posn| board.concedes_corner(posn).is_some();
+    let positions: Vec<Board> = random_positions(500)
+        .into_iter()
+        .filter(|board| {
+            let legal_moves = board.legal_moves();
+            legal_moves.iter().any(|posn| gives_up_corner(board, posn))
+                && legal_moves.iter().any(|posn| !gives_up_corner(board, posn))
+        })
+        .collect();
+
+    for max_depth in [1, 2] {
+        for quiescence_depth in [None, Some(QUIESCENCE_DEPTH)] {
+            let options = SearchOptions {
+                max_depth,
+                quiescence_depth,
+                ..SearchOptions::default()
+            };
+            let start = std::time::Instant::now();
+            let corners_given = positions
+                .iter()
+                .filter(|board| {
+                    let (posn, _) =
+                        iterative_deepening(board, &options, &mut SearchStats::default());
+                    gives_up_corner(board, &posn)
+                })
+                .count();
+            println!(
+                "Depth {}, quiescence {:?}: needlessly gave up a corner in {} of {} positions ({:?})",
+                max_depth,
+                quiescence_depth,
+                corners_given,
+                positions.len(),
+                start.elapsed()
+            );
+        }
+    }
+}
+
+/// Compare a cold search against one that reuses the tables from searching two plies earlier
+#[cfg(feature = "rand")]
+fn bench_tree_reuse() {
+    let (mut cold_nodes, mut warm_nodes) = (0, 0);
+    for board in random_positions(20) {
+        let Some(reply) = board
+            .legal_moves()
+            .first()
+            .map(|posn| board.play_move(posn))
+        else {
+            continue;
+        };
+        let Some(continuation) = reply
+            .legal_moves()
+            .first()
+            .map(|posn| reply.play_move(posn))
+        else {
+            continue;
+        };
+        if continuation.legal_moves().is_empty() {
+            continue;
+        }
+
+        let mut warm = MinimaxAgent::new(4, edge_corner_heuristic);
+        warm.rank_moves(&board);
+        let before = warm.context.nodes;
+        warm.rank_moves(&continuation);
+        warm_nodes += warm.context.nodes - before;
+
+        let mut cold = MinimaxAgent::new(4, edge_corner_heuristic);
+        cold.rank_moves(&continuation);
+        cold_nodes += cold.context.nodes;
+    }
+    println!("Cold search nodes: {}", cold_nodes);
+    println!("Reused search nodes: {}", warm_nodes);
+}
+
+/// Time the legality scan with each way of stepping to a neighbor: offset arithmetic as in
+/// neighbor_in_dir, and the NEIGHBORS lookup that legal_moves uses. Then time legal_moves itself
+#[cfg(feature = "rand")]
+fn bench_neighbor_table() {
+    const ROUNDS: usize = 20;
+    let positions = random_positions(1000);
+    let count_legal = |step: &dyn Fn(&Posn, Dir) -> Option<Posn>| {
+        let start = std::time::Instant::now();
+        let mut count = 0;
+        for _ in 0..ROUNDS {
+            for board in &positions {
+                count += POSNS
+                    .iter()
+                    .filter(|posn| {
+                        !board.piece_at(posn).is_occupied()
+                            && DIRS.into_iter().any(|dir| {
+                                let mut run = 0;
+                                let mut curr = step(posn, dir);
+                                while let Some(square) = curr {
+                                    match board.piece_at(&square).color() {
+                                        Some(color) if color == board.turn => return run > 0,
+                                        Some(_) => run += 1,
+                                        None => return false,
+                                    }
+                                    curr = step(&square, dir);
+                                }
+                                false
+                            })
+                    })
+                    .count();
+            }
+        }
+        (count, start.elapsed())
+    };
+    let (arithmetic_count, arithmetic_time) = count_legal(&|posn, dir| posn.neighbor_in_dir(&dir));
+    let (table_count, table_time) = count_legal(&|posn, dir| posn.neighbor(dir));
+
+    let start = std::time::Instant::now();
+    let mut legal_moves_count = 0;
+    for _ in 0..ROUNDS {
+        for board in &positions {
+            legal_moves_count += board.legal_moves().len();
+        }
+    }
+    let legal_moves_time = start.elapsed();
+
+    assert_eq!(arithmetic_count, table_count);
+    assert_eq!(table_count, legal_moves_count);
+    println!("Neighbors by offset arithmetic: {:?}", arithmetic_time);
+    println!("Neighbors by table: {:?}", table_time);
+    println!("legal_moves: {:?}", legal_moves_time);
+}
+
+/// Time 10,000 MCTS playouts on one thread and on four. Whether the parallel agent plays as well
+/// is for the ignored parallel_mcts_keeps_its_strength test, which plays enough games to tell
+#[cfg(feature = "sim")]
+fn bench_mcts() {
+    let board = Board::new();
+    let mut serial_time = None;
+    for (threads, parallelism) in [
+        (1, mcts::Parallelism::Root),
+        (4, mcts::Parallelism::Root),
+        (4, mcts::Parallelism::Tree),
+    ] {
+        let options = mcts::MctsOptions {
+            iterations: 10_000,
+            threads,
+            parallelism,
+            seed: 0,
+        };
+        let start = std::time::Instant::now();
+        mcts::search(&board, &options);
+        let elapsed = start.elapsed();
+        let serial = *serial_time.get_or_insert(elapsed);
+        println!(
+            "MCTS, 10000 playouts on {} threads ({:?}): {:?}, {:.2}x",
+            threads,
+            parallelism,
+            elapsed,
+            serial.as_secs_f64() / elapsed.as_secs_f64()
+        );
+    }
+}
+
+/// Time a search far too deep to finish, cancelled once 10ms in and once before it starts. That
+/// each still returns a legal move within a second is the cancelled_search_returns_quickly test
+#[cfg(feature = "rand")]
+fn bench_cancellation() {
+    let board = Board::new().play_move(&Posn::try_from_alphanumeric("e3").unwrap());
+    for delay in [Some(std::time::Duration::from_millis(10)), None] {
+        let flag = Arc::new(AtomicBool::new(delay.is_none()));
+        let mut agent = MinimaxAgent::new(12, edge_corner_heuristic);
+        agent.cancel_with(flag.clone());
+
+        let start = std::time::Instant::now();
+        let posn = std::thread::scope(|scope| {
+            let search = scope.spawn(|| agent.choose_move(&board));
+            if let Some(delay) = delay {
+                std::thread::sleep(delay);
+                flag.store(true, std::sync::atomic::Ordering::Relaxed);
+            }
+            search.join().expect("search thread panicked")
+        });
+        let elapsed = start.elapsed();
+        println!(
+            "Depth 12 cancelled after {:?}: {} in {:?}",
+            delay.unwrap_or_default(),
+            posn,
+            elapsed
+        );
+    }
+}
+
+/// Time the same fixed-depth alpha-beta written two ways, copying the board for each move or
+/// making and unmaking moves on one board, checking they agree on every score
+#[cfg(feature = "rand")]
+fn bench_make_unmake() {
+    const SEARCH_DEPTH: i32 = 6;
+    fn cloning(board: &Board, depth: i32, mut alpha: i32, mut beta: i32) -> i32 {
+        if depth == 0 || board.is_over() {
+            return edge_corner_heuristic(board);
+        }
+        let legal_moves = board.legal_moves();
+        if legal_moves.is_empty() {
+            return cloning(&board.change_turn(), depth, alpha, beta);
+        }
+        for posn in legal_moves {
+            let score = cloning(&board.play_move(&posn), depth - 1, alpha, beta);
+            match board.turn {
+                Color::White => alpha = alpha.max(score),
+                Color::Black => beta = beta.min(score),
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+        match board.turn {
+            Color::White => alpha,
+            Color::Black => beta,
+        }
+    }
+    fn in_place(board: &mut Board, depth: i32, mut alpha: i32, mut beta: i32) -> i32 {
+        if depth == 0 || board.is_over() {
+            return edge_corner_heuristic(board);
+        }
+        let legal_moves = board.legal_moves();
+        if legal_moves.is_empty() {
+            board.turn = board.turn.opponent();
+            let score = in_place(board, depth, alpha, beta);
+            board.turn = board.turn.opponent();
+            return score;
+        }
+        for posn in legal_moves {
+            let undo = board.apply_in_place(&posn);
+            let score = in_place(board, depth - 1, alpha, beta);
+            board.undo_move(&undo);
+            match board.turn {
+                Color::White => alpha = alpha.max(score),
+                Color::Black => beta = beta.min(score),
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+        match board.turn {
+            Color::White => alpha,
+            Color::Black => beta,
+        }
+    }
+
+    let positions = random_positions(100);
+    let start = std::time::Instant::now();
+    let copied: Vec<i32> = positions
+        .iter()
+        .map(|board| cloning(board, SEARCH_DEPTH, SCORE_LOSS, SCORE_WIN))
+        .collect();
+    let cloning_time = start.elapsed();
+    let start = std::time::Instant::now();
+    let made: Vec<i32> = positions
+        .iter()
+        .map(|board| in_place(&mut board.clone(), SEARCH_DEPTH, SCORE_LOSS, SCORE_WIN))
+        .collect();
+    let in_place_time = start.elapsed();
+    assert_eq!(copied, made, "make/unmake search disagrees with copying");
+    println!(
+        "Depth {} alpha-beta over {} positions: copying boards {:?}, make/unmake {:?} ({:.2}x)",
+        SEARCH_DEPTH,
+        positions.len(),
+        cloning_time,
+        in_place_time,
+        cloning_time.as_secs_f64() / in_place_time.as_secs_f64()
+    );
+}
+
+/// Time the same searches with no subscriber, with one whose filter turns tracing off, and with
+/// one taking every trace event into a sink. The first two should match: disabled call sites
+/// skip their arguments
+#[cfg(all(feature = "rand", feature = "std"))]
+fn bench_logging() {
+    const SEARCH_DEPTH: i32 = 6;
+    let positions: Vec<Board> = random_positions(40)
+        .into_iter()
+        .filter(|board| !board.legal_moves().is_empty())
+        .collect();
+    let search_all = || {
+        let start = std::time::Instant::now();
+        let mut nodes = 0;
+        for board in &positions {
+            let mut agent = MinimaxAgent::new(SEARCH_DEPTH, edge_corner_heuristic);
+            agent.choose_move(board);
+            nodes += agent.context.nodes;
+        }
+        (start.elapsed(), nodes)
+    };
+    let subscriber = |level: &str| {
+        tracing_subscriber::fmt()
+            .with_env_filter(level)
+            .with_writer(std::io::sink)
+            .finish()
+    };
+
+    let (off, nodes) = search_all();
+    let (filtered, _) = tracing::subscriber::with_default(subscriber("warn"), search_all);
+    let (traced, _) = tracing::subscriber::with_default(subscriber("trace"), search_all);
+    println!(
+        "Logging {} depth {} searches ({} nodes): none {:?}, filtered to warn {:?} ({:.2}x), \
+         tracing everything {:?} ({:.2}x)",
+        positions.len(),
+        SEARCH_DEPTH,
+        nodes,
+        off,
+        filtered,
+        filtered.as_secs_f64() / off.as_secs_f64(),
+        traced,
+        traced.as_secs_f64() / off.as_secs_f64()
+    );
+}
+
+/// Time is_over against the move-list version it replaced over random and finished positions,
+/// checking they agree, then time a deep search, which asks is_over at every node
+#[cfg(feature = "rand")]
+fn bench_is_over() {
+    const ROUNDS: usize = 20;
+    const SEARCH_DEPTH: i32 = 9;
+    let mut positions = random_positions(1000);
+    positions.extend((0..100).map(|_| {
+        let (mut black, mut white): (AgentFn, AgentFn) = (random_agent, random_agent);
+        play_from(Board::random_set_up(), &mut black, &mut white).final_board
+    }));
+
+    let time = |is_over: &dyn Fn(&Board) -> bool| {
+        let start = std::time::Instant::now();
+        let mut over = vec![];
+        for _ in 0..ROUNDS {
+            over = positions.iter().map(is_over).collect();
+        }
+        (over, start.elapsed())
+    };
+    let (by_move_lists, move_lists_time) = time(&|board| {
+        board.legal_moves().is_empty() && board.change_turn().legal_moves().is_empty()
+    });
+    let (short_circuit, short_circuit_time) = time(&Board::is_over);
+    assert_eq!(by_move_lists, short_circuit);
+    println!("is_over by move lists: {:?}", move_lists_time);
+    println!("is_over short-circuiting: {:?}", short_circuit_time);
+
+    let board = Board::new().play_move(&Posn::try_from_alphanumeric("e3").unwrap());
+    let start = std::time::Instant::now();
+    let (posn, score) = alphabeta_root(
+        &board,
+        SEARCH_DEPTH,
+        SCORE_LOSS,
+        SCORE_WIN,
+        edge_corner_heuristic,
+        0,
+    );
+    println!(
+        "Depth {} search: {} ({}) in {:?}",
+        SEARCH_DEPTH,
+        posn,
+        score,
+        start.elapsed()
+    );
+}
+
+/// Time legality checks over many random positions, comparing the allocating
+/// potential_flipped_pieces path against the non-allocating would_flip path
+#[cfg(feature = "rand")]
+fn bench_legality() {
+    let positions = random_positions(1000);
+
+    let start = std::time::Instant::now();
+    let mut allocating_count = 0;
+    for board in &positions {
+        allocating_count += POSNS
+            .into_iter()
+            .filter(|posn| {
+                !board.piece_at(posn).is_occupied()
+                    && !board.potential_flipped_pieces(posn).is_empty()
+            })
+            .count();
+    }
+    let allocating_time = start.elapsed();
+
+    let start = std::time::Instant::now();
+    let mut non_allocating_count = 0;
+    for board in &positions {
+        non_allocating_count += board.legal_moves().len();
+    }
+    let non_allocating_time = start.elapsed();
+
+    assert_eq!(allocating_count, non_allocating_count);
+    println!("potential_flipped_pieces: {:?}", allocating_time);
+    println!("would_flip: {:?}", non_allocating_time);
+}
+
+#[cfg(feature = "sim")]
+#[derive(Debug, Clone, Copy)]
+struct TournamentConfig {
+    /// Games to play from fresh random openings, when there's no opening pool
+    games: usize,
+    /// Play each of this many distinct openings twice, once with each agent as Black
+    openings: Option<usize>,
+    /// Random moves played to reach each opening in the pool
+    opening_plies: usize,
+    /// Print every pooled opening's results
+    verbose: bool,
+    /// Stop each game once its winner is decided and score it for that side
+    early_adjudicate: bool,
+    /// Play games on every core at once instead of one after another
+    parallel: bool,
+    /// Derive every opening from this, so runs repeat exactly; random openings without it
+    seed: Option<u64>,
+    /// Where the posterior starts: Beta(2, 2), or what an earlier run saved with --resume
+    prior: SavedPosterior,
+    /// Blocks of consecutive games to report separately and test for drift; 1 reports none
+    blocks: usize,
+}
+
+/// Search depth of the tournament's minimax agent
+#[cfg(feature = "sim")]
+const TOURNAMENT_DEPTH: i32 = 3;
+
+#[cfg(feature = "sim")]
+impl Default for TournamentConfig {
+    fn default() -> Self {
+        Self {
+            games: config::get().simulate.games,
+            openings: None,
+            opening_plies: 4,
+            verbose: false,
+            early_adjudicate: false,
+            parallel: true,
+            seed: None,
+            prior: SavedPosterior::default(),
+            blocks: 1,
+        }
+    }
+}
+
+/// The shape parameters of a tournament's Beta posterior on the standard agent's win rate, as
+/// --resume saves them between runs so the evidence keeps adding up
+#[cfg(feature = "sim")]
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+struct SavedPosterior {
+    shape_a: f64,
+    shape_b: f64,
+}
+
+/// Beta(2, 2): a win rate near a half is likelier than one near the ends, before any games
+#[cfg(feature = "sim")]
+impl Default for SavedPosterior {
+    fn default() -> Self {
+        Self {
+            shape_a: 2.0,
+            shape_b: 2.0,
+        }
+    }
+}
+
+#[cfg(feature = "sim")]
+impl SavedPosterior {
+    fn of(posterior: &Beta) -> Self {
+        Self {
+            shape_a: posterior.shape_a(),
+            shape_b: posterior.shape_b(),
+        }
+    }
+
+    fn to_beta(self) -> Result<Beta, OthelloError> {
+        Ok(Beta::new(self.shape_a, self.shape_b)?)
+    }
+
+    /// What an earlier run saved to path, or the default prior if nothing has been saved there
+    fn load(path: &str) -> Result<Self, OthelloError> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => {
+                let saved: Self = serde_json::from_str(&text)?;
+                // Refuse shapes Beta can't take now, rather than after a whole tournament
+                saved.to_beta()?;
+                Ok(saved)
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn save(&self, path: &str) -> Result<(), OthelloError> {
+        std::fs::write(path, serde_json::to_string_pretty(self)? + "\n")?;
+        Ok(())
+    }
+}
+
+/// Up to count openings reached by plies random moves, no two the same up to symmetry. Gives up
+/// early if there aren't that many distinct openings to be found
+#[cfg(feature = "sim")]
+fn opening_pool(count: usize, plies: usize, rng: &mut impl MoveRng) -> Vec<Board> {
+    let mut seen = std::collections::HashSet::new();
+    let mut pool = vec![];
+    for _ in 0..count * 100 {
+        if pool.len() == count {
+            break;
+        }
+        let mut board = Board::new();
+        for _ in 0..plies {
+            if board.is_over() {
+                break;
+            }
+            if board.legal_moves().is_empty() {
+                board = board.change_turn();
+                continue;
+            }
+            board = board.play_move(&random_agent_with(&board, rng));
+        }
+        if seen.insert(stats::canonical_key(&board)) {
+            pool.push(board);
+        }
+    }
+    pool
+}
+
+/// Everything a finished tournament's summary reports
+#[cfg(feature = "sim")]
+struct TournamentOutcome {
+    /// The opening pool, when the tournament used one
+    pool: Option<Vec<Board>>,
+    minimax_wins: usize,
+    standard_wins: usize,
+    ties: usize,
+    /// Beta posterior on the standard agent's win rate
+    posterior: Beta,
+    /// Per game: whether minimax won, or None for a draw
+    minimax_results: Vec<Option<bool>>,
+    /// The position each game started from
+    starts: Vec<Board>,
+    /// The last position of each game, unfinished where early adjudication stopped it
+    finals: Vec<Board>,
+    /// Per game: whether early adjudication stopped it
+    adjudicated_games: Vec<bool>,
+    /// Each game's moves from its opening, passes included
+    moves: Vec<Vec<Move>>,
+    /// The color minimax played in each game
+    minimax_colors: Vec<Color>,
+    adjudicated: usize,
+    plies_saved: usize,
+}
+
+#[cfg(feature = "sim")]
+impl TournamentOutcome {
+    /// The last positions of the games played to the end, leaving out adjudicated ones whose
+    /// disc counts say little about the result
+    fn finished_finals(&self) -> Vec<Board> {
+        self.finals
+            .iter()
+            .zip(&self.adjudicated_games)
+            .filter(|(_, adjudicated)| !**adjudicated)
+            .map(|(board, _)| board.clone())
+            .collect()
+    }
+
+    /// The heading for the final-disc histogram, noting any adjudicated games it leaves out
+    fn histogram_heading(&self) -> String {
+        match self.adjudicated_games.iter().filter(|a| **a).count() {
+            0 => "Final disc differences (White minus Black)".to_string(),
+            1 => "Final disc differences (White minus Black), 1 adjudicated game left out"
+                .to_string(),
+            left_out => format!(
+                "Final disc differences (White minus Black), {} adjudicated games left out",
+                left_out
+            ),
+        }
+    }
+}
+
+/// Play the tournament, across threads unless config says otherwise, telling each observer the
+/// running totals after every game. With no observers the posterior's mean and interval aren't
+/// computed until the summary
+#[cfg(feature = "sim")]
+fn play_tournament(
+    config: &TournamentConfig,
+    observers: &mut [Box<dyn observer::TournamentObserver>],
+) -> Result<TournamentOutcome, OthelloError> {
+    // Each game as a start position and the minimax agent's color. With a seed, game i's opening
+    // comes from seed + i, so it's the same however the games are spread over threads
+    let pool = config.openings.map(|count| match config.seed {
+        Some(seed) => opening_pool(count, config.opening_plies, &mut rng::SeededRng::new(seed)),
+        None => opening_pool(count, config.opening_plies, &mut rng::ThreadRng),
+    });
+    let games: Vec<(Board, Color)> = match &pool {
+        Some(pool) => pool
+            .iter()
+            .flat_map(|opening| {
+                [
+                    (opening.clone(), Color::Black),
+                    (opening.clone(), Color::White),
+                ]
+            })
+            .collect(),
+        None => (0..config.games)
+            .map(|i| {
+                let start = match config.seed {
+                    Some(seed) => Board::random_set_up_with(&mut rng::SeededRng::new(
+                        seed.wrapping_add(i as u64),
+                    )),
+                    None => Board::random_set_up(),
+                };
+                (start, Color::Black)
+            })
+            .collect(),
+    };
+    let total_games = games.len();
+
+    // Games finish in any order when parallel, so observers hear about them as they finish
+    let tally = std::sync::Mutex::new((
+        TournamentTally {
+            prior: config.prior,
+            ..TournamentTally::default()
+        },
+        observers,
+    ));
+    let play = |(start, minimax_color): (Board, Color)| {
+        let game = play_tournament_game(config, start.clone(), minimax_color)?;
+        let (tally, observers) = &mut *tally.lock().expect("tournament tally poisoned");
+        tally.add(&game, minimax_color);
+        if !observers.is_empty() {
+            let progress = tally.progress(total_games)?;
+            for observer in observers.iter_mut() {
+                observer.on_game(&progress)?;
+            }
+        }
+        Ok((start, game, minimax_color))
+    };
+    let played: Result<Vec<(Board, TournamentGame, Color)>, OthelloError> = if config.parallel {
+        games.into_par_iter().map(play).collect()
+    } else {
+        games.into_iter().map(play).collect()
+    };
+    let played = played?;
+    let (tally, observers) = tally.into_inner().expect("tournament tally poisoned");
+    for observer in observers.iter_mut() {
+        observer.finish()?;
+    }
+
+    let mut starts = vec![];
+    let mut finals = vec![];
+    let mut adjudicated_games = vec![];
+    let mut moves = vec![];
+    let mut minimax_colors = vec![];
+    let mut minimax_results = vec![];
+    for (start, game, minimax_color) in played {
+        minimax_results.push(game.winner.map(|color| color == minimax_color));
+        starts.push(start);
+        finals.push(game.board);
+        adjudicated_games.push(game.adjudicated);
+        moves.push(game.moves);
+        minimax_colors.push(minimax_color);
+    }
+    Ok(TournamentOutcome {
+        pool,
+        minimax_wins: tally.minimax_wins,
+        standard_wins: tally.standard_wins,
+        ties: tally.ties,
+        posterior: tally.posterior()?,
+        minimax_results,
+        starts,
+        finals,
+        adjudicated_games,
+        moves,
+        minimax_colors,
+        adjudicated: tally.adjudicated,
+        plies_saved: tally.plies_saved,
+    })
+}
+
+/// One tournament game's last position and result
+#[cfg(feature = "sim")]
+struct TournamentGame {
+    board: Board,
+    moves: Vec<Move>,
+    winner: Option<Color>,
+    /// Whether early adjudication stopped the game, and the empty squares it left unplayed
+    adjudicated: bool,
+    plies_saved: usize,
+}
+
+/// Play one game between fresh agents, so it comes out the same whatever was played before
+#[cfg(feature = "sim")]
+fn play_tournament_game(
+    config: &TournamentConfig,
+    start: Board,
+    minimax_color: Color,
+) -> Result<TournamentGame, OthelloError> {
+    let mut minimax_player = MinimaxAgent::new(TOURNAMENT_DEPTH, edge_corner_heuristic);
+    let mut standard_player: AgentFn = |board| heuristic_agent(board, standard_heuristic);
+    let (black, white): (&mut dyn Agent, &mut dyn Agent) = match minimax_color {
+        Color::Black => (&mut minimax_player, &mut standard_player),
+        Color::White => (&mut standard_player, &mut minimax_player),
+    };
+    let log = events::MoveLog::default();
+    let mut sinks: Vec<Box<dyn events::EventSink>> = vec![Box::new(log.clone())];
+    let (board, plies_saved) = if config.early_adjudicate {
+        play_until_decided(start, black, white, &mut sinks)?
+    } else {
+        (play_with_events(start, black, white, &mut sinks)?, 0)
+    };
+    let adjudicated = !board.is_over();
+    let winner = if adjudicated {
+        board.decided_winner()
+    } else {
+        board.winner()
+    };
+    Ok(TournamentGame {
+        board,
+        moves: log.moves(),
+        winner,
+        adjudicated,
+        plies_saved,
+    })
+}
+
+/// Running totals over the games finished so far, in whatever order they finished
+#[cfg(feature = "sim")]
+#[derive(Debug, Default)]
+struct TournamentTally {
+    minimax_wins: usize,
+    standard_wins: usize,
+    ties: usize,
+    adjudicated: usize,
+    plies_saved: usize,
+    prior: SavedPosterior,
+}
+
+#[cfg(feature = "sim")]
+impl TournamentTally {
+    fn add(&mut self, game: &TournamentGame, minimax_color: Color) {
+        match game.winner {
+            Some(color) if color == minimax_color => self.minimax_wins += 1,
+            Some(_) => self.standard_wins += 1,
+            None => self.ties += 1,
+        }
+        if game.adjudicated {
+            self.adjudicated += 1;
+            self.plies_saved += game.plies_saved;
+        }
+    }
+
+    /// Beta posterior on the standard agent's win rate, from the prior. Its wins count toward
+    /// the first shape parameter
+    fn posterior(&self) -> Result<Beta, OthelloError> {
+        Ok(Beta::new(
+            self.prior.shape_a + self.standard_wins as f64,
+            self.prior.shape_b + self.minimax_wins as f64,
+        )?)
+    }
+
+    fn progress(&self, total_games: usize) -> Result<observer::TournamentProgress, OthelloError> {
+        let n = self.posterior()?;
+        Ok(observer::TournamentProgress {
+            games_done: self.minimax_wins + self.standard_wins + self.ties,
+            total_games,
+            minimax_wins: self.minimax_wins,
+            standard_wins: self.standard_wins,
+            ties: self.ties,
+            mean: n.shape_a() / (n.shape_a() + n.shape_b()),
+            low: n.inverse_cdf(0.05),
+            high: n.inverse_cdf(0.95),
+        })
+    }
+}
+
+#[cfg(feature = "sim")]
+fn run_tournament(
+    config: &TournamentConfig,
+    observers: &mut [Box<dyn observer::TournamentObserver>],
+    report_path: Option<&str>,
+    resume_path: Option<&str>,
+) -> Result<(), OthelloError> {
+    let outcome = play_tournament(config, observers)?;
+    if let Some(path) = resume_path {
+        SavedPosterior::of(&outcome.posterior).save(path)?;
+    }
+    let TournamentOutcome {
+        pool,
+        minimax_wins,
+        standard_wins,
+        ties: num_ties,
+        posterior: n,
+        minimax_results,
+        adjudicated,
+        plies_saved,
+        ..
+    } = &outcome;
+
+    println!(
+        "Minimax depth {} w/ edge corner heuristic vs standard heuristic: ",
+        TOURNAMENT_DEPTH
+    );
+    match &pool {
+        Some(pool) => {
+            println!(
+                "{} distinct openings of {} plies, each played with both colors",
+                pool.len(),
+                config.opening_plies
+            );
+            println!(
+                "Minimax wins: {}, Standard wins: {}, Ties: {}",
+                minimax_wins, standard_wins, num_ties
+            );
+        }
+        None => println!(
+            "Black wins: {}, White wins: {}, Ties: {}",
+            minimax_wins, standard_wins, num_ties
+        ),
+    }
+
+    println!(
+        "Credible Interval: {:.2}%, {:.2}%",
+        n.inverse_cdf(0.05) * 100.0,
+        n.inverse_cdf(0.95) * 100.0
+    );
+    if let Some(path) = resume_path {
+        println!(
+            "Resumed from Beta({}, {}); saved Beta({}, {}) to {}",
+            config.prior.shape_a,
+            config.prior.shape_b,
+            n.shape_a(),
+            n.shape_b(),
+            path
+        );
+    }
+    if config.early_adjudicate {
+        // Each skipped move would have filled one of the empty squares left, so this is exact
+        // unless a game would have ended with squares still empty
+        println!(
+            "Adjudicated {} games early, saving up to {} plies",
+            adjudicated, plies_saved
+        );
+    }
+
+    if let (Some(pool), true) = (&pool, config.verbose) {
+        let describe = |result: Option<bool>| match result {
+            Some(true) => "win",
+            Some(false) => "loss",
+            None => "draw",
+        };
+        for (opening, results) in pool.iter().zip(minimax_results.chunks(2)) {
+            println!(
+                "{}: minimax {} as Black, {} as White",
+                opening.to_position_string(),
+                describe(results[0]),
+                describe(results[1])
+            );
+        }
+    }
+
+    // COLUMNS is set by most shells; fall back to a standard terminal's width
+    let width = std::env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+        .unwrap_or(80);
+    if config.blocks > 1 {
+        println!("Results by block of games:");
+        print!(
+            "{}",
+            blocks::table(&blocks::summarize(minimax_results, config.blocks))
+        );
+    }
+
+    println!("{}:", outcome.histogram_heading());
+    print!(
+        "{}",
+        analysis::disc_histogram(&outcome.finished_finals(), width, RenderStyle::current())
+    );
+
+    if let Some(path) = report_path {
+        std::fs::write(path, report::markdown(config, &outcome))?;
+        println!("Wrote the report to {}", path);
+    }
+    Ok(())
+}
+
+/// tournament [--games N] [--openings K] [--opening-plies N] [--verbose] [--early-adjudicate]
+/// [--csv <path>] [--report <path.md>] [--seed N] [--sequential] [--resume <posterior.json>]
+/// [--blocks B], showing a status line as it plays, optionally recording it to a CSV file and
+/// writing a Markdown report at the end. --resume starts the posterior from the one saved there,
+/// if any, and saves the new one back. --blocks splits the results into B blocks of games with a
+/// test for drift between them
+#[cfg(feature = "sim")]
+fn tournament_command(args: &[String]) -> Result<(), OthelloError> {
+    let usage = || {
+        OthelloError::Usage(
+            "tournament [--games N] [--openings K] [--opening-plies N] [--verbose] \
+             [--early-adjudicate] [--csv <path>] [--report <path.md>] [--seed N] [--sequential] \
+             [--resume <posterior.json>] [--blocks B]"
+                .to_string(),
+        )
+    };
+    let mut config = TournamentConfig::default();
+    let mut observers: Vec<Box<dyn observer::TournamentObserver>> =
+        vec![Box::new(observer::StatusLine)];
+    let mut report_path = None;
+    let mut resume_path = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--verbose" => {
+                config.verbose = true;
+                continue;
+            }
+            "--early-adjudicate" => {
+                config.early_adjudicate = true;
+                continue;
+            }
+            "--sequential" => {
+                config.parallel = false;
+                continue;
+            }
+            _ => {}
+        }
+        let value = args.next().ok_or_else(usage)?;
+        match arg.as_str() {
+            "--games" => config.games = value.parse()?,
+            "--openings" => config.openings = Some(value.parse()?),
+            "--opening-plies" => config.opening_plies = value.parse()?,
+            "--csv" => observers.push(Box::new(observer::CsvObserver::create(value)?)),
+            "--report" => report_path = Some(value.as_str()),
+            "--seed" => config.seed = Some(value.parse()?),
+            "--resume" => {
+                config.prior = SavedPosterior::load(value)?;
+                resume_path = Some(value.as_str());
+            }
+            "--blocks" => config.blocks = value.parse()?,
+            _ => return Err(usage()),
+        }
+    }
+    run_tournament(&config, &mut observers, report_path, resume_path)
+}
+
+fn play_human() -> Result<(), OthelloError> {
+    let args: Vec<String> = std::env::args().collect();
+    let settings = &config::get().play;
+    let start_rule = if settings.reversi {
+        StartRule::Reversi
+    } else {
+        StartRule::Othello
+    };
+    let first_player = if settings.white_first {
+        Color::White
+    } else {
+        Color::Black
+    };
+    let start = Board::with_rules(start_rule, first_player);
+    let computer =
+        opponent_agent(&settings.opponent)?.map(|agent| (settings.computer_color, agent));
+
+    // "--moves-file <path>" plays a scripted game, as does piping commands in on stdin
+    let board = match args.iter().position(|arg| arg == "--moves-file") {
+        Some(i) => {
+            let Some(path) = args.get(i + 1) else {
+                return Err(OthelloError::Usage("play --moves-file <path>".to_string()));
+            };
+            let mut input = std::io::BufReader::new(std::fs::File::open(path)?);
+            let mut sinks: Vec<Box<dyn events::EventSink>> =
+                vec![Box::new(events::ConsolePrinter::new(&start))];
+            play_interactive(start, computer, &mut input, true, &mut sinks)?
+        }
+        None => {
+            let batch = !std::io::stdin().is_terminal();
+            let mut sinks: Vec<Box<dyn events::EventSink>> =
+                vec![Box::new(events::ConsolePrinter::new(&start))];
+            play_interactive(start, computer, &mut *input::stdin(), batch, &mut sinks)?
+        }
+    };
+
+    println!("{}", board);
+    println!("Score: {:?}", board.score());
+    match board.winner() {
+        Some(color) => println!("{} wins!", color),
+        None if board.is_draw() => println!("Draw"),
+        None => println!("No winner"),
+    }
+    Ok(())
+}
+
+/// Search depth of the minimax opponent, and of the book opponent once it's out of its book
+const OPPONENT_DEPTH: i32 = 4;
+
+/// The computer opponent a config names, or None for "none"
+fn opponent_agent(name: &str) -> Result<Option<Box<dyn Agent>>, OthelloError> {
+    let agent: Box<dyn Agent> = match name {
+        "none" => return Ok(None),
+        #[cfg(feature = "rand")]
+        "random" => Box::new(random_agent as AgentFn),
+        "greedy" => Box::new(
+            (|board: &Board| match greedy_agent(board) {
+                Move::Place(posn) => posn,
+                Move::Pass => panic!("choose_move called with no legal moves"),
+            }) as AgentFn,
+        ),
+        "mesh" => Box::new(mesh_agent as AgentFn),
+        "minimax" => Box::new(MinimaxAgent {
+            contempt: config::get().play.contempt,
+            ..MinimaxAgent::new(OPPONENT_DEPTH, weights::engine_heuristic())
+        }),
+        "adaptive" => Box::new(AdaptiveAgent::from_config()),
+        "mobility" => Box::new(
+            (|board: &Board| match minimize_opponent_mobility_agent(board) {
+                Move::Place(posn) => posn,
+                Move::Pass => panic!("choose_move called with no legal moves"),
+            }) as AgentFn,
+        ),
+        #[cfg(feature = "sim")]
+        "book" => {
+            let Some(path) = &config::get().play.book else {
+                return Err(OthelloError::Usage(
+                    "--opponent book --book <book.json>".to_string(),
+                ));
+            };
+            Box::new(book::BookAgent {
+                book: book::Book::load(path)?,
+                fallback: MinimaxAgent::new(OPPONENT_DEPTH, weights::engine_heuristic()),
+            })
+        }
+        _ => {
+            return Err(OthelloError::Parse(format!(
+                "Unknown opponent \"{}\" (expected none, random, greedy, mesh, minimax, adaptive, \
+                 mobility or book)",
+                name
+            )))
+        }
+    };
+    Ok(Some(agent))
+}
+
+/// Print the board for the REPL, with legal moves marked when --show-moves is on
+fn show_board(board: &Board) {
+    if config::get().play.show_moves {
+        println!("{}", board.legal_move_grid(RenderStyle::current()));
+    } else {
+        println!("{}", board);
+    }
+}
+
+/// The REPL's answer to "moves": the side to move and each of its legal moves
+fn format_moves(board: &Board) -> String {
+    let moves: Vec<String> = board.legal_moves().iter().map(Posn::to_string).collect();
+    format!("{}'s moves: {}", board.turn, moves.join(", "))
+}
+
+/// The REPL's answer to "counts" below the grid: the legal moves, most discs flipped first
+fn format_captures(board: &Board) -> String {
+    let moves: Vec<String> = board
+        .moves_by_capture()
+        .iter()
+        .map(|(posn, count)| format!("{} {}", posn, count))
+        .collect();
+    format!("Most flips first: {}", moves.join(", "))
+}
+
+/// The REPL's answer to "info": each side's discs, moves, potential mobility and discs next to
+/// the corners, then the empty regions with their parity and the squares neither side can take
+fn format_info(board: &Board) -> String {
+    let (black_moves, white_moves) = board.mobilities();
+    let mut info = format!("Position: {}\n", board.to_position_string());
+    for (color, moves) in [(Color::Black, black_moves), (Color::White, white_moves)] {
+        info.push_str(&format!(
+            "{}: {} discs, {} moves, {} potential mobility, {} on X-squares, {} on C-squares\n",
+            color,
+            board.count_color_pieces(color),
+            moves,
+            board.potential_mobility(color),
+            board.x_square_count(color),
+            board.c_square_count(color)
+        ));
+    }
+    let regions: Vec<String> = board
+        .empty_regions()
+        .iter()
+        .map(|region| {
+            let parity = if region.len() % 2 == 1 { "odd" } else { "even" };
+            format!("{} ({} squares, {})", region[0], region.len(), parity)
+        })
+        .collect();
+    info.push_str(&format!("Empty regions: {}\n", regions.join(", ")));
+    let quiet: Vec<String> = board.quiet_empties().iter().map(Posn::to_string).collect();
+    info.push_str(&format!(
+        "Empty squares neither side can move to: {}",
+        if quiet.is_empty() {
+            "none".to_string()
+        } else {
+            quiet.join(", ")
+        }
+    ));
+    info
+}
+
+/// Play a game from commands read one per line, optionally against a computer agent playing the
+/// given color. In batch mode the first invalid line ends the game instead of being retried.
+/// Returns the last position, which is only finished if nobody quit and the input didn't run out
+fn play_interactive(
+    mut board: Board,
+    mut computer: Option<(Color, Box<dyn Agent>)>,
+    input: &mut dyn input::LineInput,
+    batch: bool,
+    sinks: &mut [Box<dyn events::EventSink>],
+) -> Result<Board, OthelloError> {
+    println!("Enter a legal square (e.g. \"e4\" or \"E4\") or its index (\"28\") to play a move");
+    println!("Enter \"moves\" to see all legal moves");
+    println!("Enter \"turn\" to see whose move it is, or \"setturn black|white\" to change it");
+    println!("Enter \"counts\" to see how many discs each legal move would flip");
+    println!("Enter \"info\" to see each side's mobility and risky squares, and the empty regions");
+    println!("Enter \"hint\" to see the engine's move, why it likes it and the line it expects");
+    println!("Enter \"hint deep\" to see how the engine's choice changes with search depth");
+    println!("Enter \"hint quick\" for an instant suggestion from a shallow search");
+    println!("Enter \"hash\" to print a fingerprint of the position for bug reports");
+    println!("Enter \"export <file>.svg\" to save an image of the board");
+    println!(
+        "Enter \"save <file>\" to save the game so far as a transcript, adding --dense to leave passes out"
+    );
+    println!("Enter \"quit\" to quit the game");
+    show_board(&board);
+    let first_player = board.turn;
+    let mut history: Vec<Move> = vec![];
+    let mut line_number = 0;
+    // Whether TurnStarted has gone out for the side to move, which REPL commands don't change
+    let mut turn_started = false;
+
+    while !board.is_over() {
+        let color = board.turn;
+        let legal_moves = board.legal_moves();
+        if legal_moves.is_empty() {
+            events::emit(sinks, GameEvent::Pass { color })?;
+            board = board.change_turn();
+            history.push(Move::Pass);
+            turn_started = false;
+            continue;
+        }
+        if !turn_started {
+            events::emit(sinks, GameEvent::TurnStarted { color, legal_moves })?;
+            turn_started = true;
+        }
+
+        if let Some((computer_color, agent)) = &mut computer {
+            if color == *computer_color {
+                let posn = agent.choose_move(&board);
+                let flipped = board.potential_flipped_pieces(&posn);
+                board = board.play_move(&posn);
+                history.push(Move::Place(posn));
+                turn_started = false;
+                events::emit(
+                    sinks,
+                    GameEvent::MovePlayed {
+                        color,
+                        posn,
+                        flipped,
+                    },
+                )?;
+                continue;
+            }
+        }
+
+        let mut line = String::new();
+        if input.read_line_for(&board, &mut line)? == 0 {
+            break;
+        }
+        line_number += 1;
+        let input = line.trim();
+        if input.is_empty() {
+            continue;
+        }
+
+        if input == "moves" {
+            println!("{}", format_moves(&board));
+            continue;
+        }
+
+        if input == "turn" {
+            println!("{} to move", board.turn);
+            continue;
+        }
+
+        if let Some(color) = input.strip_prefix("setturn ") {
+            match color.trim().parse::<Color>() {
+                Ok(color) => {
+                    if color != board.turn {
+                        println!(
+                            "Warning: handing the move to {} may leave an irregular position, and saved transcripts won't record it",
+                            color
+                        );
+                    }
+                    board = board.with_turn(color);
+                    turn_started = false;
+                    println!("{} to move", board.turn);
+                }
+                Err(err) => println!("{}", err),
+            }
+            continue;
+        }
+
+        if input == "counts" {
+            print!("{}", board.flip_count_grid(RenderStyle::current()));
+            println!("{}", format_captures(&board));
+            continue;
+        }
+
+        if input == "info" {
+            println!("{}", format_info(&board));
+            continue;
+        }
+
+        if input == "hash" {
+            println!("{}", board.fingerprint());
+            continue;
+        }
+
+        if input == "hint quick" {
+            println!("Try {}", quick_hint(&board));
+            continue;
+        }
+
+        if input == "hint deep" {
+            let rows =
+                analysis::evaluate_at_depths(&board, &[1, 2, 3, 4, 5], weights::engine_heuristic());
+            print!("{}", analysis::format_depth_table(&board, &rows));
+            continue;
+        }
+
+        if input == "hint" {
+            let mut agent = MinimaxAgent::with_table(
+                config::get().play.hint_depth,
+                weights::engine_heuristic(),
+                table::ONE_SHOT_MEGABYTES,
+            );
+            let analysis = agent.analyze(&board);
+            println!(
+                "{}",
+                MinimaxAgent::explain_ranked(&board, &analysis.ranked_moves)
+            );
+            let line: Vec<String> = analysis
+                .principal_variation
+                .iter()
+                .map(Move::to_string)
+                .collect();
+            println!("Best line: {}", line.join(" "));
+            println!("Transposition table: {}", agent.context.table_stats());
+            continue;
+        }
+
+        if input == "quit" {
+            break;
+        }
+
+        if let Some(path) = input.strip_prefix("export ") {
+            let options = svg::SvgOptions {
+                last_move: match history.last() {
+                    Some(Move::Place(posn)) => Some(*posn),
+                    _ => None,
+                },
+                ..svg::SvgOptions::default()
+            };
+            std::fs::write(path.trim(), board.to_svg(&options))?;
+            println!("Saved {}", path.trim());
+            continue;
+        }
+
+        if let Some(rest) = input.strip_prefix("save ") {
+            let (path, style) = match rest.trim().strip_suffix("--dense") {
+                Some(path) => (path.trim(), transcript::TranscriptStyle::Dense),
+                None => (rest.trim(), transcript::TranscriptStyle::Explicit),
+            };
+            let record = transcript::GameRecord::with_first_player(history.clone(), first_player);
+            std::fs::write(path, record.to_transcript(style))?;
+            println!("Saved {}", path);
+            continue;
+        }
+
+        let posn = match parse_move(input) {
+            Ok(Move::Place(posn)) if board.is_legal(&posn) => posn,
+            // Passes happen by themselves, so a typed pass always has legal moves left
+            Ok(_) => {
+                println!("Invalid move");
+                if batch {
+                    println!("Stopping at line {}: \"{}\"", line_number, input);
+                    break;
+                }
+                continue;
+            }
+            Err(err) => {
+                println!("Invalid input: {}", err);
+                if batch {
+                    println!("Stopping at line {}: \"{}\"", line_number, input);
+                    break;
+                }
+                continue;
+            }
+        };
+        let flipped = board.potential_flipped_pieces(&posn);
+        board = board.play_move(&posn);
+        history.push(Move::Place(posn));
+        turn_started = false;
+        events::emit(
+            sinks,
+            GameEvent::MovePlayed {
+                color,
+                posn,
+                flipped,
+            },
+        )?;
+    }
+
+    if board.is_over() {
+        events::emit(
+            sinks,
+            GameEvent::GameOver {
+                result: board.winner(),
+            },
+        )?;
+    }
+    Ok(board)
+}
+
+/// analyze <transcript or file> [depth] [blunder threshold] [--snapshot-dir dir]
+/// [--ownership timeline.csv]
+fn analyze_command(args: &[String]) -> Result<(), OthelloError> {
+    let mut args = args.to_vec();
+    let snapshot_dir = match args.iter().position(|arg| arg == "--snapshot-dir") {
+        Some(i) if i + 1 < args.len() => {
+            let dir = args.remove(i + 1);
+            args.remove(i);
+            Some(dir)
+        }
+        Some(_) => {
+            return Err(OthelloError::Usage(
+                "analyze ... --snapshot-dir <directory>".to_string(),
+            ))
+        }
+        None => None,
+    };
+    let ownership_csv = match args.iter().position(|arg| arg == "--ownership") {
+        Some(i) if i + 1 < args.len() => {
+            let path = args.remove(i + 1);
+            args.remove(i);
+            Some(path)
+        }
+        Some(_) => {
+            return Err(OthelloError::Usage(
+                "analyze ... --ownership <timeline.csv>".to_string(),
+            ))
+        }
+        None => None,
+    };
+    let Some(source) = args.first() else {
+        return Err(OthelloError::Usage(
+            "analyze <transcript or file> [depth] [blunder threshold] [--snapshot-dir dir] \
+             [--ownership timeline.csv]"
+                .to_string(),
+        ));
+    };
+    let depth: i32 = args.get(1).map(|d| d.parse()).transpose()?.unwrap_or(4);
+    let threshold: i32 = args.get(2).map(|t| t.parse()).transpose()?.unwrap_or(10);
+
+    let replay = transcript::load_transcript(source)?.replay()?;
+    let mut context = search::SearchContext::default();
+    let rows = analysis::analyze_game(&replay, depth, weights::engine_heuristic(), &mut context);
+    print!("{}", analysis::format_analysis(&replay, &rows, threshold));
+    println!("Transposition table: {}", context.table_stats());
+
+    if let Some(dir) = snapshot_dir {
+        std::fs::create_dir_all(&dir)?;
+        for (i, mv) in replay.moves.iter().enumerate() {
+            let options = svg::SvgOptions {
+                last_move: match mv {
+                    Move::Place(posn) => Some(*posn),
+                    Move::Pass => None,
+                },
+                ..svg::SvgOptions::default()
+            };
+            let path = std::path::Path::new(&dir).join(format!("ply-{:03}.svg", i + 1));
+            std::fs::write(path, replay.boards[i + 1].to_svg(&options))?;
+        }
+        println!("Saved {} snapshots to {}", replay.moves.len(), dir);
+    }
+
+    if let Some(path) = ownership_csv {
+        let timeline = analysis::OwnershipTimeline::from_replay(&replay);
+        println!("Times each square was flipped:");
+        print!("{}", timeline.flip_count_board());
+        std::fs::write(&path, timeline.to_csv())?;
+        println!("Saved the ownership timeline to {}", path);
+    }
+    Ok(())
+}
+
+/// stats <transcript file> [min ply] [top N] [max ply]
+fn stats_command(args: &[String]) -> Result<(), OthelloError> {
+    let Some(path) = args.first() else {
+        return Err(OthelloError::Usage(
+            "stats <transcript file> [min ply] [top N] [max ply]".to_string(),
+        ));
+    };
+    let min_ply: usize = args.get(1).map(|k| k.parse()).transpose()?.unwrap_or(0);
+    let top_n: usize = args.get(2).map(|n| n.parse()).transpose()?.unwrap_or(10);
+    let max_ply: usize = args.get(3).map(|m| m.parse()).transpose()?.unwrap_or(20);
+
+    let mut stats = stats::PositionStats::new(max_ply.max(min_ply));
+    for record in transcript::load_records(path)? {
+        stats.add_game(&record)?;
+    }
+
+    println!("{} distinct positions", stats.len());
+    for entry in stats.most_common(min_ply, top_n) {
+        println!(
+            "Ply {}, {} to move: seen {} times, {}W/{}D/{}L ({:.1}%)",
+            entry.ply,
+            entry.board.turn,
+            entry.visits,
+            entry.wins,
+            entry.draws,
+            entry.losses,
+            entry.win_rate() * 100.0
+        );
+        if let Some((posn, win_rate, visits)) = stats.best_move_by_winrate(&entry.board) {
+            println!(
+                "Best move by win rate: {} ({:.1}% over {} games)",
+                posn,
+                win_rate * 100.0,
+                visits
+            );
+        }
+        println!("{}", entry.board);
+    }
+    Ok(())
+}
+
+/// Replay the transcript in the file at path, one move per line or all on one, handing each move
+/// and the position it led to to on_ply
+fn replay_file(
+    path: &str,
+    on_ply: impl FnMut(&Move, &Board),
+) -> Result<transcript::Replay, OthelloError> {
+    let text = std::fs::read_to_string(path)?;
+    let (first_player, text) = transcript::split_first_player(&text);
+    transcript::replay_with(&transcript::parse_transcript(text)?, first_player, on_ply)
+}
+
+/// --replay <file>: print every position of a recorded game, stopping at the first illegal move
+fn replay_command(args: &[String]) -> Result<(), OthelloError> {
+    let Some(path) = args.first() else {
+        return Err(OthelloError::Usage(
+            "--replay <transcript file>".to_string(),
+        ));
+    };
+    let replay = replay_file(path, |mv, board| {
+        println!("{} played {}", board.turn.opponent(), mv);
+        println!("{}", board);
+    })?;
+
+    let board = replay.final_board();
+    println!("Score: {:?}", board.score());
+    println!(
+        "Disc difference: {}",
+        analysis::sparkline(&replay.score_history(), RenderStyle::current())
+    );
+    Ok(())
+}
+
+/// --serve <port> [black|white] [position string] or --connect <host:port>
+#[cfg(feature = "std")]
+fn network_command(mode: &str, args: &[String]) -> Result<(), OthelloError> {
+    let board = match mode {
+        "--serve" => {
+            let Some(port) = args.first() else {
+                return Err(OthelloError::Usage(
+                    "--serve <port> [black|white] [position string]".to_string(),
+                ));
+            };
+            let local_color = match args.get(1) {
+                Some(color) => color.parse()?,
+                None => Color::Black,
+            };
+            let start = match args.get(2) {
+                Some(position) => {
+                    Board::from_position_string(&args[2..].join(" ")).ok_or_else(|| {
+                        OthelloError::Parse(format!("Malformed position \"{}\"", position))
+                    })?
+                }
+                None => Board::new(),
+            };
+            network::serve(port.parse()?, local_color, start, network::read_local_move)?
+        }
+        _ => {
+            let Some(addr) = args.first() else {
+                return Err(OthelloError::Usage("--connect <host:port>".to_string()));
+            };
+            network::connect(addr, network::read_local_move)?
+        }
+    };
+
+    println!("Score: {:?}", board.score());
+    match board.winner() {
+        Some(color) => println!("{} wins!", color),
+        None if board.is_draw() => println!("Draw"),
+        None => println!("No winner"),
+    }
+    Ok(())
+}
+
+/// The othello command line: reads the config file and global flags, then runs the subcommand
+/// named by the first argument, or a tournament and a game when there is none
+pub fn run() -> Result<(), OthelloError> {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    // A broken config file shouldn't stop "config path" from showing where it is
+    let mut settings = match config::load() {
+        Ok((settings, warnings)) => {
+            for warning in warnings {
+                eprintln!("Warning: {}", warning);
+            }
+            settings
+        }
+        Err(err) => {
+            eprintln!("Warning: ignoring the config file: {}", err);
+            config::Config::default()
+        }
+    };
+    // Flags like "--ascii" or "--opponent greedy" anywhere on the command line beat the file
+    settings.apply_args(&mut args)?;
+    if let Some(style) = settings.render.style {
+        RenderStyle::choose(style);
+    }
+    config::set(settings);
+    // "--weights <path>" anywhere plays and analyzes with a weighted heuristic from that file
+    if let Some(i) = args.iter().position(|arg| arg == "--weights") {
+        let Some(path) = args.get(i + 1) else {
+            return Err(OthelloError::Usage("--weights <weights.toml>".to_string()));
+        };
+        weights::select(weights::WeightedHeuristic::load(path)?);
+        args.drain(i..=i + 1);
+    }
+    // "--log-level <filter>" anywhere logs to stderr, as OTHELLO_LOG would
+    let log_level = match args.iter().position(|arg| arg == "--log-level") {
+        Some(i) => {
+            let Some(level) = args.get(i + 1).cloned() else {
+                return Err(OthelloError::Usage(
+                    "--log-level <level or filter>".to_string(),
+                ));
+            };
+            args.drain(i..=i + 1);
+            Some(level)
+        }
+        None => None,
+    };
+    logging::init(log_level.as_deref())?;
+    let command = (!args.is_empty()).then(|| args.remove(0));
+    match command.as_deref() {
+        Some("analyze") => analyze_command(&args),
+        Some("solve") => solve_command(&args),
+        #[cfg(feature = "sim")]
+        Some("book") => book::command(&args),
+        Some("--replay") => replay_command(&args),
+        #[cfg(feature = "std")]
+        Some(mode @ ("--serve" | "--connect")) => network_command(mode, &args),
+        Some("stats") => stats_command(&args),
+        #[cfg(feature = "rand")]
+        Some("fuzz") => fuzz::run(),
+        #[cfg(feature = "sim")]
+        Some("compare-heuristics") => tuning::compare_command(&args),
+        #[cfg(feature = "sim")]
+        Some("tune") => tuning::tune_command(&args),
+        Some("perft") => tree::run(&args),
+        #[cfg(feature = "sim")]
+        Some("mcts") => mcts::command(&args),
+        Some("config") => config::command(&args),
+        #[cfg(feature = "sim")]
+        Some("selfplay") => selfplay::run(&args),
+        Some("play") => play_human(),
+        #[cfg(feature = "rand")]
+        Some("puzzle") => puzzle::run(&args),
+        #[cfg(feature = "rand")]
+        Some("ladder") => ladder::run(&args),
+        #[cfg(feature = "rand")]
+        Some("bench") => {
+            bench_legality();
+            bench_neighbor_table();
+            bench_is_over();
+            bench_aspiration();
+            bench_tree_reuse();
+            bench_quiescence();
+            bench_cancellation();
+            #[cfg(feature = "std")]
+            bench_logging();
+            bench_make_unmake();
+            #[cfg(feature = "sim")]
+            bench_mcts();
+            Ok(())
+        }
+        #[cfg(feature = "sim")]
+        Some("tournament") => tournament_command(&args),
+        _ => {
+            #[cfg(feature = "sim")]
+            run_tournament(
+                &TournamentConfig::default(),
+                &mut [Box::new(observer::StatusLine)],
+                None,
+                None,
+            )?;
+            play_human()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,136 @@
+//! Structured logging through tracing, off unless asked for. Games and moves get spans, chosen
+//! moves a debug event with their score, and the search trace events for cutoffs and table hits.
+//! The coz progress points are separate and unaffected.
+//!
+//! With no filter, no subscriber is installed at all, so every tracing call site is disabled and
+//! costs one check without formatting its arguments. Logging to stderr needs the std feature;
+//! without it a filter is refused.
+
+#[cfg(feature = "std")]
+use tracing_subscriber::EnvFilter;
+
+use crate::error::{OthelloError, Result};
+
+/// The environment variable read for a filter when there's no --log-level, with the same syntax:
+/// a level such as "debug", or directives such as "othello::search=trace"
+pub const LOG_ENV: &str = "OTHELLO_LOG";
+
+/// Where each game's per-ply events go, so a filter can take them alone
+pub const GAME_TARGET: &str = "othello::game";
+
+/// Log to stderr with the filter in level, or else in OTHELLO_LOG; with neither, stay off
+#[cfg(feature = "std")]
+pub fn init(level: Option<&str>) -> Result<()> {
+    let directives = match level {
+        Some(level) => level.to_string(),
+        None => match std::env::var(LOG_ENV) {
+            Ok(directives) if !directives.trim().is_empty() => directives,
+            _ => return Ok(()),
+        },
+    };
+    let filter = EnvFilter::builder().parse(&directives).map_err(|err| {
+        OthelloError::Parse(format!("Invalid log filter {}: {}", directives, err))
+    })?;
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::IsTerminal::is_terminal(&std::io::stderr()))
+        .try_init()
+        .map_err(|err| OthelloError::Usage(format!("logging is already set up: {}", err)))
+}
+
+/// Without the std feature there's nowhere to log to, so only an absent filter is accepted
+#[cfg(not(feature = "std"))]
+pub fn init(level: Option<&str>) -> Result<()> {
+    match level
+        .map(str::to_string)
+        .or_else(|| std::env::var(LOG_ENV).ok())
+    {
+        Some(directives) if !directives.trim().is_empty() => Err(OthelloError::Usage(
+            "logging needs othello built with the std feature".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::tests::{ScriptedAgent, PASS_GAME};
+    use crate::transcript::GameRecord;
+    use crate::{play_from, Board, Color, Move};
+    use std::sync::{Arc, Mutex};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    /// How many game and move spans were opened and events logged to GAME_TARGET
+    #[derive(Debug, Default, Clone, Copy, PartialEq)]
+    struct LogCounts {
+        games: usize,
+        moves: usize,
+        game_events: usize,
+    }
+
+    /// A subscriber layer that only counts, to see what a game logs without printing any of it
+    struct CountingLayer(Arc<Mutex<LogCounts>>);
+
+    impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for CountingLayer {
+        fn on_new_span(
+            &self,
+            attrs: &tracing::span::Attributes<'_>,
+            _id: &tracing::span::Id,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            let mut counts = self.0.lock().unwrap();
+            match attrs.metadata().name() {
+                "game" => counts.games += 1,
+                "move" => counts.moves += 1,
+                _ => {}
+            }
+        }
+
+        fn on_event(
+            &self,
+            event: &tracing::Event<'_>,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            if event.metadata().target() == GAME_TARGET {
+                self.0.lock().unwrap().game_events += 1;
+            }
+        }
+    }
+
+    /// PASS_GAME played by scripted agents under a counting subscriber logs one game span, and one
+    /// move span and one event per ply, passes included
+    #[test]
+    fn game_logs_a_span_per_move() {
+        let replay = GameRecord::from_transcript(PASS_GAME)
+            .and_then(|record| record.replay())
+            .unwrap_or_else(|err| panic!("pass game didn't replay: {}", err));
+        let (mut black_moves, mut white_moves) = (vec![], vec![]);
+        for (board, mv) in replay.boards.iter().zip(&replay.moves) {
+            if let Move::Place(posn) = mv {
+                match board.turn {
+                    Color::Black => black_moves.push(*posn),
+                    Color::White => white_moves.push(*posn),
+                }
+            }
+        }
+        let mut black = ScriptedAgent(black_moves.into_iter());
+        let mut white = ScriptedAgent(white_moves.into_iter());
+        let counts = Arc::new(Mutex::new(LogCounts::default()));
+        let subscriber = tracing_subscriber::registry().with(CountingLayer(counts.clone()));
+        tracing::subscriber::with_default(subscriber, || {
+            play_from(Board::new(), &mut black, &mut white);
+        });
+        let counts = *counts.lock().unwrap();
+        let plies = replay.moves.len();
+        let expected = LogCounts {
+            games: 1,
+            moves: plies,
+            game_events: plies,
+        };
+        if counts != expected {
+            panic!("a game of {} plies logged {:?}", plies, counts);
+        }
+    }
+}
@@ -0,0 +1,486 @@
+//! Monte Carlo tree search: grow a tree of positions by UCT selection, score each new leaf by
+//! playing a random game from it to the end, and play the root move that was visited most.
+//!
+//! Several threads can share the work. By default each grows its own tree from its own seed and
+//! the trees vote with their root visit counts (root parallelization), which repeats exactly for
+//! the same seed. Parallelism::Tree has the threads grow one tree behind a mutex instead, adding a
+//! virtual loss to every node on a thread's path until its playout comes back so the others look
+//! elsewhere; it shares what each thread learns, but depends on how the threads are scheduled.
+
+use std::sync::Mutex;
+
+use rayon::prelude::*;
+
+use crate::error::{OthelloError, Result};
+use crate::rng::{MoveRng, SeededRng};
+use crate::transcript;
+use crate::{forced_move, random_agent_with, Agent, Board, Color, Move, Posn, COLS, ROWS};
+
+/// How strongly UCT favors moves it has tried little over moves that have done well
+const EXPLORATION: f64 = std::f64::consts::SQRT_2;
+
+/// Losses charged to each node on a thread's path while its playout is out, in tree parallelism
+const VIRTUAL_LOSS: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Parallelism {
+    /// A tree per thread, merged by visit count at the end
+    Root,
+    /// One tree shared by every thread, with virtual loss
+    Tree,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MctsOptions {
+    /// Playouts in all, split as evenly as possible between the threads. Searches make at least
+    /// one whatever this says
+    pub iterations: usize,
+    pub threads: usize,
+    pub parallelism: Parallelism,
+    /// Thread i draws from SeededRng::new(seed + i)
+    pub seed: u64,
+}
+
+impl Default for MctsOptions {
+    fn default() -> Self {
+        Self {
+            iterations: 10_000,
+            threads: 1,
+            parallelism: Parallelism::Root,
+            seed: 0,
+        }
+    }
+}
+
+/// What a search found: every root move with its visits, most visited first, and the move
+/// played
+#[derive(Debug, Clone)]
+pub struct MctsResult {
+    pub visits: Vec<(Move, u32)>,
+    pub best: Move,
+}
+
+struct Node {
+    board: Board,
+    /// The move that led here from the parent, played by mover
+    mv: Option<Move>,
+    mover: Color,
+    children: Vec<usize>,
+    untried: Vec<Move>,
+    visits: u32,
+    /// Playouts won by mover, with draws counting half
+    wins: f64,
+    virtual_loss: u32,
+}
+
+struct Tree {
+    nodes: Vec<Node>,
+}
+
+/// The moves out of board: its placements, the pass if it has none, or nothing once it's over
+fn moves_from(board: &Board) -> Vec<Move> {
+    if board.is_over() {
+        return vec![];
+    }
+    match board.legal_moves() {
+        posns if posns.is_empty() => vec![Move::Pass],
+        posns => posns.into_iter().map(Move::Place).collect(),
+    }
+}
+
+fn apply(board: &Board, mv: &Move) -> Board {
+    match mv {
+        Move::Place(posn) => board.play_move(posn),
+        Move::Pass => board.change_turn(),
+    }
+}
+
+/// A square index for placements and one past the board for the pass, to add up votes by
+fn move_key(mv: &Move) -> usize {
+    match mv {
+        Move::Place(posn) => posn.index(),
+        Move::Pass => ROWS * COLS,
+    }
+}
+
+/// Play random moves from board to the end of the game and return the winner
+fn playout(board: &Board, rng: &mut SeededRng) -> Option<Color> {
+    let mut board = board.clone();
+    while !board.is_over() {
+        board = if board.legal_moves().is_empty() {
+            board.change_turn()
+        } else {
+            board.play_move(&random_agent_with(&board, rng))
+        };
+    }
+    board.winner()
+}
+
+impl Tree {
+    fn new(root: &Board) -> Self {
+        Self {
+            nodes: vec![Node {
+                board: root.clone(),
+                mv: None,
+                mover: root.turn.opponent(),
+                children: vec![],
+                untried: moves_from(root),
+                visits: 0,
+                wins: 0.0,
+                virtual_loss: 0,
+            }],
+        }
+    }
+
+    /// Walk from the root by UCT to a node with an untried move, expand it, and return the path
+    /// with the new node last. Each node on the path takes virtual_loss until backup
+    fn descend(&mut self, rng: &mut SeededRng, virtual_loss: u32) -> Vec<usize> {
+        let mut path = vec![0];
+        let mut current = 0;
+        self.nodes[0].virtual_loss += virtual_loss;
+        while self.nodes[current].untried.is_empty() && !self.nodes[current].children.is_empty() {
+            current = self.best_child(current);
+            self.nodes[current].virtual_loss += virtual_loss;
+            path.push(current);
+        }
+        let node = &mut self.nodes[current];
+        if !node.untried.is_empty() {
+            let mv = node.untried.swap_remove(rng.index(node.untried.len()));
+            let board = apply(&node.board, &mv);
+            let child = Node {
+                untried: moves_from(&board),
+                mover: node.board.turn,
+                board,
+                mv: Some(mv),
+                children: vec![],
+                visits: 0,
+                wins: 0.0,
+                virtual_loss,
+            };
+            let index = self.nodes.len();
+            self.nodes[current].children.push(index);
+            self.nodes.push(child);
+            path.push(index);
+        }
+        path
+    }
+
+    /// The child of parent with the highest upper confidence bound, counting virtual losses as
+    /// visits its mover lost
+    fn best_child(&self, parent: usize) -> usize {
+        let parent_visits = self.nodes[parent].visits + self.nodes[parent].virtual_loss;
+        let log_visits = (parent_visits.max(1) as f64).ln();
+        let bound = |child: usize| {
+            let node = &self.nodes[child];
+            let visits = (node.visits + node.virtual_loss).max(1) as f64;
+            node.wins / visits + EXPLORATION * (log_visits / visits).sqrt()
+        };
+        let children = &self.nodes[parent].children;
+        // First of the best, so ties go the same way every time
+        children
+            .iter()
+            .copied()
+            .reduce(|best, child| {
+                if bound(child) > bound(best) {
+                    child
+                } else {
+                    best
+                }
+            })
+            .unwrap()
+    }
+
+    /// Count a playout won by winner along path, taking back its virtual losses
+    fn backup(&mut self, path: &[usize], winner: Option<Color>, virtual_loss: u32) {
+        for &index in path {
+            let node = &mut self.nodes[index];
+            node.visits += 1;
+            node.virtual_loss -= virtual_loss;
+            node.wins += match winner {
+                Some(color) if color == node.mover => 1.0,
+                Some(_) => 0.0,
+                None => 0.5,
+            };
+        }
+    }
+
+    fn root_visits(&self) -> Vec<(Move, u32)> {
+        self.nodes[0]
+            .children
+            .iter()
+            .map(|&child| (self.nodes[child].mv.unwrap(), self.nodes[child].visits))
+            .collect()
+    }
+}
+
+/// Grow one tree from root for iterations playouts
+fn grow(root: &Board, iterations: usize, rng: &mut SeededRng) -> Tree {
+    let mut tree = Tree::new(root);
+    for _ in 0..iterations {
+        let path = tree.descend(rng, 0);
+        let winner = playout(&tree.nodes[*path.last().unwrap()].board, rng);
+        tree.backup(&path, winner, 0);
+    }
+    tree
+}
+
+/// Thread i's share of iterations: the same for every thread, with the remainder going one each
+/// to the first threads
+fn share(iterations: usize, threads: usize, thread: usize) -> usize {
+    iterations / threads + usize::from(thread < iterations % threads)
+}
+
+/// Search board, which must not be over, with options. Moves with the same visits keep the order
+/// the tree first met them in, and the first of the most visited is played
+pub fn search(board: &Board, options: &MctsOptions) -> MctsResult {
+    let threads = options.threads.max(1);
+    let iterations = options.iterations.max(1);
+    let seed = |thread: usize| options.seed.wrapping_add(thread as u64);
+    let root_visits: Vec<Vec<(Move, u32)>> = match options.parallelism {
+        Parallelism::Root => (0..threads)
+            .into_par_iter()
+            .map(|thread| {
+                let mut rng = SeededRng::new(seed(thread));
+                grow(board, share(iterations, threads, thread), &mut rng).root_visits()
+            })
+            .collect(),
+        Parallelism::Tree => {
+            let tree = Mutex::new(Tree::new(board));
+            std::thread::scope(|scope| {
+                for thread in 0..threads {
+                    let tree = &tree;
+                    scope.spawn(move || {
+                        let mut rng = SeededRng::new(seed(thread));
+                        for _ in 0..share(iterations, threads, thread) {
+                            let (path, leaf) = {
+                                let mut tree = tree.lock().unwrap();
+                                let path = tree.descend(&mut rng, VIRTUAL_LOSS);
+                                let leaf = tree.nodes[*path.last().unwrap()].board.clone();
+                                (path, leaf)
+                            };
+                            let winner = playout(&leaf, &mut rng);
+                            tree.lock().unwrap().backup(&path, winner, VIRTUAL_LOSS);
+                        }
+                    });
+                }
+            });
+            vec![tree.into_inner().unwrap().root_visits()]
+        }
+    };
+
+    let mut visits: Vec<(Move, u32)> = vec![];
+    for (mv, count) in root_visits.into_iter().flatten() {
+        match visits
+            .iter_mut()
+            .find(|(seen, _)| move_key(seen) == move_key(&mv))
+        {
+            Some((_, total)) => *total += count,
+            None => visits.push((mv, count)),
+        }
+    }
+    visits.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    let best = visits.first().map_or(Move::Pass, |(mv, _)| *mv);
+    MctsResult { visits, best }
+}
+
+/// Plays the most visited move of an MCTS search from each position, or its only move without
+/// searching
+#[derive(Debug, Clone, Copy)]
+pub struct MctsAgent {
+    pub options: MctsOptions,
+}
+
+impl Agent for MctsAgent {
+    fn choose_move(&mut self, board: &Board) -> Posn {
+        if let Some(posn) = forced_move(board) {
+            return posn;
+        }
+        match search(board, &self.options).best {
+            Move::Place(posn) => posn,
+            Move::Pass => panic!("choose_move called with no legal moves"),
+        }
+    }
+}
+
+/// mcts <transcript or file> [iterations] [--threads n] [--tree] [--seed n]: search the
+/// position the game reaches and print each move's visits. Iterations must be at least 1
+pub fn command(args: &[String]) -> Result<()> {
+    let usage = || {
+        OthelloError::Usage(
+            "mcts <transcript or file> [iterations, at least 1] [--threads n] [--tree] [--seed n]"
+                .to_string(),
+        )
+    };
+    let mut options = MctsOptions::default();
+    let mut positional = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--tree" => options.parallelism = Parallelism::Tree,
+            "--threads" => options.threads = args.next().ok_or_else(usage)?.parse()?,
+            "--seed" => options.seed = args.next().ok_or_else(usage)?.parse()?,
+            _ => positional.push(arg),
+        }
+    }
+    let Some(source) = positional.first() else {
+        return Err(usage());
+    };
+    if let Some(iterations) = positional.get(1) {
+        options.iterations = iterations.parse()?;
+    }
+    if options.iterations == 0 {
+        return Err(usage());
+    }
+
+    let replay = transcript::load_transcript(source)?.replay()?;
+    let board = replay.final_board();
+    if board.is_over() {
+        return Err(OthelloError::Usage(
+            "mcts needs a game that isn't over".to_string(),
+        ));
+    }
+    let start = std::time::Instant::now();
+    let result = search(board, &options);
+    println!("{}", board);
+    println!(
+        "{} playouts on {} threads ({:?}) in {:?}",
+        options.iterations,
+        options.threads.max(1),
+        options.parallelism,
+        start.elapsed()
+    );
+    println!("{:>6} {:>8} {:>6}", "move", "visits", "share");
+    for (mv, visits) in &result.visits {
+        println!(
+            "{:>6} {:>8} {:>5.1}%",
+            mv.to_string(),
+            visits,
+            *visits as f64 * 100.0 / options.iterations as f64
+        );
+    }
+    println!("Best: {}", result.best);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{opening_pool, play_from};
+    use statrs::distribution::{Beta, ContinuousCDF};
+
+    /// MCTS spends exactly its playouts however they're split between threads, and a root-parallel
+    /// search gives the same visits every time for the same seed. Zero iterations still makes one
+    /// playout, though the mcts command refuses them
+    #[test]
+    fn playouts_split_exactly_between_threads() {
+        let board = Board::new();
+        let visits = |result: &MctsResult| -> Vec<String> {
+            let counts = result.visits.iter();
+            counts
+                .map(|(mv, count)| format!("{} {}", mv, count))
+                .collect()
+        };
+        for parallelism in [Parallelism::Root, Parallelism::Tree] {
+            let options = MctsOptions {
+                iterations: 301,
+                threads: 3,
+                parallelism,
+                seed: 5,
+            };
+            let result = search(&board, &options);
+            let total: u32 = result.visits.iter().map(|(_, count)| count).sum();
+            let most = result.visits.iter().map(|(_, count)| *count).max();
+            let legal = matches!(result.best, Move::Place(posn) if board.is_legal(&posn));
+            let best_visits = result
+                .visits
+                .iter()
+                .find(|(mv, _)| mv.to_string() == result.best.to_string())
+                .map(|(_, count)| *count);
+            if total != 301 || !legal || best_visits != most {
+                panic!(
+                    "{:?} search spent {} of 301 playouts and played {}: {:?}",
+                    parallelism,
+                    total,
+                    result.best,
+                    visits(&result)
+                );
+            }
+            if parallelism == Parallelism::Root {
+                let again = search(&board, &options);
+                if visits(&again) != visits(&result) {
+                    panic!(
+                        "root-parallel search gave {:?}, then {:?}",
+                        visits(&result),
+                        visits(&again)
+                    );
+                }
+            }
+        }
+
+        // Asking for no playouts still makes one, rather than playing the pass
+        let options = MctsOptions {
+            iterations: 0,
+            ..MctsOptions::default()
+        };
+        let result = search(&board, &options);
+        let played = MctsAgent { options }.choose_move(&board);
+        if !matches!(result.best, Move::Place(posn) if board.is_legal(&posn))
+            || !board.is_legal(&played)
+        {
+            panic!(
+                "with no iterations the search played {} and the agent {}",
+                result.best, played
+            );
+        }
+        let args = ["e6".to_string(), "0".to_string()];
+        if !matches!(command(&args), Err(OthelloError::Usage(_))) {
+            panic!("mcts accepted 0 iterations");
+        }
+    }
+
+    /// Root-parallel MCTS on four threads is not much weaker than on one at equal total playouts.
+    /// Each thread's tree gets a quarter of the playouts, so some loss is expected; this bounds it.
+    /// Each of OPENINGS seeded random openings is played once with each color, and the 95% lower
+    /// bound on the four-thread agent's score minus the one-thread agent's, from a uniform prior on
+    /// its share of the points, must stay above -MARGIN
+    #[test]
+    #[ignore = "plays 120 MCTS games; run with cargo test --release -- --ignored"]
+    fn parallel_mcts_keeps_its_strength() {
+        const OPENINGS: usize = 60;
+        const OPENING_PLIES: usize = 30;
+        const PLAYOUTS: usize = 256;
+        const MARGIN: f64 = 0.35;
+        let agent = |threads, seed| MctsAgent {
+            options: MctsOptions {
+                iterations: PLAYOUTS,
+                threads,
+                parallelism: Parallelism::Root,
+                seed,
+            },
+        };
+        let mut points = 0.0;
+        let openings = opening_pool(OPENINGS, OPENING_PLIES, &mut SeededRng::new(0));
+        for (opening, start) in openings.iter().enumerate() {
+            let seed = (opening as u64).wrapping_mul(PLAYOUTS as u64);
+            let (mut serial, mut parallel) = (agent(1, seed), agent(4, seed));
+            for color in [Color::Black, Color::White] {
+                let last = match color {
+                    Color::Black => play_from(start.clone(), &mut parallel, &mut serial),
+                    Color::White => play_from(start.clone(), &mut serial, &mut parallel),
+                };
+                points += last.points(color);
+            }
+        }
+        let games = (2 * openings.len()) as f64;
+        let share =
+            Beta::new(points + 1.0, games - points + 1.0).unwrap_or_else(|err| panic!("{}", err));
+        let lower = 2.0 * share.inverse_cdf(0.05) - 1.0;
+        if lower < -MARGIN {
+            panic!(
+                "four-thread MCTS scored {} of {} against one thread at {} playouts, so its score \
+                 could be as much as {:.2} lower, more than {}",
+                points, games, PLAYOUTS, -lower, MARGIN
+            );
+        }
+    }
+}
@@ -0,0 +1,290 @@
+//! Two-player games over TCP. After a short handshake, each side sends its moves as
+//! newline-delimited coordinates ("e3"), or "pass" when it has no legal move. Moves are read with
+//! parse_move, so "E3", "20" and "--" are understood too:
+//!
+//! ```text
+//! server -> client: OTHELLO <version>
+//! client -> server: OTHELLO <version>
+//! server -> client: COLOR <Black|White>      (the client's color)
+//! server -> client: POSITION <start|position string>
+//! ```
+//!
+//! Either side may send "ERROR <reason>" before closing the connection. While it's the local
+//! player's turn, typing "hash" prints the position's fingerprint, to compare with the peer's.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+
+use crate::error::{OthelloError, Result};
+use crate::{parse_move, Board, Color, Move};
+
+const PROTOCOL_VERSION: u32 = 1;
+
+struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> Result<Self> {
+        Ok(Self {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        })
+    }
+
+    fn send(&mut self, line: &str) -> Result<()> {
+        writeln!(self.writer, "{}", line)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    fn receive(&mut self) -> Result<String> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(OthelloError::Protocol("Opponent disconnected".to_string()));
+        }
+        let line = line.trim().to_string();
+        if let Some(reason) = line.strip_prefix("ERROR ") {
+            return Err(OthelloError::Protocol(format!(
+                "Opponent ended the game: {}",
+                reason
+            )));
+        }
+        Ok(line)
+    }
+
+    /// Report a protocol violation to the peer, then fail with the same message
+    fn reject<T>(&mut self, reason: String) -> Result<T> {
+        // The connection is being dropped anyway, so a failed send changes nothing
+        let _ = self.send(&format!("ERROR {}", reason));
+        Err(OthelloError::Protocol(reason))
+    }
+
+    /// Read the opponent's next move and check that it's legal on the given board
+    fn receive_move(&mut self, board: &Board) -> Result<Move> {
+        let line = self.receive()?;
+        match parse_move(&line) {
+            Ok(Move::Pass) if !board.legal_moves().is_empty() => {
+                self.reject(format!("Illegal pass: {} has legal moves", board.turn))
+            }
+            Ok(Move::Pass) => Ok(Move::Pass),
+            Ok(Move::Place(posn)) if board.is_legal(&posn) => Ok(Move::Place(posn)),
+            Ok(Move::Place(posn)) => {
+                self.reject(format!("Illegal move {} for {}", posn, board.turn))
+            }
+            Err(err) => self.reject(format!("Malformed move \"{}\": {}", line, err)),
+        }
+    }
+}
+
+/// Host a game on the given port, playing local_color against whoever connects
+pub fn serve(
+    port: u16,
+    local_color: Color,
+    start: Board,
+    choose_move: impl FnMut(&Board) -> Result<Move>,
+) -> Result<Board> {
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+    println!("Waiting for an opponent on port {}", port);
+    accept_game(&listener, local_color, start, choose_move)
+}
+
+/// Host a game for the first opponent to connect to listener
+pub fn accept_game(
+    listener: &TcpListener,
+    local_color: Color,
+    start: Board,
+    choose_move: impl FnMut(&Board) -> Result<Move>,
+) -> Result<Board> {
+    let (stream, addr) = listener.accept()?;
+    println!("{} connected", addr);
+    host_game(stream, local_color, start, choose_move)
+}
+
+fn host_game(
+    stream: TcpStream,
+    local_color: Color,
+    start: Board,
+    choose_move: impl FnMut(&Board) -> Result<Move>,
+) -> Result<Board> {
+    let mut conn = Connection::new(stream)?;
+    conn.send(&format!("OTHELLO {}", PROTOCOL_VERSION))?;
+    let reply = conn.receive()?;
+    if reply != format!("OTHELLO {}", PROTOCOL_VERSION) {
+        return conn.reject(format!("Unsupported protocol \"{}\"", reply));
+    }
+    conn.send(&format!("COLOR {}", local_color.opponent()))?;
+    conn.send(&format!("POSITION {}", start.to_position_string()))?;
+
+    play(conn, local_color, start, choose_move)
+}
+
+/// Join a game hosted at addr ("host:port"), playing whichever color the host assigns
+pub fn connect(addr: &str, choose_move: impl FnMut(&Board) -> Result<Move>) -> Result<Board> {
+    let mut conn = Connection::new(TcpStream::connect(addr)?)?;
+
+    let greeting = conn.receive()?;
+    if greeting != format!("OTHELLO {}", PROTOCOL_VERSION) {
+        return conn.reject(format!("Unsupported protocol \"{}\"", greeting));
+    }
+    conn.send(&format!("OTHELLO {}", PROTOCOL_VERSION))?;
+
+    let local_color = match conn.receive()?.as_str() {
+        "COLOR Black" => Color::Black,
+        "COLOR White" => Color::White,
+        other => return conn.reject(format!("Malformed color assignment \"{}\"", other)),
+    };
+    let position = conn.receive()?;
+    let start = match position.strip_prefix("POSITION ") {
+        Some("start") => Board::new(),
+        Some(position) => match Board::from_position_string(position) {
+            Some(board) => board,
+            None => return conn.reject(format!("Malformed position \"{}\"", position)),
+        },
+        None => return conn.reject(format!("Expected a position, got \"{}\"", position)),
+    };
+    println!("Playing as {}", local_color);
+
+    play(conn, local_color, start, choose_move)
+}
+
+fn play(
+    mut conn: Connection,
+    local_color: Color,
+    start: Board,
+    mut choose_move: impl FnMut(&Board) -> Result<Move>,
+) -> Result<Board> {
+    let mut board = start;
+    while !board.is_over() {
+        println!("{}", board);
+        let mv = if board.turn == local_color {
+            let mv = choose_move(&board)?;
+            conn.send(&mv.to_string())?;
+            mv
+        } else {
+            println!("Waiting for {}...", board.turn);
+            let mv = conn.receive_move(&board)?;
+            println!("{} played {}", board.turn, mv);
+            mv
+        };
+        board = match mv {
+            Move::Place(posn) => board.play_move(&posn),
+            Move::Pass => board.change_turn(),
+        };
+    }
+    println!("{}", board);
+    Ok(board)
+}
+
+/// Ask on stdin for a legal move, passing automatically when there isn't one
+pub fn read_local_move(board: &Board) -> Result<Move> {
+    if board.legal_moves().is_empty() {
+        println!("{} has no legal moves and passes", board.turn);
+        return Ok(Move::Pass);
+    }
+    loop {
+        println!("{}'s move:", board.turn);
+        let mut input = String::new();
+        if std::io::stdin().read_line(&mut input)? == 0 {
+            return Err(OthelloError::Io(std::io::ErrorKind::UnexpectedEof.into()));
+        }
+        if input.trim() == "hash" {
+            println!("{}", board.fingerprint());
+            continue;
+        }
+        match parse_move(&input) {
+            Ok(Move::Place(posn)) if board.is_legal(&posn) => return Ok(Move::Place(posn)),
+            Ok(_) => println!("Invalid move"),
+            Err(err) => println!("Invalid move: {}", err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::PASS_GAME;
+    use crate::transcript::GameRecord;
+    use crate::Posn;
+
+    /// PASS_GAME played over a loopback connection, the host as Black and the client as White, each
+    /// side sending its own moves and passes from the script: both ends must finish on the game's
+    /// final position. Then a client whose first move is illegal is refused, and told why
+    #[test]
+    fn network_game_over_loopback() {
+        use std::collections::VecDeque;
+        use std::net::TcpListener;
+
+        let replay = GameRecord::from_transcript(PASS_GAME)
+            .and_then(|record| record.replay())
+            .unwrap_or_else(|err| panic!("pass game didn't replay: {}", err));
+        let script = |color: Color| -> VecDeque<Move> {
+            replay
+                .moves
+                .iter()
+                .zip(&replay.boards)
+                .filter(|(_, board)| board.turn == color)
+                .map(|(mv, _)| *mv)
+                .collect()
+        };
+        let scripted = |mut moves: VecDeque<Move>| {
+            move |_: &Board| {
+                moves
+                    .pop_front()
+                    .ok_or_else(|| OthelloError::Protocol("script ran out".to_string()))
+            }
+        };
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap_or_else(|err| panic!("{}", err));
+        let addr = listener
+            .local_addr()
+            .unwrap_or_else(|err| panic!("{}", err))
+            .to_string();
+
+        let (host, client) = std::thread::scope(|scope| {
+            let host = scope.spawn(|| {
+                accept_game(
+                    &listener,
+                    Color::Black,
+                    Board::new(),
+                    scripted(script(Color::Black)),
+                )
+            });
+            let client = connect(&addr, scripted(script(Color::White)));
+            (host.join().unwrap(), client)
+        });
+        match (host, client) {
+            (Ok(host), Ok(client)) if host == *replay.final_board() && client == host => {}
+            (host, client) => {
+                panic!(
+                    "the scripted network game ended {:?} for the host and {:?} for the client",
+                    host, client
+                )
+            }
+        }
+
+        let illegal = VecDeque::from([Move::Place(Posn::try_from_alphanumeric("a1").unwrap())]);
+        let (host, client) = std::thread::scope(|scope| {
+            let host = scope.spawn(|| {
+                accept_game(
+                    &listener,
+                    Color::Black,
+                    Board::new(),
+                    scripted(script(Color::Black)),
+                )
+            });
+            let client = connect(&addr, scripted(illegal));
+            (host.join().unwrap(), client)
+        });
+        match (host, client) {
+            (Err(OthelloError::Protocol(host)), Err(OthelloError::Protocol(client)))
+                if host.contains("Illegal move a1") && client.contains(&host) => {}
+            (host, client) => {
+                panic!(
+                    "after an illegal move the host got {:?} and the client {:?}",
+                    host, client
+                )
+            }
+        }
+    }
+}
@@ -0,0 +1,160 @@
+//! Watching a tournament as it runs. The driver hands every observer the running totals after each
+//! game, so a long tournament can show its win-rate estimate settling or be plotted while it plays.
+
+use std::fs::File;
+use std::io::Write;
+
+use crate::error::Result;
+
+/// The tournament so far, after one more game
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TournamentProgress {
+    /// Games finished, counting this one
+    pub games_done: usize,
+    pub total_games: usize,
+    pub minimax_wins: usize,
+    pub standard_wins: usize,
+    pub ties: usize,
+    /// Mean of the Beta posterior on the standard agent's win rate
+    pub mean: f64,
+    /// The posterior's 5th and 95th percentiles, the summary's 90% credible interval
+    pub low: f64,
+    pub high: f64,
+}
+
+/// Send so a tournament playing on several threads can report from whichever finishes a game
+pub trait TournamentObserver: Send {
+    /// Called once after every game
+    fn on_game(&mut self, progress: &TournamentProgress) -> Result<()>;
+
+    /// Called once after the last game
+    fn finish(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// One line on stderr, rewritten in place after each game
+#[derive(Debug, Default)]
+pub struct StatusLine;
+
+impl TournamentObserver for StatusLine {
+    fn on_game(&mut self, progress: &TournamentProgress) -> Result<()> {
+        let mut stderr = std::io::stderr();
+        write!(
+            stderr,
+            "\rGame {}/{}: minimax {}, standard {}, ties {} | standard wins {:.1}% (90%: {:.1}%-{:.1}%)",
+            progress.games_done,
+            progress.total_games,
+            progress.minimax_wins,
+            progress.standard_wins,
+            progress.ties,
+            progress.mean * 100.0,
+            progress.low * 100.0,
+            progress.high * 100.0
+        )?;
+        stderr.flush()?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        eprintln!();
+        Ok(())
+    }
+}
+
+/// A row per game in a CSV file, written as each game ends so the file can be plotted mid-run
+pub struct CsvObserver {
+    file: File,
+}
+
+impl CsvObserver {
+    pub const HEADER: &'static str =
+        "games_done,total_games,minimax_wins,standard_wins,ties,mean,low,high";
+
+    /// Create (or empty) the file at path and write the header
+    pub fn create(path: &str) -> Result<Self> {
+        let mut file = File::create(path)?;
+        writeln!(file, "{}", Self::HEADER)?;
+        Ok(Self { file })
+    }
+}
+
+impl TournamentObserver for CsvObserver {
+    fn on_game(&mut self, progress: &TournamentProgress) -> Result<()> {
+        writeln!(
+            self.file,
+            "{},{},{},{},{},{:.6},{:.6},{:.6}",
+            progress.games_done,
+            progress.total_games,
+            progress.minimax_wins,
+            progress.standard_wins,
+            progress.ties,
+            progress.mean,
+            progress.low,
+            progress.high
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{play_tournament, TournamentConfig};
+    use std::sync::{Arc, Mutex};
+
+    /// Keeps every progress report and counts finish calls, for checking what the driver reports
+    struct RecordingObserver(Arc<Mutex<(Vec<TournamentProgress>, usize)>>);
+
+    impl TournamentObserver for RecordingObserver {
+        fn on_game(&mut self, progress: &TournamentProgress) -> Result<()> {
+            self.0.lock().unwrap().0.push(*progress);
+            Ok(())
+        }
+
+        fn finish(&mut self) -> Result<()> {
+            self.0.lock().unwrap().1 += 1;
+            Ok(())
+        }
+    }
+
+    /// A short tournament must report to its observer once per game, in order, with running totals
+    /// that add up, and finish it once
+    #[test]
+    fn observer_sees_every_game_in_order() {
+        const GAMES: usize = 3;
+        let record = Arc::new(Mutex::new((vec![], 0)));
+        let config = TournamentConfig {
+            games: GAMES,
+            ..TournamentConfig::default()
+        };
+        let mut observers: Vec<Box<dyn TournamentObserver>> =
+            vec![Box::new(RecordingObserver(record.clone()))];
+        let outcome = play_tournament(&config, &mut observers)
+            .unwrap_or_else(|err| panic!("tournament: {}", err));
+        let (reports, finishes) = &*record.lock().unwrap();
+        if reports.len() != GAMES || *finishes != 1 {
+            panic!(
+                "{} games reported {} times and finished {} times",
+                GAMES,
+                reports.len(),
+                finishes
+            );
+        }
+        for (i, report) in reports.iter().enumerate() {
+            let counted = report.minimax_wins + report.standard_wins + report.ties;
+            if report.games_done != i + 1 || report.total_games != GAMES || counted != i + 1 {
+                panic!("report {} was {:?}", i + 1, report);
+            }
+            if !(report.low <= report.mean && report.mean <= report.high) {
+                panic!("report {} has its mean outside the interval", i + 1);
+            }
+        }
+        let last = reports[GAMES - 1];
+        if (last.minimax_wins, last.standard_wins, last.ties)
+            != (outcome.minimax_wins, outcome.standard_wins, outcome.ties)
+        {
+            panic!("last report {:?} disagrees with the outcome", last);
+        }
+    }
+}
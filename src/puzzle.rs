@@ -0,0 +1,407 @@
+//! Puzzle mode: endgame positions with one move that beats every other by a clear margin under
+//! perfect play, shown one at a time. You get a few tries at each, and the answer comes with the
+//! exact solver's verdict on it and on the next best move.
+
+use crate::error::{OthelloError, Result};
+use crate::input::LineInput;
+use crate::rng::{MoveRng, SeededRng};
+use crate::{input, parse_move, solve_exact, Board, Color, Move, Posn, SCORE_LOSS, SCORE_WIN};
+
+/// Discs by which every puzzle's solution beats the next best move under perfect play
+pub const MARGIN: i32 = 6;
+
+/// Tries at each puzzle when --tries isn't given
+const DEFAULT_TRIES: usize = 3;
+
+pub struct Puzzle {
+    /// As Board::from_position_string reads it
+    pub position: &'static str,
+    pub to_move: Color,
+    pub solution: &'static str,
+    /// The final disc difference after the solution with perfect play, White positive
+    pub value: i32,
+}
+
+/// Positions from seeded games of minimax against random moves, kept where the solver found a
+/// unique best move that wins or draws for the side to move
+pub const PUZZLES: [Puzzle; 18] = [
+    Puzzle {
+        position: "X-X-XO-OOXXXOOO---XXXX-XX-OOXXXXXXOOXXXXXOOOOOXXXXOXXO-XX-OOOO-- X",
+        to_move: Color::Black,
+        solution: "b8",
+        value: -22,
+    },
+    Puzzle {
+        position: "XOOOX-OXOOOOOO-XXOXOOXOX--OXXOXX-OXOOXXXOOOOO-XX-OOO-OXOOOOOO-X- X",
+        to_move: Color::Black,
+        solution: "f1",
+        value: -24,
+    },
+    Puzzle {
+        position: "OOOOOXOO--OO-XXXXOOXOXXXXOOOOXXXXXOOXXOOXXOXOXO---OXOOXX--X-O-OX X",
+        to_move: Color::Black,
+        solution: "e2",
+        value: -36,
+    },
+    Puzzle {
+        position: "OOOOOOOO-XXXXXOOOXXOOXXOOXXOOOOOOXXOOOOOOOXXXX--OOXX----OOO-X--- O",
+        to_move: Color::White,
+        solution: "a2",
+        value: 50,
+    },
+    Puzzle {
+        position: "---O-X-OOOOOOOOOOOXXXXXOOXOOOXOOOXXOXX--OOOXOX--OOXXXX--OOOOOX-- O",
+        to_move: Color::White,
+        solution: "g7",
+        value: 17,
+    },
+    Puzzle {
+        position: "-X-XXXX-XOOOOXX--XOXXOXXXXXOOO-----OOOXO-XXXXXXX-OOOOOOO-OOOOOOO O",
+        to_move: Color::White,
+        solution: "h4",
+        value: 28,
+    },
+    Puzzle {
+        position: "OOOOOOOOXXOXOOOOXXXOOXXOOXOXOXXO-OXOOOXO-OOOOOOO-O---O-OXXX----- X",
+        to_move: Color::Black,
+        solution: "a7",
+        value: -4,
+    },
+    Puzzle {
+        position: "OOXXOOO-OOOXXXXXXOOXOXOXOOOXX-X-OOXXOX--OOOOOOX--XOXXXXX-XXXX--- O",
+        to_move: Color::White,
+        solution: "h1",
+        value: 32,
+    },
+    Puzzle {
+        position: "X-OOOOOOXX-OOXO-XXXXXO--XOXOXOO-XOOXOXOOXOX-X--OXOOXOXOOXOOOX-X- O",
+        to_move: Color::White,
+        solution: "f8",
+        value: 24,
+    },
+    Puzzle {
+        position: "O-OX--OXOOXXXOOOOXXXOXOXOOXOOOO-XXXXXXOOXXXXOO-O-OOOO-O--OOOO--- X",
+        to_move: Color::Black,
+        solution: "h4",
+        value: -18,
+    },
+    Puzzle {
+        position: "OOOOXXX---XXXXO---XXXOXOXXXOXXOXXXOXOXXOXXOOXXX-XOXOXX-XXXXXX--- O",
+        to_move: Color::White,
+        solution: "h1",
+        value: 14,
+    },
+    Puzzle {
+        position: "OOXXXXOOOOOXXXXOOOOXXXXOO-XOXXOO-XXOOXOOX-OOOOO--X-X-OO-X-XXXXO- X",
+        to_move: Color::Black,
+        solution: "b4",
+        value: -12,
+    },
+    Puzzle {
+        position: "--OOOXX---OOXX-X-O-XO-XXO-OXOXXXOOOXXXXXOOOXXXXXOOOOOX-XO-XXXXX- O",
+        to_move: Color::White,
+        solution: "h1",
+        value: 28,
+    },
+    Puzzle {
+        position: "--O--XXX-OXXXX--XXXXOOOO-O-XXO-OXOXXOXXO-O-XXOXOOOOOXXOO-OOOOOOO O",
+        to_move: Color::White,
+        solution: "g4",
+        value: 10,
+    },
+    Puzzle {
+        position: "-XXX-OX-X-XX-OOOOOOXXXXXOOOXXO--OOOOXXOXOXOOXOXXXOXXX--XO--OOO-- O",
+        to_move: Color::White,
+        solution: "h1",
+        value: 34,
+    },
+    Puzzle {
+        position: "-OX-X--OXOX-X-O--OXOXXXXXOOOOOO-XXOOOOOOXXOOOXOOXOOOOOOX---OOOOX X",
+        to_move: Color::Black,
+        solution: "a8",
+        value: -26,
+    },
+    Puzzle {
+        position: "--OOO-----XOOOOOOXXXXXOOOXXXXOOOOXOXOXOOOXXOXOOO--XXO-OO-OXOOO-O X",
+        to_move: Color::Black,
+        solution: "h1",
+        value: -20,
+    },
+    Puzzle {
+        position: "----XOXXOXO-OXX--XXOOOXOXXOOOXOO-OOOXOOOOXOXOXOOOXXOXX-OOXOOOOO- X",
+        to_move: Color::Black,
+        solution: "h8",
+        value: -2,
+    },
+];
+
+impl Puzzle {
+    pub fn board(&self) -> Board {
+        Board::from_position_string(self.position).expect("bundled puzzle positions parse")
+    }
+
+    pub fn solution_posn(&self) -> Posn {
+        Posn::try_from_alphanumeric(self.solution).expect("bundled puzzle solutions parse")
+    }
+
+    /// The value from the side to move's point of view
+    pub fn value_for_mover(&self) -> i32 {
+        match self.to_move {
+            Color::White => self.value,
+            Color::Black => -self.value,
+        }
+    }
+}
+
+/// Every legal move with the final disc difference it leads to under perfect play, from the
+/// side to move's point of view, best first. Moves with equal results keep legal_moves order
+pub fn solve(board: &Board) -> Vec<(Posn, i32)> {
+    let sign = match board.turn {
+        Color::White => 1,
+        Color::Black => -1,
+    };
+    let mut solved: Vec<(Posn, i32)> = board
+        .legal_moves()
+        .into_iter()
+        .map(|posn| {
+            let score = solve_exact(&board.play_move(&posn), SCORE_LOSS, SCORE_WIN);
+            (posn, sign * score)
+        })
+        .collect();
+    solved.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+    solved
+}
+
+/// The order seed deals the puzzles in, as indices into PUZZLES
+pub fn order(seed: u64) -> Vec<usize> {
+    let mut rng = SeededRng::new(seed);
+    let mut order: Vec<usize> = (0..PUZZLES.len()).collect();
+    for i in (1..order.len()).rev() {
+        order.swap(i, rng.index(i + 1));
+    }
+    order
+}
+
+/// "wins by 6", "draws" or "loses by 4", for a disc difference from the mover's side
+fn result_phrase(score: i32) -> String {
+    match score {
+        0 => "draws".to_string(),
+        score if score > 0 => format!("wins by {}", score),
+        score => format!("loses by {}", -score),
+    }
+}
+
+/// How one puzzle went
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Attempt {
+    /// Found on this try, counting from 1
+    Solved(usize),
+    /// Every try used, or skipped
+    Failed,
+}
+
+/// Show puzzle and read guesses from input until it's solved, the tries run out, or "skip".
+/// Guesses that aren't legal moves don't use up a try. None when the input ends or says "quit"
+pub fn play_puzzle(
+    puzzle: &Puzzle,
+    tries: usize,
+    input: &mut dyn LineInput,
+) -> Result<Option<Attempt>> {
+    let board = puzzle.board();
+    let solved = solve(&board);
+    let mover = puzzle.to_move;
+    let goal = if puzzle.value_for_mover() > 0 {
+        "win"
+    } else {
+        "draw"
+    };
+    println!("{}", board);
+    println!("{} to move and {}", mover, goal);
+
+    let mut tried = 0;
+    let mut line = String::new();
+    let attempt = loop {
+        if tried == tries {
+            break Attempt::Failed;
+        }
+        println!(
+            "Your move ({} {} left, or skip):",
+            tries - tried,
+            if tries - tried == 1 { "try" } else { "tries" }
+        );
+        line.clear();
+        if input.read_line_for(&board, &mut line)? == 0 {
+            return Ok(None);
+        }
+        let guess = match line.trim() {
+            "quit" => return Ok(None),
+            "skip" => break Attempt::Failed,
+            text => match parse_move(text) {
+                Ok(Move::Place(posn)) if board.is_legal(&posn) => posn,
+                _ => {
+                    println!("Not a legal move here");
+                    continue;
+                }
+            },
+        };
+        tried += 1;
+        if guess.index() == puzzle.solution_posn().index() {
+            println!(
+                "Correct! {} {} with perfect play",
+                mover,
+                result_phrase(puzzle.value_for_mover())
+            );
+            break Attempt::Solved(tried);
+        }
+        let score = solved
+            .iter()
+            .find(|(posn, _)| posn.index() == guess.index())
+            .map_or(0, |(_, score)| *score);
+        println!(
+            "Not quite: after {}, {} {} with perfect play",
+            guess,
+            mover,
+            result_phrase(score)
+        );
+    };
+
+    if attempt == Attempt::Failed {
+        let solution = puzzle.solution_posn();
+        let mut explanation = format!(
+            "The answer was {}{}: {} {} with perfect play",
+            solution,
+            if solution.is_corner() {
+                ", taking the corner"
+            } else {
+                ""
+            },
+            mover,
+            result_phrase(puzzle.value_for_mover())
+        );
+        if let Some((next_best, score)) = solved.get(1) {
+            explanation.push_str(&format!(
+                ", while the next best move, {}, {}",
+                next_best,
+                result_phrase(*score)
+            ));
+        }
+        println!("{}", explanation);
+    }
+    Ok(Some(attempt))
+}
+
+/// puzzle [--seed n] [--tries n]: the bundled puzzles in an order the seed decides, then how the
+/// session went
+pub fn run(args: &[String]) -> Result<()> {
+    let usage = || OthelloError::Usage("puzzle [--seed n] [--tries n]".to_string());
+    let mut seed = None;
+    let mut tries = DEFAULT_TRIES;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or_else(usage)?;
+        match arg.as_str() {
+            "--seed" => seed = Some(value.parse()?),
+            "--tries" => tries = value.parse()?,
+            _ => return Err(usage()),
+        }
+    }
+    if tries == 0 {
+        return Err(usage());
+    }
+    let seed = seed.unwrap_or_else(|| {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs())
+    });
+    println!(
+        "{} puzzles, {} tries each (--seed {} deals them in this order again)",
+        PUZZLES.len(),
+        tries,
+        seed
+    );
+
+    let mut input = input::stdin();
+    let mut attempts = vec![];
+    for (number, index) in order(seed).into_iter().enumerate() {
+        println!("\nPuzzle {} of {}", number + 1, PUZZLES.len());
+        match play_puzzle(&PUZZLES[index], tries, &mut *input)? {
+            Some(attempt) => attempts.push(attempt),
+            None => break,
+        }
+    }
+
+    let solved = attempts
+        .iter()
+        .filter(|attempt| matches!(attempt, Attempt::Solved(_)))
+        .count();
+    let first_try = attempts
+        .iter()
+        .filter(|attempt| **attempt == Attempt::Solved(1))
+        .count();
+    println!(
+        "\nSolved {} of {} puzzles, {} on the first try",
+        solved,
+        attempts.len(),
+        first_try
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    /// Every bundled puzzle parses with the side to move it claims, and the exact solver finds its
+    /// solution to be the one best move, worth the stated value, ahead of the next by MARGIN.
+    /// Each seed deals every puzzle once, the same way every time
+    #[test]
+    fn bundled_puzzles_solve_as_stated() {
+        let mut positions = HashSet::new();
+        for puzzle in &PUZZLES {
+            let board = Board::from_position_string(puzzle.position)
+                .unwrap_or_else(|| panic!("puzzle {} doesn't parse", puzzle.position));
+            if board.turn != puzzle.to_move || !positions.insert(board.clone()) {
+                panic!(
+                    "puzzle {} has {} to move or is bundled twice",
+                    puzzle.position, board.turn
+                );
+            }
+            let solution = Posn::try_from_alphanumeric(puzzle.solution)
+                .filter(|posn| board.is_legal(posn))
+                .unwrap_or_else(|| {
+                    panic!("{} isn't legal in {}", puzzle.solution, puzzle.position)
+                });
+            let solved = solve(&board);
+            let best = solved[0];
+            let margin = solved.get(1).map_or(i32::MAX, |(_, next)| best.1 - next);
+            if best.0.index() != solution.index()
+                || best.1 != puzzle.value_for_mover()
+                || best.1 < 0
+                || margin < MARGIN
+            {
+                panic!(
+                    "puzzle {} claims {} for {}, but the solver ranks {:?}",
+                    puzzle.position,
+                    puzzle.solution,
+                    puzzle.value_for_mover(),
+                    solved
+                        .iter()
+                        .map(|(posn, score)| format!("{} {}", posn, score))
+                        .collect::<Vec<_>>()
+                );
+            }
+        }
+
+        for seed in 0..5 {
+            let order = order(seed);
+            let dealt: HashSet<usize> = order.iter().copied().collect();
+            if dealt.len() != PUZZLES.len() || order.len() != dealt.len() {
+                panic!("seed {} dealt {:?}", seed, order);
+            }
+            if order != super::order(seed) {
+                panic!("seed {} dealt two different orders", seed);
+            }
+        }
+    }
+}
@@ -0,0 +1,240 @@
+//! A finished tournament written up as Markdown, to share or keep next to its CSV: the matchup,
+//! the results with the posterior's credible interval, the spread of final disc differences, and
+//! the most lopsided games in full with their final positions.
+
+use statrs::distribution::ContinuousCDF;
+
+use crate::transcript::{GameRecord, TranscriptStyle};
+use crate::{analysis, Color, RenderStyle, TournamentConfig, TournamentOutcome, TOURNAMENT_DEPTH};
+
+/// Games shown move by move at the end of the report
+const LOPSIDED_GAMES: usize = 3;
+
+/// Width the histogram's bars are scaled to
+const HISTOGRAM_WIDTH: usize = 72;
+
+/// The report for a tournament played with config. Only reads outcome, so it can be given
+/// made-up results
+pub fn markdown(config: &TournamentConfig, outcome: &TournamentOutcome) -> String {
+    let games = outcome.finals.len();
+    let mut report = "# Tournament report\n\n".to_string();
+
+    report.push_str("## Matchup\n\n");
+    report.push_str("| Player | Agent | Depth | Heuristic |\n");
+    report.push_str("| --- | --- | --- | --- |\n");
+    report.push_str(&format!(
+        "| Minimax | Alpha-beta search | {} | edge_corner |\n",
+        TOURNAMENT_DEPTH
+    ));
+    report.push_str("| Standard | Best move one ply ahead | 1 | standard |\n\n");
+    match &outcome.pool {
+        Some(pool) => report.push_str(&format!(
+            "{} distinct openings of {} random plies, each played with both colors.\n\n",
+            pool.len(),
+            config.opening_plies
+        )),
+        None => report.push_str(
+            "Each game starts from a fresh random opening, with minimax playing Black.\n\n",
+        ),
+    }
+
+    report.push_str("## Results\n\n");
+    report.push_str(&format!("{} games played.\n\n", games));
+    report.push_str("| Result | Games | Share |\n");
+    report.push_str("| --- | --- | --- |\n");
+    for (name, count) in [
+        ("Minimax wins", outcome.minimax_wins),
+        ("Standard wins", outcome.standard_wins),
+        ("Ties", outcome.ties),
+    ] {
+        report.push_str(&format!(
+            "| {} | {} | {:.1}% |\n",
+            name,
+            count,
+            count as f64 * 100.0 / games.max(1) as f64
+        ));
+    }
+    report.push_str(&format!(
+        "\n90% credible interval for the standard agent's win rate: {:.2}% to {:.2}%.\n",
+        outcome.posterior.inverse_cdf(0.05) * 100.0,
+        outcome.posterior.inverse_cdf(0.95) * 100.0
+    ));
+    let plies: usize = outcome.moves.iter().map(Vec::len).sum();
+    report.push_str(&format!(
+        "Games lasted {:.1} plies on average, passes included.\n",
+        plies as f64 / games.max(1) as f64
+    ));
+    if config.early_adjudicate {
+        report.push_str(&format!(
+            "{} games were adjudicated early, saving up to {} plies.\n",
+            outcome.adjudicated, outcome.plies_saved
+        ));
+    }
+
+    report.push_str("\n## Final disc differences (White minus Black)\n\n");
+    let finished = outcome.finished_finals();
+    if finished.len() < games {
+        report.push_str(&format!(
+            "Adjudicated games, which stopped before the end, are left out: {} of {}.\n\n",
+            games - finished.len(),
+            games
+        ));
+    }
+    report.push_str("```text\n");
+    report.push_str(&analysis::disc_histogram(
+        &finished,
+        HISTOGRAM_WIDTH,
+        RenderStyle::Ascii,
+    ));
+    report.push_str("```\n");
+
+    report.push_str("\n## Most lopsided games\n");
+    let mut by_margin: Vec<usize> = (0..games).collect();
+    // Stable, so equally lopsided games keep the order they were played in
+    by_margin.sort_by_key(|&game| std::cmp::Reverse(outcome.finals[game].score().abs()));
+    for game in by_margin.into_iter().take(LOPSIDED_GAMES) {
+        let board = &outcome.finals[game];
+        let minimax = outcome.minimax_colors[game];
+        let side = |winner: Color| {
+            let player = if winner == minimax {
+                "minimax"
+            } else {
+                "standard"
+            };
+            format!("{} ({})", winner, player)
+        };
+        // An adjudicated game's discs don't decide it, so give the adjudicated result instead
+        let result = match (outcome.adjudicated_games[game], board.score()) {
+            (true, _) => match outcome.minimax_results[game] {
+                None => "adjudicated a draw".to_string(),
+                Some(true) => format!("{} won on adjudication", side(minimax)),
+                Some(false) => format!("{} won on adjudication", side(minimax.opponent())),
+            },
+            (false, 0) => "a draw".to_string(),
+            (false, score) if score > 0 => format!("{} won by {}", side(Color::White), score),
+            (false, score) => format!("{} won by {}", side(Color::Black), -score),
+        };
+        let record = GameRecord::from_moves(outcome.moves[game].clone());
+        report.push_str(&format!(
+            "\n### Game {}: {}\n\nMinimax played {}. Opening: `{}`\n\nMoves from the opening:\n\n\
+             ```text\n{}\n```\n\n{}:\n\n```text\n{}```\n",
+            game + 1,
+            result,
+            minimax,
+            outcome.starts[game].to_position_string(),
+            record.to_transcript(TranscriptStyle::Explicit).trim_end(),
+            match outcome.adjudicated_games[game] {
+                true => "Position when adjudicated",
+                false => "Final position",
+            },
+            board.render(RenderStyle::Ascii)
+        ));
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Board, Move, Posn};
+    use statrs::distribution::Beta;
+
+    /// A report over four made-up games has every section, a row per player and per result, and
+    /// the three widest margins shown in full, widest first. Marking one adjudicated labels it so and
+    /// leaves its board out of the histogram
+    #[test]
+    fn match_report_has_every_section() {
+        let filled = |black: usize| {
+            let position = "X".repeat(black) + &"O".repeat(64 - black);
+            Board::from_position_string(&format!("{} X", position)).unwrap()
+        };
+        let e3 = Move::Place(Posn::try_from_alphanumeric("e3").unwrap());
+        let outcome = TournamentOutcome {
+            pool: None,
+            minimax_wins: 2,
+            standard_wins: 1,
+            ties: 1,
+            posterior: Beta::new(3.0, 4.0).unwrap_or_else(|err| panic!("{}", err)),
+            minimax_results: vec![None, Some(true), Some(false), Some(true)],
+            starts: vec![Board::new(); 4],
+            finals: vec![Board::new(), filled(64), filled(24), filled(33)],
+            adjudicated_games: vec![false; 4],
+            moves: vec![vec![], vec![e3], vec![e3, Move::Pass], vec![e3]],
+            minimax_colors: vec![Color::Black; 4],
+            adjudicated: 0,
+            plies_saved: 0,
+        };
+        let report = crate::report::markdown(&TournamentConfig::default(), &outcome);
+
+        let headers: Vec<&str> = report
+            .lines()
+            .filter(|line| line.starts_with("## "))
+            .collect();
+        let expected_headers = [
+            "## Matchup",
+            "## Results",
+            "## Final disc differences (White minus Black)",
+            "## Most lopsided games",
+        ];
+        if headers != expected_headers || !report.starts_with("# Tournament report\n") {
+            panic!("report has sections {:?}", headers);
+        }
+        // Rows after the section's first table's header and separator, up to the blank line ending it
+        let table_rows = |section: &str| {
+            report
+                .split(section)
+                .nth(1)
+                .and_then(|rest| rest.split("\n\n").find(|chunk| chunk.starts_with("| ")))
+                .map_or(0, |table| table.lines().count().saturating_sub(2))
+        };
+        if (table_rows("## Matchup"), table_rows("## Results")) != (2, 3) {
+            panic!(
+                "report's matchup table has {} rows and results table {}",
+                table_rows("## Matchup"),
+                table_rows("## Results")
+            );
+        }
+        let games: Vec<&str> = report
+            .lines()
+            .filter(|line| line.starts_with("### "))
+            .collect();
+        let expected_games = [
+            "### Game 2: Black (minimax) won by 64",
+            "### Game 3: White (standard) won by 16",
+            "### Game 4: Black (minimax) won by 2",
+        ];
+        if games != expected_games {
+            panic!("report shows games {:?}", games);
+        }
+        if report.matches("```").count() != 2 + 4 * expected_games.len() {
+            panic!("report's code blocks aren't all closed");
+        }
+        if report.contains("left out") || outcome.histogram_heading().contains("left out") {
+            panic!("a report with no adjudicated games says some were left out");
+        }
+
+        let outcome = TournamentOutcome {
+            adjudicated_games: vec![false, false, true, false],
+            adjudicated: 1,
+            ..outcome
+        };
+        let report = crate::report::markdown(&TournamentConfig::default(), &outcome);
+        if !report.contains("### Game 3: White (standard) won on adjudication\n")
+            || report.matches("Position when adjudicated:").count() != 1
+        {
+            panic!("an adjudicated game was shown as\n{}", report);
+        }
+        let note = "Adjudicated games, which stopped before the end, are left out: 1 of 4.";
+        let heading = "Final disc differences (White minus Black), 1 adjudicated game left out";
+        if !report.contains(note) || outcome.histogram_heading() != heading {
+            panic!(
+                "the histogram over an adjudicated game is headed {:?} in\n{}",
+                outcome.histogram_heading(),
+                report
+            );
+        }
+        if outcome.finished_finals().len() != 3 {
+            panic!("the adjudicated game's board went into the histogram");
+        }
+    }
+}
@@ -0,0 +1,150 @@
+//! The engine's source of randomness. Everything random goes through MoveRng, so a caller can make
+//! play reproducible with SeededRng, or script it entirely with their own implementation.
+
+#[cfg(feature = "rand")]
+use rand::rngs::StdRng;
+#[cfg(feature = "rand")]
+use rand::{Rng, SeedableRng};
+
+pub trait MoveRng {
+    /// A uniformly random index in 0..len, where len is at least 1
+    fn index(&mut self, len: usize) -> usize;
+
+    /// A uniformly random number in [0, 1)
+    fn unit(&mut self) -> f64;
+
+    /// An index drawn in proportion to weights, which must not all be zero
+    fn weighted_index(&mut self, weights: &[f64]) -> usize {
+        let total: f64 = weights.iter().sum();
+        let mut target = self.unit() * total;
+        for (index, weight) in weights.iter().enumerate() {
+            if target < *weight {
+                return index;
+            }
+            target -= weight;
+        }
+        // Rounding can leave a sliver past the last weight; give it to the last nonzero one
+        weights
+            .iter()
+            .rposition(|weight| *weight > 0.0)
+            .unwrap_or(0)
+    }
+}
+
+/// Fresh, unrepeatable randomness from the operating system, for normal play
+#[cfg(feature = "rand")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ThreadRng;
+
+#[cfg(feature = "rand")]
+impl MoveRng for ThreadRng {
+    fn index(&mut self, len: usize) -> usize {
+        rand::thread_rng().gen_range(0..len)
+    }
+
+    fn unit(&mut self) -> f64 {
+        rand::thread_rng().gen()
+    }
+}
+
+/// The same sequence every time for the same seed
+#[cfg(feature = "rand")]
+#[derive(Debug, Clone)]
+pub struct SeededRng(StdRng);
+
+#[cfg(feature = "rand")]
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        Self(StdRng::seed_from_u64(seed))
+    }
+}
+
+#[cfg(feature = "rand")]
+impl MoveRng for SeededRng {
+    fn index(&mut self, len: usize) -> usize {
+        self.0.gen_range(0..len)
+    }
+
+    fn unit(&mut self) -> f64 {
+        self.0.gen()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "sim")]
+    use crate::selfplay::{NoiseConfig, NoisySearchAgent};
+    #[cfg(feature = "sim")]
+    use crate::{edge_corner_heuristic, Agent, MinimaxAgent, Move};
+    use crate::{random_agent_with, Board};
+
+    /// A MoveRng replaying a fixed list of draws: index returns the next one as is, and unit the next
+    /// one in thousandths
+    struct ScriptedRng(std::vec::IntoIter<usize>);
+
+    impl ScriptedRng {
+        fn new(draws: Vec<usize>) -> Self {
+            Self(draws.into_iter())
+        }
+
+        fn next(&mut self) -> usize {
+            self.0.next().expect("the script ran out of draws")
+        }
+    }
+
+    impl MoveRng for ScriptedRng {
+        fn index(&mut self, len: usize) -> usize {
+            let index = self.next();
+            assert!(index < len, "scripted index {} out of 0..{}", index, len);
+            index
+        }
+
+        fn unit(&mut self) -> f64 {
+            self.next() as f64 / 1000.0
+        }
+    }
+
+    /// Scripted draws decide the random agent's moves, each picking that index of the legal moves
+    #[test]
+    fn scripted_draws_pick_the_random_agents_moves() {
+        let mut board = Board::new();
+        let mut rng = ScriptedRng::new(vec![2, 0, 3]);
+        for index in [2, 0, 3] {
+            let expected = board.legal_moves()[index];
+            let chosen = random_agent_with(&board, &mut rng);
+            if chosen.to_tuple() != expected.to_tuple() {
+                panic!(
+                    "the draw {} gave {}, not {}\n{}",
+                    index, chosen, expected, board
+                );
+            }
+            board = board.play_move(&chosen);
+        }
+    }
+
+    /// A noisy agent with no noise and a huge temperature plays close to uniformly over the search's
+    /// ranking, so a draw near 0 picks its top move and one near 1 its bottom one
+    #[cfg(feature = "sim")]
+    #[test]
+    fn scripted_draws_pick_the_noisy_agents_moves() {
+        let board = Board::from_position_string(
+            "---------OX-------OXXX-X--OOOOX-XXXXXXO----X-O------O----------- X",
+        )
+        .unwrap();
+        let search = MinimaxAgent::new(2, edge_corner_heuristic);
+        let ranked = search.clone().rank_moves(&board);
+        let config = NoiseConfig::new(0.3, 0.0, 1e9, 60).unwrap();
+        for (draw, rank) in [(0, 0), (999, ranked.len() - 1)] {
+            let chosen =
+                NoisySearchAgent::new(search.clone(), config, ScriptedRng::new(vec![draw]))
+                    .choose_move(&board);
+            if Move::Place(chosen).to_string() != ranked[rank].0.to_string() {
+                panic!(
+                    "the draw {} gave {}, not the move ranked {}, {}",
+                    draw, chosen, rank, ranked[rank].0
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,348 @@
+//! Alpha-beta search backed by state that outlives a single search: a transposition table plus
+//! killer and history tables for move ordering. An agent keeps one SearchContext for a whole game,
+//! so the position after the opponent's reply is often already partly searched.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::table::{Bound, TableEntry, TableStats, TranspositionTable, DEFAULT_MEGABYTES};
+use crate::{terminal_score, Board, Color, Move, Posn, COLS, ROWS, SCORE_LOSS, SCORE_WIN};
+
+/// Nodes searched between looks at the cancellation flag
+const CANCEL_CHECK_INTERVAL: u64 = 1024;
+
+#[derive(Debug, Clone)]
+pub struct SearchContext {
+    table: TranspositionTable,
+    /// Two moves per ply that recently caused a cutoff there
+    killers: Vec<[Option<Posn>; 2]>,
+    /// How often each square's move has caused a cutoff, weighted by depth
+    history: [u32; ROWS * COLS],
+    /// Incremented once per search, to tell fresh table entries from stale ones
+    age: u32,
+    /// Positions visited since the context was created or last cleared
+    pub nodes: u64,
+    /// Positions visited by the current search
+    pub search_nodes: u64,
+    /// Stop each search, as if cancelled, once it has visited this many positions. Survives clear
+    pub node_limit: Option<u64>,
+    /// Set from another thread to stop the current search. Survives clear
+    pub cancel: Option<Arc<AtomicBool>>,
+    /// Whether the current search saw the cancel flag; its scores are meaningless from then on
+    cancelled: bool,
+    /// White-positive score of a drawn finish, 0 unless the searching side has some contempt
+    draw_score: i32,
+}
+
+impl Default for SearchContext {
+    fn default() -> Self {
+        Self::with_megabytes(DEFAULT_MEGABYTES)
+    }
+}
+
+impl SearchContext {
+    /// A fresh context whose table takes at most megabytes
+    pub fn with_megabytes(megabytes: usize) -> Self {
+        Self {
+            table: TranspositionTable::resize(megabytes),
+            killers: vec![],
+            history: [0; ROWS * COLS],
+            age: 0,
+            nodes: 0,
+            search_nodes: 0,
+            node_limit: None,
+            cancel: None,
+            cancelled: false,
+            draw_score: 0,
+        }
+    }
+
+    /// Forget everything learned, keeping the table's size, the cancel flag and the node limit
+    pub fn clear(&mut self) {
+        self.table.clear();
+        self.killers.clear();
+        self.history = [0; ROWS * COLS];
+        self.age = 0;
+        self.nodes = 0;
+        self.search_nodes = 0;
+        self.cancelled = false;
+    }
+
+    /// Swap in an empty table of at most megabytes
+    pub fn resize(&mut self, megabytes: usize) {
+        self.table = TranspositionTable::resize(megabytes);
+    }
+
+    /// Score drawn finishes as score from now on. The table's entries were scored with the old
+    /// value, so a change empties it
+    pub fn set_draw_score(&mut self, score: i32) {
+        if score != self.draw_score {
+            self.table.clear();
+            self.draw_score = score;
+        }
+    }
+
+    /// How the table has fared since the context was made, resized or cleared
+    pub fn table_stats(&self) -> TableStats {
+        self.table.stats
+    }
+
+    /// Whether the current search was cancelled, so any score it returned is unusable
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled
+    }
+
+    fn poll_cancel(&mut self) {
+        if let Some(cancel) = &self.cancel {
+            self.cancelled |= cancel.load(Ordering::Relaxed);
+        }
+    }
+
+    /// Start a new search: age the tables, so entries left unused for too long stop counting
+    pub fn new_search(&mut self) {
+        self.cancelled = false;
+        self.search_nodes = 0;
+        self.poll_cancel();
+        self.age += 1;
+        self.killers.clear();
+        for count in self.history.iter_mut() {
+            *count /= 2;
+        }
+    }
+
+    /// The line of best moves the table holds from board on, with passes where a side has no
+    /// move, stopping after max_moves placements or where the table runs out
+    pub fn principal_variation(&self, board: &Board, max_moves: usize) -> Vec<Move> {
+        let mut line = vec![];
+        let mut board = board.clone();
+        let mut placed = 0;
+        while placed < max_moves && !board.is_over() {
+            if !board.has_legal_move(board.turn) {
+                line.push(Move::Pass);
+                board = board.change_turn();
+                continue;
+            }
+            let best_move = self
+                .table
+                .get(&(board.packed(), board.turn), self.age)
+                .and_then(|entry| entry.best_move);
+            let Some(posn) = best_move.filter(|posn| board.is_legal(posn)) else {
+                break;
+            };
+            line.push(Move::Place(posn));
+            board = board.play_move(&posn);
+            placed += 1;
+        }
+        line
+    }
+
+    /// Try the table's best move first, then killers, then moves with the most history
+    fn order_moves(&self, moves: &mut [Posn], table_move: Option<Posn>, ply: usize) {
+        let killers = self.killers.get(ply).copied().unwrap_or([None, None]);
+        let same = |a: Option<Posn>, b: &Posn| a.is_some_and(|a| a.to_tuple() == b.to_tuple());
+        moves.sort_by_key(|posn| {
+            let rank = if same(table_move, posn) {
+                0
+            } else if killers.iter().any(|killer| same(*killer, posn)) {
+                1
+            } else {
+                2
+            };
+            (rank, std::cmp::Reverse(self.history[posn.index()]))
+        });
+    }
+
+    fn record_cutoff(&mut self, posn: Posn, depth: i32, ply: usize) {
+        if self.killers.len() <= ply {
+            self.killers.resize(ply + 1, [None, None]);
+        }
+        let killers = &mut self.killers[ply];
+        if killers[0].map(|k| k.to_tuple()) != Some(posn.to_tuple()) {
+            killers[1] = killers[0];
+            killers[0] = Some(posn);
+        }
+        self.history[posn.index()] += (depth * depth) as u32;
+    }
+}
+
+/// Alpha-beta like crate::alphabeta, but consulting and filling the context's tables. Once the
+/// context is cancelled, or the search reaches its node limit, it returns 0 at once without
+/// storing anything, so callers must check is_cancelled before trusting the score. A drawn
+/// finish scores whatever set_draw_score last set
+pub fn alphabeta(
+    board: &Board,
+    depth: i32,
+    alpha: i32,
+    beta: i32,
+    heuristic: fn(&Board) -> i32,
+    context: &mut SearchContext,
+    ply: usize,
+) -> i32 {
+    alphabeta_in_place(
+        &mut board.clone(),
+        depth,
+        alpha,
+        beta,
+        heuristic,
+        context,
+        ply,
+    )
+}
+
+/// alphabeta, making and unmaking each move on the one board rather than copying it. The board
+/// is back as it was on return, cancelled or not
+fn alphabeta_in_place(
+    board: &mut Board,
+    depth: i32,
+    mut alpha: i32,
+    mut beta: i32,
+    heuristic: fn(&Board) -> i32,
+    context: &mut SearchContext,
+    ply: usize,
+) -> i32 {
+    if context
+        .node_limit
+        .is_some_and(|limit| context.search_nodes >= limit)
+    {
+        context.cancelled = true;
+    }
+    if context.cancelled {
+        return 0;
+    }
+    context.nodes += 1;
+    context.search_nodes += 1;
+    if context.nodes.is_multiple_of(CANCEL_CHECK_INTERVAL) {
+        context.poll_cancel();
+    }
+    if context.cancelled {
+        return 0;
+    }
+    if board.is_over() {
+        return match board.winner() {
+            Some(_) => terminal_score(board),
+            None => context.draw_score,
+        };
+    }
+    if depth == 0 {
+        return heuristic(board);
+    }
+
+    let mut legal_moves = board.legal_moves();
+    if legal_moves.is_empty() {
+        // Forced pass: the opponent moves next without using up any depth
+        board.turn = board.turn.opponent();
+        let score = alphabeta_in_place(board, depth, alpha, beta, heuristic, context, ply);
+        board.turn = board.turn.opponent();
+        return score;
+    }
+
+    let key = (board.packed(), board.turn);
+    let mut table_move = None;
+    let age = context.age;
+    if let Some(entry) = context.table.probe(&key, age) {
+        table_move = entry.best_move;
+        let usable = match entry.bound {
+            Bound::Exact => true,
+            Bound::Lower => entry.score >= beta,
+            Bound::Upper => entry.score <= alpha,
+        };
+        if entry.depth >= depth && usable {
+            tracing::trace!(depth, ply, score = entry.score, "table hit");
+            return entry.score;
+        }
+    }
+    let (original_alpha, original_beta) = (alpha, beta);
+
+    context.order_moves(&mut legal_moves, table_move, ply);
+    let mut best_move = legal_moves[0];
+    let mut best = match board.turn {
+        Color::White => SCORE_LOSS,
+        Color::Black => SCORE_WIN,
+    };
+    for legal_move in legal_moves {
+        let undo = board.apply_in_place(&legal_move);
+        let score = alphabeta_in_place(board, depth - 1, alpha, beta, heuristic, context, ply + 1);
+        board.undo_move(&undo);
+        if context.cancelled {
+            return 0;
+        }
+        match board.turn {
+            Color::White => {
+                if score > best {
+                    best = score;
+                    best_move = legal_move;
+                }
+                alpha = alpha.max(score);
+            }
+            Color::Black => {
+                if score < best {
+                    best = score;
+                    best_move = legal_move;
+                }
+                beta = beta.min(score);
+            }
+        }
+        if alpha >= beta {
+            tracing::trace!(depth, ply, posn = %legal_move, "cutoff");
+            context.record_cutoff(legal_move, depth, ply);
+            break;
+        }
+    }
+
+    let bound = if best <= original_alpha {
+        Bound::Upper
+    } else if best >= original_beta {
+        Bound::Lower
+    } else {
+        Bound::Exact
+    };
+    context.table.store(
+        key,
+        TableEntry {
+            depth,
+            score: best,
+            bound,
+            best_move: Some(best_move),
+            age,
+        },
+    );
+    best
+}
+
+/// The best move and its score, like crate::alphabeta_root with no quiescence but searching
+/// through the context's tables, so it picks the same move. The board must have a legal move
+pub fn alphabeta_root(
+    board: &Board,
+    depth: i32,
+    heuristic: fn(&Board) -> i32,
+    context: &mut SearchContext,
+) -> (Posn, i32) {
+    let (mut alpha, mut beta) = (SCORE_LOSS, SCORE_WIN);
+    let legal_moves = board.legal_moves();
+    let mut best_move = legal_moves[0];
+    let mut best_score = match board.turn {
+        Color::White => SCORE_LOSS,
+        Color::Black => SCORE_WIN,
+    };
+    for legal_move in legal_moves {
+        let next = board.play_move(&legal_move);
+        let score = alphabeta(&next, depth - 1, alpha, beta, heuristic, context, 1);
+        match board.turn {
+            Color::White if score > best_score => {
+                best_score = score;
+                best_move = legal_move;
+                alpha = alpha.max(score);
+            }
+            Color::Black if score < best_score => {
+                best_score = score;
+                best_move = legal_move;
+                beta = beta.min(score);
+            }
+            _ => {}
+        }
+        if alpha >= beta {
+            break;
+        }
+    }
+    (best_move, best_score)
+}
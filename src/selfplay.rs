@@ -0,0 +1,251 @@
+//! Self-play game generation. Moves are sampled from the search's scores for every root move,
+//! softened by a temperature and mixed with Dirichlet noise, so games vary while staying strong.
+//! The temperature drops to zero after the opening, so the rest of each game is played straight.
+
+use statrs::distribution::{ContinuousCDF, Gamma};
+
+use crate::error::{OthelloError, Result};
+use crate::rng::{MoveRng, SeededRng};
+use crate::transcript::{GameRecord, TranscriptStyle};
+use crate::{
+    config, forced_move, weights, Agent, Board, Color, MinimaxAgent, Move, Posn, COLS, ROWS,
+};
+
+#[derive(Debug, Clone, Copy)]
+pub struct NoiseConfig {
+    /// Gamma(alpha, 1), whose normalized samples are Dirichlet noise of concentration alpha
+    noise: Gamma,
+    /// Share of the move probabilities that comes from the noise
+    pub epsilon: f64,
+    /// Softmax temperature, in score units, for the opening
+    pub temperature: f64,
+    /// From this ply on the temperature is zero, the noise is left out, and the best move is
+    /// always played
+    pub cool_after: usize,
+}
+
+impl NoiseConfig {
+    /// Noise of concentration alpha, which must be positive; smaller values put the noise on
+    /// fewer moves
+    pub fn new(alpha: f64, epsilon: f64, temperature: f64, cool_after: usize) -> Result<Self> {
+        Ok(Self {
+            noise: Gamma::new(alpha, 1.0)?,
+            epsilon,
+            temperature,
+            cool_after,
+        })
+    }
+}
+
+impl Default for NoiseConfig {
+    fn default() -> Self {
+        Self::new(0.3, 0.25, 2.0, 20).expect("the default alpha is positive")
+    }
+}
+
+/// Wraps a search that scores every root move and samples from those scores instead of always
+/// playing the best one, drawing from rng
+#[derive(Debug, Clone)]
+pub struct NoisySearchAgent<R: MoveRng = SeededRng> {
+    pub search: MinimaxAgent,
+    pub config: NoiseConfig,
+    rng: R,
+}
+
+impl<R: MoveRng> NoisySearchAgent<R> {
+    pub fn new(search: MinimaxAgent, config: NoiseConfig, rng: R) -> Self {
+        Self {
+            search,
+            config,
+            rng,
+        }
+    }
+
+    /// Discs placed since the standard start, which counts plies apart from passes
+    fn ply(board: &Board) -> usize {
+        (ROWS * COLS - 4).saturating_sub(board.count_empty())
+    }
+
+    fn temperature(&self, board: &Board) -> f64 {
+        if Self::ply(board) < self.config.cool_after {
+            self.config.temperature
+        } else {
+            0.0
+        }
+    }
+
+    /// Probability of playing each ranked move. At zero temperature it all goes to the search's
+    /// top move, with no noise
+    fn policy(&mut self, ranked: &[(Move, i32)], mover: Color, temperature: f64) -> Vec<f64> {
+        let mut policy: Vec<f64> = if temperature <= 0.0 {
+            (0..ranked.len())
+                .map(|i| if i == 0 { 1.0 } else { 0.0 })
+                .collect()
+        } else {
+            // Scores from the mover's point of view, shifted so the best is 0 to keep exp finite
+            let own_scores: Vec<f64> = ranked
+                .iter()
+                .map(|(_, score)| match mover {
+                    Color::White => *score as f64,
+                    Color::Black => -*score as f64,
+                })
+                .collect();
+            let best = own_scores.iter().copied().fold(f64::MIN, f64::max);
+            let weights: Vec<f64> = own_scores
+                .iter()
+                .map(|score| ((score - best) / temperature).exp())
+                .collect();
+            let total: f64 = weights.iter().sum();
+            weights.iter().map(|weight| weight / total).collect()
+        };
+
+        let epsilon = self.config.epsilon;
+        if epsilon > 0.0 && temperature > 0.0 && ranked.len() > 1 {
+            let noise = self.dirichlet(ranked.len());
+            for (p, n) in policy.iter_mut().zip(noise) {
+                *p = (1.0 - epsilon) * *p + epsilon * n;
+            }
+        }
+        policy
+    }
+
+    /// A sample from a symmetric Dirichlet distribution, by normalizing Gamma samples. Each Gamma
+    /// sample inverts its CDF at a uniform draw, which is all a MoveRng offers
+    fn dirichlet(&mut self, len: usize) -> Vec<f64> {
+        let samples: Vec<f64> = (0..len)
+            .map(|_| self.config.noise.inverse_cdf(self.rng.unit()))
+            .collect();
+        let total: f64 = samples.iter().sum();
+        if total > 0.0 {
+            samples.iter().map(|sample| sample / total).collect()
+        } else {
+            vec![1.0 / len as f64; len]
+        }
+    }
+}
+
+impl<R: MoveRng> Agent for NoisySearchAgent<R> {
+    fn choose_move(&mut self, board: &Board) -> Posn {
+        if let Some(posn) = forced_move(board) {
+            return posn;
+        }
+        let ranked = self.search.rank_moves(board);
+        let policy = self.policy(&ranked, board.turn, self.temperature(board));
+        let index = self.rng.weighted_index(&policy);
+        match ranked.get(index) {
+            Some((Move::Place(posn), _)) => *posn,
+            _ => panic!("choose_move called with no legal moves"),
+        }
+    }
+}
+
+/// Play one game between two noisy agents, returning every move including passes
+fn play_game(black: &mut NoisySearchAgent, white: &mut NoisySearchAgent) -> Vec<Move> {
+    black.search.new_game();
+    white.search.new_game();
+    let mut board = Board::new();
+    let mut moves = vec![];
+    while !board.is_over() {
+        if board.legal_moves().is_empty() {
+            moves.push(Move::Pass);
+            board = board.change_turn();
+            continue;
+        }
+        let posn = match board.turn {
+            Color::Black => black.choose_move(&board),
+            Color::White => white.choose_move(&board),
+        };
+        moves.push(Move::Place(posn));
+        board = board.play_move(&posn);
+    }
+    moves
+}
+
+/// selfplay <games> [depth] [seed]: print one transcript per game, ready for the stats command
+pub fn run(args: &[String]) -> Result<()> {
+    let Some(games) = args.first() else {
+        return Err(OthelloError::Usage(
+            "selfplay <games> [depth] [seed]".to_string(),
+        ));
+    };
+    let games: usize = games.parse()?;
+    let settings = &config::get().simulate;
+    let depth: i32 = args
+        .get(1)
+        .map(|d| d.parse())
+        .transpose()?
+        .unwrap_or(settings.depth);
+    let seed: u64 = args
+        .get(2)
+        .map(|s| s.parse())
+        .transpose()?
+        .unwrap_or(settings.seed);
+
+    let search = MinimaxAgent::new(depth, weights::engine_heuristic());
+    let config = NoiseConfig::default();
+    // Separate streams for each side, so changing one side's settings doesn't reshuffle the other
+    let mut black =
+        NoisySearchAgent::new(search.clone(), config, SeededRng::new(seed.wrapping_mul(2)));
+    let mut white = NoisySearchAgent::new(
+        search,
+        config,
+        SeededRng::new(seed.wrapping_mul(2).wrapping_add(1)),
+    );
+    for _ in 0..games {
+        let record = GameRecord::from_moves(play_game(&mut black, &mut white));
+        println!("{}", record.to_transcript(TranscriptStyle::Explicit));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{edge_corner_heuristic, random_agent_with};
+
+    /// With no noise and zero temperature, or past cool_after where both are dropped, the noisy agent
+    /// plays what its search would, whatever its seed, on positions a few random moves into seeded
+    /// games. NoiseConfig refuses a Dirichlet alpha that isn't positive
+    #[test]
+    fn noiseless_agent_plays_its_search() {
+        let rng = &mut SeededRng::new(0);
+        for alpha in [0.0, -1.0, f64::NAN] {
+            if NoiseConfig::new(alpha, 0.25, 2.0, 20).is_ok() {
+                panic!("noise with alpha {} was accepted", alpha);
+            }
+        }
+        const DEPTH: i32 = 3;
+        let straight = NoiseConfig::new(0.3, 0.0, 0.0, 20).unwrap();
+        let cooled = NoiseConfig::new(0.3, 0.25, 2.0, 0).unwrap();
+        for _ in 0..20 {
+            let mut board = Board::random_set_up_with(rng);
+            for _ in 0..rng.index(30) {
+                if board.is_over() {
+                    break;
+                }
+                board = match board.legal_moves().is_empty() {
+                    true => board.change_turn(),
+                    false => board.play_move(&random_agent_with(&board, rng)),
+                };
+            }
+            if board.legal_moves().is_empty() {
+                continue;
+            }
+            let search = MinimaxAgent::new(DEPTH, edge_corner_heuristic);
+            let expected = search.clone().choose_move(&board);
+            for (name, config) in [("no noise", straight), ("cooled", cooled)] {
+                for seed in 0..3 {
+                    let chosen =
+                        NoisySearchAgent::new(search.clone(), config, SeededRng::new(seed))
+                            .choose_move(&board);
+                    if chosen.to_tuple() != expected.to_tuple() {
+                        panic!(
+                            "the {} agent with seed {} played {}, not the search's {}\n{}",
+                            name, seed, chosen, expected, board
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
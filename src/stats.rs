@@ -0,0 +1,184 @@
+//! Statistics over a corpus of games: how often each position occurs and how it turned out.
+//! Positions are identified up to the board's eight symmetries, so mirrored lines are pooled.
+
+use std::collections::HashMap;
+
+use crate::error::Result;
+use crate::transcript::GameRecord;
+use crate::{Board, Color, Posn};
+
+/// Two bits per square, taking the smallest encoding over all symmetries
+pub type PositionKey = (u128, Color);
+
+pub fn canonical_key(board: &Board) -> PositionKey {
+    let encoding = (0..8)
+        .map(|symmetry| board.transformed(symmetry).packed())
+        .min()
+        .unwrap();
+    (encoding, board.turn)
+}
+
+/// Results of the games that passed through a position, from the point of view of the player to
+/// move there
+#[derive(Debug, Clone)]
+pub struct PositionEntry {
+    pub board: Board,
+    pub ply: usize,
+    pub visits: u32,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl PositionEntry {
+    /// Fraction of points scored by the player to move, counting draws as half
+    pub fn win_rate(&self) -> f64 {
+        (self.wins as f64 + 0.5 * self.draws as f64) / self.visits as f64
+    }
+}
+
+pub struct PositionStats {
+    /// Positions past this ply are ignored to keep memory bounded on large corpora
+    max_ply: usize,
+    entries: HashMap<PositionKey, PositionEntry>,
+}
+
+impl PositionStats {
+    pub fn new(max_ply: usize) -> Self {
+        Self {
+            max_ply,
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Count every position of the game up to max_ply. Unfinished games count as draws
+    pub fn add_game(&mut self, record: &GameRecord) -> Result<()> {
+        let replay = record.replay()?;
+        let winner = replay.final_board().winner();
+
+        for (ply, board) in replay.boards.iter().enumerate().take(self.max_ply + 1) {
+            let entry = self
+                .entries
+                .entry(canonical_key(board))
+                .or_insert_with(|| PositionEntry {
+                    board: board.clone(),
+                    ply,
+                    visits: 0,
+                    wins: 0,
+                    draws: 0,
+                    losses: 0,
+                });
+            entry.visits += 1;
+            match winner {
+                Some(color) if color == board.turn => entry.wins += 1,
+                Some(_) => entry.losses += 1,
+                None => entry.draws += 1,
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, board: &Board) -> Option<&PositionEntry> {
+        self.entries.get(&canonical_key(board))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// The legal move whose resulting position scored best for the mover, along with that score
+    /// and how many games reached it
+    pub fn best_move_by_winrate(&self, board: &Board) -> Option<(Posn, f64, u32)> {
+        board
+            .legal_moves()
+            .into_iter()
+            .filter_map(|posn| {
+                let child = board.play_move(&posn);
+                let entry = self.get(&child)?;
+                // The child is scored for its own mover, which is always the opponent
+                Some((posn, 1.0 - entry.win_rate(), entry.visits))
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    /// The most visited positions at or after the given ply, most common first
+    pub fn most_common(&self, min_ply: usize, count: usize) -> Vec<&PositionEntry> {
+        let mut entries: Vec<&PositionEntry> = self
+            .entries
+            .values()
+            .filter(|entry| entry.ply >= min_ply)
+            .collect();
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.visits));
+        entries.truncate(count);
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::PASS_GAME;
+    use crate::Move;
+
+    /// PositionStats over PASS_GAME's first 20 plies: the game fed in twice counts every position
+    /// twice with the same results, and a mirror image of the game pools with the original, leaving
+    /// no new entries
+    #[test]
+    fn position_stats_pool_symmetric_games() {
+        const MAX_PLY: usize = 20;
+        let record = GameRecord::from_transcript(PASS_GAME).unwrap_or_else(|err| panic!("{}", err));
+        let replay = record.replay().unwrap_or_else(|err| panic!("{}", err));
+        let stats_of = |records: &[&GameRecord]| {
+            let mut stats = PositionStats::new(MAX_PLY);
+            for record in records {
+                stats
+                    .add_game(record)
+                    .unwrap_or_else(|err| panic!("{}", err));
+            }
+            stats
+        };
+        // A symmetry that leaves the opening alone turns the game into another legal one
+        let symmetry = (1..8)
+            .find(|symmetry| Board::new().transformed(*symmetry) == Board::new())
+            .expect("no symmetry keeps the opening");
+        let mirrored = GameRecord::from_moves(
+            record
+                .moves
+                .iter()
+                .map(|mv| match mv {
+                    Move::Place(posn) => Move::Place(posn.transformed(symmetry)),
+                    Move::Pass => Move::Pass,
+                })
+                .collect(),
+        );
+        let once = stats_of(&[&record]);
+        for (name, stats) in [
+            ("twice", stats_of(&[&record, &record])),
+            ("with its mirror image", stats_of(&[&record, &mirrored])),
+        ] {
+            if stats.len() != once.len() {
+                panic!(
+                    "the game {} has {} positions, not {}",
+                    name,
+                    stats.len(),
+                    once.len()
+                );
+            }
+            for board in &replay.boards[..=MAX_PLY] {
+                let (single, double) = (once.get(board).unwrap(), stats.get(board).unwrap());
+                let counts =
+                    |entry: &PositionEntry| (entry.visits, entry.wins, entry.draws, entry.losses);
+                let (v, w, d, l) = counts(single);
+                if counts(double) != (2 * v, 2 * w, 2 * d, 2 * l) {
+                    panic!(
+                        "the game {} counted {:?} where once counted {:?}\n{}",
+                        name,
+                        counts(double),
+                        counts(single),
+                        board
+                    );
+                }
+            }
+        }
+    }
+}
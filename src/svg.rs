@@ -0,0 +1,215 @@
+//! Board images as hand-built SVG, laid out like the terminal board: a1 at the top left.
+
+use std::fmt::Write;
+
+use crate::{Board, Color, Posn, Square, COLS, POSNS, ROWS};
+
+#[derive(Debug, Clone, Copy)]
+pub struct SvgOptions {
+    /// Width and height of one square, in pixels
+    pub square_size: usize,
+    /// Draw a dot on every square the side to move could play
+    pub show_legal_moves: bool,
+    /// Draw a marker on the most recently played disc
+    pub last_move: Option<Posn>,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        Self {
+            square_size: 48,
+            show_legal_moves: true,
+            last_move: None,
+        }
+    }
+}
+
+impl Board {
+    pub fn to_svg(&self, options: &SvgOptions) -> String {
+        let size = options.square_size;
+        // Room along the top and left for the coordinate labels
+        let margin = size / 2;
+        let width = margin + COLS * size;
+        let height = margin + ROWS * size;
+        let center = |index: usize| margin + index * size + size / 2;
+
+        let mut svg = String::new();
+        // Writing to a String can't fail
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+        );
+        let _ = writeln!(
+            svg,
+            r##"<rect x="{margin}" y="{margin}" width="{}" height="{}" fill="#2e8b57"/>"##,
+            COLS * size,
+            ROWS * size
+        );
+
+        for i in 0..=COLS {
+            let x = margin + i * size;
+            let _ = writeln!(
+                svg,
+                r#"<line x1="{x}" y1="{margin}" x2="{x}" y2="{height}" stroke="black"/>"#
+            );
+        }
+        for i in 0..=ROWS {
+            let y = margin + i * size;
+            let _ = writeln!(
+                svg,
+                r#"<line x1="{margin}" y1="{y}" x2="{width}" y2="{y}" stroke="black"/>"#
+            );
+        }
+
+        for col in 0..COLS {
+            let _ = writeln!(
+                svg,
+                r#"<text x="{}" y="{}" text-anchor="middle" font-family="sans-serif">{}</text>"#,
+                center(col),
+                margin * 3 / 4,
+                (b'a' + col as u8) as char
+            );
+        }
+        for row in 0..ROWS {
+            let _ = writeln!(
+                svg,
+                r#"<text x="{}" y="{}" text-anchor="middle" dominant-baseline="middle" font-family="sans-serif">{}</text>"#,
+                margin / 2,
+                center(row),
+                row + 1
+            );
+        }
+
+        let legal_moves = if options.show_legal_moves {
+            self.legal_moves()
+        } else {
+            vec![]
+        };
+        for posn in POSNS {
+            let (cx, cy) = (center(posn.col), center(posn.row));
+            match self.piece_at(&posn) {
+                Square::Occupied(color) => {
+                    let (fill, stroke) = match color {
+                        Color::Black => ("black", "black"),
+                        Color::White => ("white", "black"),
+                    };
+                    let _ = writeln!(
+                        svg,
+                        r#"<circle cx="{cx}" cy="{cy}" r="{}" fill="{fill}" stroke="{stroke}"/>"#,
+                        size * 2 / 5
+                    );
+                }
+                Square::Unoccupied
+                    if legal_moves
+                        .iter()
+                        .any(|legal| legal.to_tuple() == posn.to_tuple()) =>
+                {
+                    let _ = writeln!(
+                        svg,
+                        r#"<circle cx="{cx}" cy="{cy}" r="{}" fill="black" fill-opacity="0.3"/>"#,
+                        size / 10
+                    );
+                }
+                Square::Unoccupied => {}
+            }
+        }
+
+        if let Some(last) = options.last_move {
+            let _ = writeln!(
+                svg,
+                r#"<rect x="{}" y="{}" width="{}" height="{}" fill="red"/>"#,
+                center(last.col) - size / 12,
+                center(last.row) - size / 12,
+                size / 6,
+                size / 6
+            );
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The opening's SVG has a circle for each of its 4 discs, and one more for each of Black's 4
+    /// legal moves when those are shown; the middlegame position of moves_rank_by_capture has one
+    /// per disc. The last-move marker adds a red square. Every tag closes, nested inside one svg
+    /// element
+    #[test]
+    fn svg_draws_discs_and_moves() {
+        let circles = |svg: &str| svg.matches("<circle ").count();
+        let opening = Board::new();
+        let hidden = SvgOptions {
+            show_legal_moves: false,
+            ..SvgOptions::default()
+        };
+        let middlegame = Board::from_position_string(
+            "---------OX-------OXXX-X--OOOOX-XXXXXXO----X-O------O----------- X",
+        )
+        .unwrap();
+        let discs = 64 - middlegame.count_empty();
+        let marked = SvgOptions {
+            last_move: Some(Posn::try_from_alphanumeric("d5").unwrap()),
+            ..SvgOptions::default()
+        };
+        for (name, svg, expected) in [
+            ("opening", opening.to_svg(&SvgOptions::default()), 8),
+            ("opening without legal moves", opening.to_svg(&hidden), 4),
+            ("middlegame", middlegame.to_svg(&hidden), discs),
+            ("marked opening", opening.to_svg(&marked), 8),
+        ] {
+            if circles(&svg) != expected {
+                panic!(
+                    "the {} SVG has {} circles, not {}\n{}",
+                    name,
+                    circles(&svg),
+                    expected,
+                    svg
+                );
+            }
+            if svg.contains(r#"fill="red""#) != (name == "marked opening") {
+                panic!("the {} SVG's last-move marker is wrong\n{}", name, svg);
+            }
+            check_xml_nesting(&svg)
+                .unwrap_or_else(|err| panic!("the {} SVG {}\n{}", name, err, svg));
+        }
+    }
+
+    /// Whether every tag in xml closes in order, within a single svg root element
+    fn check_xml_nesting(xml: &str) -> std::result::Result<(), String> {
+        let mut open: Vec<&str> = vec![];
+        let mut roots = 0;
+        for tag in xml.split('<').skip(1) {
+            let tag = tag
+                .split_once('>')
+                .map(|(tag, _)| tag)
+                .ok_or("has a tag that never ends")?;
+            if let Some(name) = tag.strip_prefix('/') {
+                if open.pop() != Some(name) {
+                    return Err(format!("closes {} out of order", name));
+                }
+                continue;
+            }
+            let name = tag.split_whitespace().next().unwrap_or("");
+            if open.is_empty() {
+                roots += 1;
+                if name != "svg" {
+                    return Err(format!("has a root {} element", name));
+                }
+            }
+            if !tag.ends_with('/') {
+                open.push(name);
+            }
+        }
+        if !open.is_empty() || roots != 1 {
+            return Err(format!(
+                "leaves {:?} open with {} root elements",
+                open, roots
+            ));
+        }
+        Ok(())
+    }
+}
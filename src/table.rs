@@ -0,0 +1,317 @@
+//! A fixed-size transposition table: a power-of-two array of slots, each position going to the slot
+//! its hash picks. When two positions want the same slot, the one searched deeper stays, unless
+//! the other has gone unused for too many searches. Its memory never grows past what it was
+//! created with, however long an analysis session runs.
+
+use crate::{Color, Posn};
+
+/// Entries not touched for this many searches count as empty
+pub const MAX_AGE: u32 = 4;
+
+/// Table size for a SearchContext that isn't given one
+pub const DEFAULT_MEGABYTES: usize = 4;
+
+/// Table size for a search made once and thrown away, like a hint or a single analysis, where
+/// filling a large table would cost more than the search
+pub const ONE_SHOT_MEGABYTES: usize = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    /// The true score is at least this much
+    Lower,
+    /// The true score is at most this much
+    Upper,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TableEntry {
+    pub depth: i32,
+    pub score: i32,
+    pub bound: Bound,
+    pub best_move: Option<Posn>,
+    /// The search that last stored or used this entry
+    pub age: u32,
+}
+
+/// A packed board and the side to move
+pub type TableKey = (u128, Color);
+
+#[derive(Debug, Clone, Copy)]
+struct Slot {
+    key: TableKey,
+    entry: TableEntry,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TableStats {
+    /// Lookups made by the search
+    pub probes: u64,
+    /// Lookups that found a live entry for the position
+    pub hits: u64,
+    /// Lookups that found the slot holding some other position
+    pub collisions: u64,
+    /// Stores that evicted some other position
+    pub replacements: u64,
+}
+
+impl TableStats {
+    /// Hits as a fraction of probes, or 0 before any probe
+    pub fn hit_rate(&self) -> f64 {
+        if self.probes == 0 {
+            0.0
+        } else {
+            self.hits as f64 / self.probes as f64
+        }
+    }
+}
+
+impl std::fmt::Display for TableStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} probes, {:.1}% hits, {} collisions, {} replacements",
+            self.probes,
+            self.hit_rate() * 100.0,
+            self.collisions,
+            self.replacements
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TranspositionTable {
+    slots: Vec<Option<Slot>>,
+    pub stats: TableStats,
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::resize(DEFAULT_MEGABYTES)
+    }
+}
+
+impl TranspositionTable {
+    /// The most slots that fit in megabytes, rounded down to a power of two (at least one slot)
+    pub fn resize(megabytes: usize) -> Self {
+        let slots = megabytes * 1024 * 1024 / std::mem::size_of::<Option<Slot>>();
+        Self::with_slots(slots)
+    }
+
+    /// A table of slots slots, rounded down to a power of two (at least one)
+    pub fn with_slots(slots: usize) -> Self {
+        let slots = match slots {
+            0 => 1,
+            _ => 1 << slots.ilog2(),
+        };
+        Self {
+            slots: vec![None; slots],
+            stats: TableStats::default(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Bytes the slots take, which is fixed when the table is made
+    pub fn bytes(&self) -> usize {
+        self.slots.capacity() * std::mem::size_of::<Option<Slot>>()
+    }
+
+    /// Slots holding an entry, stale ones included
+    pub fn len(&self) -> usize {
+        self.slots.iter().filter(|slot| slot.is_some()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Which slot key goes in: its bits mixed by splitmix64's finalizer, so boards a disc or two
+    /// apart land far from each other
+    pub fn index(&self, key: &TableKey) -> usize {
+        let (packed, turn) = key;
+        let mut hash = (*packed as u64) ^ ((*packed >> 64) as u64).rotate_left(29) ^ (*turn as u64);
+        hash ^= hash >> 30;
+        hash = hash.wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        hash ^= hash >> 27;
+        hash = hash.wrapping_mul(0x94d0_49bb_1331_11eb);
+        hash ^= hash >> 31;
+        hash as usize & (self.slots.len() - 1)
+    }
+
+    /// The live entry for key, marked as used by the search of age, counting the probe
+    pub fn probe(&mut self, key: &TableKey, age: u32) -> Option<&mut TableEntry> {
+        self.stats.probes += 1;
+        let index = self.index(key);
+        let (found, collided) = match &self.slots[index] {
+            Some(slot) if slot.key == *key => (age - slot.entry.age <= MAX_AGE, false),
+            Some(_) => (false, true),
+            None => (false, false),
+        };
+        if collided {
+            self.stats.collisions += 1;
+        }
+        if !found {
+            return None;
+        }
+        self.stats.hits += 1;
+        let slot = self.slots[index].as_mut()?;
+        slot.entry.age = age;
+        Some(&mut slot.entry)
+    }
+
+    /// The live entry for key as of the search of age, without counting a probe or touching it
+    pub fn get(&self, key: &TableKey, age: u32) -> Option<&TableEntry> {
+        match &self.slots[self.index(key)] {
+            Some(slot) if slot.key == *key && age - slot.entry.age <= MAX_AGE => Some(&slot.entry),
+            _ => None,
+        }
+    }
+
+    /// Store entry for key. A different position in the slot is only evicted if it has gone
+    /// stale or wasn't searched deeper than entry
+    pub fn store(&mut self, key: TableKey, entry: TableEntry) {
+        let index = self.index(&key);
+        let slot = &mut self.slots[index];
+        if let Some(held) = slot {
+            if held.key != key {
+                let stale = entry.age - held.entry.age > MAX_AGE;
+                if !stale && held.entry.depth > entry.depth {
+                    return;
+                }
+                self.stats.replacements += 1;
+            }
+        }
+        *slot = Some(Slot { key, entry });
+    }
+
+    /// Empty every slot and zero the counters, keeping the size
+    pub fn clear(&mut self) {
+        self.slots.fill(None);
+        self.stats = TableStats::default();
+    }
+}
+
+#[cfg(all(test, feature = "rand"))]
+mod tests {
+    use super::*;
+    use crate::fuzz::arbitrary_board;
+    use crate::rng::{MoveRng, SeededRng};
+    use crate::{
+        alphabeta, edge_corner_heuristic, random_agent_with, Board, MinimaxAgent, SCORE_LOSS,
+        SCORE_WIN,
+    };
+
+    /// A bounded table stays its size however much goes in, and a deeper entry wins its slot. Searches
+    /// through a one-slot table and a default one score every root move as plain alpha-beta does
+    #[test]
+    fn bounded_table_scores_as_alphabeta() {
+        let rng = &mut SeededRng::new(0);
+        let entry = |depth| TableEntry {
+            depth,
+            score: depth,
+            bound: Bound::Exact,
+            best_move: None,
+            age: 0,
+        };
+        let mut table = TranspositionTable::with_slots(64);
+        let bytes = table.bytes();
+        let keys: Vec<TableKey> = (0..1000)
+            .map(|_| (arbitrary_board(rng).packed(), Color::Black))
+            .collect();
+        for (i, key) in keys.iter().enumerate() {
+            table.store(*key, entry(i as i32 % 8));
+        }
+        if table.capacity() != 64 || table.bytes() != bytes || table.len() > 64 {
+            panic!(
+                "a 64-slot table of {} bytes holds {} entries in {} slots and {} bytes",
+                bytes,
+                table.len(),
+                table.capacity(),
+                table.bytes()
+            );
+        }
+        table.clear();
+        if !table.is_empty() || table.bytes() != bytes {
+            panic!("clearing the table left entries or changed its size");
+        }
+
+        let mut table = TranspositionTable::with_slots(64);
+        let (shallow, deep) = (
+            keys[0],
+            keys[1..]
+                .iter()
+                .copied()
+                .find(|key| table.index(key) == table.index(&keys[0]))
+                .expect("no two keys share a slot"),
+        );
+        table.store(shallow, entry(2));
+        table.store(deep, entry(5));
+        table.store(shallow, entry(1));
+        let deep_depth = table.probe(&deep, 0).map(|entry| entry.depth);
+        if deep_depth != Some(5)
+            || table.probe(&shallow, 0).is_some()
+            || table.stats.replacements != 1
+        {
+            panic!(
+                "after storing depths 2, 5 and 1 in one slot, the deep entry has depth {:?} and the \
+                 table counts {}",
+                deep_depth, table.stats
+            );
+        }
+
+        const DEPTH: i32 = 4;
+        for _ in 0..20 {
+            let mut board = Board::random_set_up_with(rng);
+            for _ in 0..rng.index(30) {
+                if board.is_over() {
+                    break;
+                }
+                board = match board.legal_moves().is_empty() {
+                    true => board.change_turn(),
+                    false => board.play_move(&random_agent_with(&board, rng)),
+                };
+            }
+            if board.legal_moves().is_empty() {
+                continue;
+            }
+            let mut expected: Vec<(String, i32)> = board
+                .legal_moves()
+                .iter()
+                .map(|posn| {
+                    let next = board.play_move(posn);
+                    let score = alphabeta(
+                        &next,
+                        DEPTH - 1,
+                        SCORE_LOSS,
+                        SCORE_WIN,
+                        edge_corner_heuristic,
+                        0,
+                    );
+                    (posn.to_string(), score)
+                })
+                .collect();
+            expected.sort();
+            for slots in [Some(0), None] {
+                let mut agent = MinimaxAgent::new(DEPTH, edge_corner_heuristic);
+                if let Some(megabytes) = slots {
+                    agent.context.resize(megabytes);
+                }
+                let mut ranked: Vec<(String, i32)> = agent
+                    .rank_moves(&board)
+                    .iter()
+                    .map(|(mv, score)| (mv.to_string(), *score))
+                    .collect();
+                ranked.sort();
+                if ranked != expected {
+                    panic!(
+                        "with a {:?} MB table the search scored {:?}, not {:?}\n{}",
+                        slots, ranked, expected, board
+                    );
+                }
+            }
+        }
+    }
+}
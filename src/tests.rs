@@ -0,0 +1,2218 @@
+//! Tests of the board, agents and search, and the fixtures the other modules' tests share
+
+use crate::error::OthelloError;
+#[cfg(feature = "rand")]
+use crate::fuzz::arbitrary_board;
+#[cfg(feature = "sim")]
+use crate::mcts::{self, MctsOptions};
+#[cfg(feature = "rand")]
+use crate::rng::{MoveRng, SeededRng};
+use crate::transcript::{parse_transcript, GameRecord};
+use crate::weights::engine_heuristic;
+#[cfg(feature = "rand")]
+use crate::{
+    alphabeta_root, aspiration_search, play_until_decided, quick_hint, random_agent_with,
+    solve_exact, AgentFn, BiasedMinimaxAgent,
+};
+use crate::{
+    alphanumeric_coords, analyze, decode_terminal, edge_corner_heuristic, exploit_agent,
+    format_grid, format_moves, greedy_agent, greedy_model, is_loss_score, is_win_score,
+    iterative_deepening, minimax_agent, minimize_opponent_mobility_agent, parse_ffo_position,
+    parse_move, play_from, replay_file, solve_command, solve_position, terminal_score,
+    AdaptiveAgent, Agent, Board, Color, Glyphs, MinimaxAgent, Move, Posn, RenderStyle,
+    SearchOptions, SearchStats, Square, COLS, DIRS, OPENING_FINGERPRINT, POSNS, QUIESCENCE_DEPTH,
+    ROWS, SCORE_LOSS, SCORE_WIN,
+};
+use crate::{analysis, standard_heuristic};
+#[cfg(feature = "sim")]
+use crate::{play_tournament, SavedPosterior, TournamentConfig, TournamentOutcome};
+#[cfg(feature = "sim")]
+use statrs::distribution::{Beta, ContinuousCDF};
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// A full game in which Black passes twice near the end, at plies 57 and 59
+pub(crate) const PASS_GAME: &str =
+    "e3f3g3e2d6h3f2f1g1e6f6d3g2h2e1f4h4h5d2c6b6c7h1a6h6e7d7c8e8f7g5d1b7a7\
+                         f8g4c3c2b2c5f5g6b8a8h7c4g7c1b3a4b1d8b4a3b5g8a2a5";
+
+/// Plays the moves it was given, in order
+pub(crate) struct ScriptedAgent(pub(crate) std::vec::IntoIter<Posn>);
+
+impl Agent for ScriptedAgent {
+    fn choose_move(&mut self, _board: &Board) -> Posn {
+        self.0.next().expect("script ran out of moves")
+    }
+}
+
+/// Every square's flat index must lead back to it, and nothing past the board may convert
+#[test]
+fn flat_indices_round_trip() {
+    for (i, posn) in POSNS.iter().enumerate() {
+        if posn.index() != i || Posn::from_index(i).map(|p| p.to_tuple()) != Some(posn.to_tuple()) {
+            panic!("{} doesn't round-trip through index {}", posn, i);
+        }
+    }
+    if Posn::try_from(POSNS.len()).is_ok() {
+        panic!("index {} converted to a square", POSNS.len());
+    }
+}
+
+/// Each color's opponent, name and parse, and what each kind of square says about its color,
+/// with From<Color> making the occupied ones
+#[test]
+fn squares_and_colors_convert() {
+    for (color, other, name) in [
+        (Color::Black, Color::White, "Black"),
+        (Color::White, Color::Black, "White"),
+    ] {
+        if color.opponent() != other || other.opponent() != color {
+            panic!("{}'s opponent is {}", color, color.opponent());
+        }
+        if color.to_string() != name || name.to_uppercase().parse::<Color>().ok() != Some(color) {
+            panic!("{:?} shows as {} and won't parse back", color, color);
+        }
+        let square = Square::from(color);
+        if square != Square::Occupied(color)
+            || square.color() != Some(color)
+            || !square.is_occupied()
+            || !square.is_color(color)
+            || square.is_color(other)
+        {
+            panic!("a {} square says otherwise about itself", name);
+        }
+    }
+    let empty = Square::Unoccupied;
+    if empty.color().is_some()
+        || empty.is_occupied()
+        || [Color::Black, Color::White]
+            .iter()
+            .any(|color| empty.is_color(*color))
+    {
+        panic!("an empty square claims a color");
+    }
+    if "grey".parse::<Color>().is_ok() {
+        panic!("\"grey\" parsed as a color");
+    }
+}
+
+/// The neighbor table must agree with neighbor_in_dir's arithmetic everywhere
+#[test]
+fn neighbor_table_matches_the_arithmetic() {
+    for posn in POSNS {
+        for (i, dir) in DIRS.into_iter().enumerate() {
+            let (table, computed) = (posn.neighbor(dir), posn.neighbor_in_dir(&dir));
+            if dir as usize != i || table.map(Posn::to_tuple) != computed.map(Posn::to_tuple) {
+                panic!(
+                    "{:?} of {}: table gives {:?}, arithmetic gives {:?}",
+                    dir, posn, table, computed
+                );
+            }
+        }
+    }
+}
+
+/// What the REPL's "setturn white" then "moves" shows from the opening
+#[test]
+fn setturn_then_moves_lists_whites_moves() {
+    let board = Board::new().with_turn("white".parse().unwrap_or_else(|err| panic!("{}", err)));
+    let moves = format_moves(&board);
+    if board.turn != Color::White || moves != "White's moves: d3, c4, f5, e6" {
+        panic!("after setturn white, {} to move: {}", board.turn, moves);
+    }
+}
+
+/// The opening drawn in each style, character for character
+#[test]
+fn opening_renders_in_every_style() {
+    let golden = [
+        (
+            RenderStyle::Unicode,
+            " abcdefgh\n1________\n2________\n3________\n4___○●___\n5___●○___\n6________\n7________\n8________\n",
+        ),
+        (
+            RenderStyle::Ascii,
+            " abcdefgh\n1........\n2........\n3........\n4...XO...\n5...OX...\n6........\n7........\n8........\n",
+        ),
+    ];
+    for (style, expected) in golden {
+        let rendered = Board::new().render(style);
+        if rendered != expected {
+            panic!(
+                "{:?} opening rendered as\n{}instead of\n{}",
+                style, rendered, expected
+            );
+        }
+    }
+
+    // Ansi wraps every cell in its own colors, and once they're stripped is the Unicode board with
+    // one disc glyph for both sides
+    let ansi = Board::new().render(RenderStyle::Ansi);
+    let black_d4 = "4\x1b[42;33m·\x1b[0m\x1b[42;33m·\x1b[0m\x1b[42;33m·\x1b[0m\x1b[42;30m●\x1b[0m";
+    let stripped: String = ansi
+        .split("\x1b[")
+        .enumerate()
+        .map(|(i, piece)| {
+            if i == 0 {
+                piece
+            } else {
+                &piece[piece.find('m').unwrap() + 1..]
+            }
+        })
+        .collect();
+    let unicode = Board::new()
+        .render(RenderStyle::Unicode)
+        .replace('_', "·")
+        .replace('○', "●");
+    if !ansi
+        .lines()
+        .nth(4)
+        .is_some_and(|row| row.starts_with(black_d4))
+        || stripped != unicode
+    {
+        panic!("Ansi opening rendered as {:?}", ansi);
+    }
+
+    // Bigger boards pad the row numbers so the columns still line up
+    let mut cells = vec![vec!['.'; 10]; 10];
+    cells[9][9] = 'W';
+    let expected = "  abcdefghij\n 1..........\n 2..........\n 3..........\n 4..........\n \
+                    5..........\n 6..........\n 7..........\n 8..........\n 9..........\n\
+                    10.........W\n";
+    let rendered = format_grid(&cells);
+    if rendered != expected {
+        panic!(
+            "10x10 grid rendered as\n{}instead of\n{}",
+            rendered, expected
+        );
+    }
+    let j10 = Posn { row: 9, col: 9 };
+    if j10.to_string() != "j10" || alphanumeric_coords("j10") != Some((9, 9)) {
+        panic!(
+            "j10 formats as {} and parses as {:?}",
+            j10,
+            alphanumeric_coords("j10")
+        );
+    }
+    if Posn::try_from_alphanumeric("j10").is_some() || Posn::try_from_alphanumeric("e03").is_some()
+    {
+        panic!("j10 or e03 parsed as a square of the 8x8 board");
+    }
+}
+
+/// The opening's char grid is 8 rows of 8 with Black's glyph on d4 and e5, White's on e4 and d5,
+/// and the empty glyph everywhere else, in whatever glyphs it's given
+#[test]
+fn char_grid_places_the_opening_glyphs() {
+    let glyphs = Glyphs {
+        empty: '-',
+        black: 'b',
+        white: 'w',
+    };
+    let grid = Board::new().to_char_grid(&glyphs);
+    let mut expected = vec![vec!['-'; COLS]; ROWS];
+    for (square, glyph) in [("d4", 'b'), ("e5", 'b'), ("e4", 'w'), ("d5", 'w')] {
+        let posn = Posn::try_from_alphanumeric(square).unwrap();
+        expected[posn.row][posn.col] = glyph;
+    }
+    if grid != expected {
+        panic!("the opening's char grid is {:?}", grid);
+    }
+}
+
+/// Black's greediest move here, b1, hands White the a1 corner. Counting discs, a depth 1 search
+/// takes it anyway, and a depth 3 search sees the corner coming and plays c3
+#[test]
+fn deeper_hint_sees_the_corner_coming() {
+    let board = Board::from_position_string(
+        "--XO-O----OOO------OXXX---OOOXXO-XXXOXX---O--O------------------ X",
+    )
+    .expect("the hint position didn't parse");
+    let rows = analysis::evaluate_at_depths(&board, &[1, 3], standard_heuristic);
+    let moves: Vec<String> = rows.iter().map(|(_, posn, _)| posn.to_string()).collect();
+    if moves != ["b1", "c3"] {
+        panic!("expected b1 at depth 1 and c3 at depth 3, got {:?}", rows);
+    }
+}
+
+/// The opening's fingerprint is documented, so bug reports stay comparable across versions
+#[test]
+fn opening_fingerprint_is_stable() {
+    let fingerprint = Board::new().fingerprint();
+    if fingerprint != OPENING_FINGERPRINT {
+        panic!(
+            "opening fingerprint is {}, not {}",
+            fingerprint, OPENING_FINGERPRINT
+        );
+    }
+}
+
+/// Black's moves in a middlegame position ranked by capture: b4 takes the row of four to g4, a1
+/// the diagonal of three to e5, and equal counts keep legal_moves order. Each count must match
+/// the pieces the move would actually flip
+#[test]
+fn moves_rank_by_capture() {
+    let board = Board::from_position_string(
+        "---------OX-------OXXX-X--OOOOX-XXXXXXO----X-O------O----------- X",
+    )
+    .unwrap();
+    let ranked: Vec<(String, usize)> = board
+        .moves_by_capture()
+        .into_iter()
+        .map(|(posn, count)| (posn.to_string(), count))
+        .collect();
+    let expected = [
+        ("b4", 4),
+        ("a1", 3),
+        ("b3", 2),
+        ("h6", 2),
+        ("a2", 1),
+        ("g3", 1),
+        ("h5", 1),
+        ("g6", 1),
+        ("f7", 1),
+        ("g7", 1),
+        ("f8", 1),
+    ]
+    .map(|(posn, count)| (posn.to_string(), count));
+    if ranked != expected {
+        panic!("moves by capture were {:?}", ranked);
+    }
+    for (posn, count) in board.moves_by_capture() {
+        let flipped = board.potential_flipped_pieces(&posn).len();
+        if flipped != count {
+            panic!("{} flips {} pieces, not {}", posn, flipped, count);
+        }
+    }
+}
+
+/// In moves_rank_by_capture's position the map shows b4's 4, a1's 3 and b3's 2, 0 on the
+/// occupied c2 and the illegal h1, and on every square the count potential_flipped_pieces finds
+#[test]
+fn flip_potential_map_counts_each_move() {
+    let board = Board::from_position_string(
+        "---------OX-------OXXX-X--OOOOX-XXXXXXO----X-O------O----------- X",
+    )
+    .unwrap();
+    let map = board.flip_potential_map();
+    for (square, expected) in [("b4", 4), ("a1", 3), ("b3", 2), ("c2", 0), ("h1", 0)] {
+        let posn = Posn::try_from_alphanumeric(square).unwrap();
+        if map[posn.index()] != expected {
+            panic!(
+                "the map has {} on {}, not {}",
+                map[posn.index()],
+                square,
+                expected
+            );
+        }
+    }
+    for posn in POSNS {
+        let flipped = board.potential_flipped_pieces(&posn).len();
+        if map[posn.index()] != flipped {
+            panic!(
+                "the map has {} on {}, but playing there flips {}",
+                map[posn.index()],
+                posn,
+                flipped
+            );
+        }
+    }
+}
+
+/// The opening's count grid shows "1" on each of Black's four legal squares, where the legal-move
+/// grid shows '*', and nothing else differs. A move flipping 12, six along the first row and six
+/// down the h column, shows as "+"
+#[test]
+fn flip_count_grid_marks_the_legal_squares() {
+    let opening = Board::new();
+    let counts = opening.flip_count_grid(RenderStyle::Ascii);
+    let marked = opening.legal_move_grid(RenderStyle::Ascii);
+    if counts != marked.replace('*', "1") || marked.matches('*').count() != 4 {
+        panic!(
+            "the opening's counts are\n{}where its legal moves are\n{}",
+            counts, marked
+        );
+    }
+    let flips: Vec<(String, usize)> = opening
+        .flip_counts()
+        .iter()
+        .map(|(posn, count)| (posn.to_string(), *count))
+        .collect();
+    let expected = ["e3", "f4", "c5", "d6"].map(|posn| (posn.to_string(), 1));
+    if flips != expected {
+        panic!("the opening's flip counts are {:?}", flips);
+    }
+
+    let board = Board::from_position_string(&format!("XOOOOOO-{}-------X X", "-------O".repeat(6)))
+        .unwrap();
+    let h1 = Posn::try_from_alphanumeric("h1").unwrap();
+    let first_row = board.flip_count_grid(RenderStyle::Ascii);
+    let first_row = first_row
+        .lines()
+        .find(|line| line.trim_start().starts_with('1'));
+    if board.flipped_count(&h1) != 12 || !first_row.is_some_and(|row| row.ends_with('+')) {
+        panic!(
+            "h1 flips {} and shows as {:?}\n{}",
+            board.flipped_count(&h1),
+            first_row,
+            board
+        );
+    }
+}
+
+/// After e3 from the opening each side has three moves. In moves_rank_by_capture's position b4
+/// leaves White six replies, and Black as many moves as it would have if White passed
+#[test]
+fn mobility_after_counts_the_replies() {
+    let e3 = Posn::try_from_alphanumeric("e3").unwrap();
+    if Board::new().mobility_after(&e3) != (3, 3) {
+        panic!(
+            "e3 from the opening leaves mobilities {:?}, not (3, 3)",
+            Board::new().mobility_after(&e3)
+        );
+    }
+    let board = Board::from_position_string(
+        "---------OX-------OXXX-X--OOOOX-XXXXXXO----X-O------O----------- X",
+    )
+    .unwrap();
+    let b4 = Posn::try_from_alphanumeric("b4").unwrap();
+    let next = board.play_move(&b4);
+    let own = next.change_turn().legal_moves().len();
+    if board.mobility_after(&b4) != (own, 6) {
+        panic!(
+            "b4 leaves mobilities {:?}, not ({}, 6)\n{}",
+            board.mobility_after(&b4),
+            own,
+            next
+        );
+    }
+}
+
+/// In moves_rank_by_capture's position greedy_agent plays b4, the one move flipping four. From
+/// the opening every move flips one, and the tie goes to the first of them for Black and the last
+/// for White. With no legal move it passes
+#[test]
+fn greedy_agent_takes_the_biggest_capture() {
+    let board = Board::from_position_string(
+        "---------OX-------OXXX-X--OOOOX-XXXXXXO----X-O------O----------- X",
+    )
+    .unwrap();
+    let greediest = greedy_agent(&board);
+    if greediest.to_string() != "b4" {
+        panic!("greedy_agent played {}, not b4\n{}", greediest, board);
+    }
+    for opening in [Board::new(), Board::new().with_turn(Color::White)] {
+        let legal = opening.legal_moves();
+        let expected = match opening.turn {
+            Color::Black => legal[0],
+            Color::White => legal[legal.len() - 1],
+        };
+        let tied = greedy_agent(&opening);
+        if tied.to_string() != expected.to_string() {
+            panic!(
+                "from the opening greedy_agent played {} for {:?}, not {}",
+                tied, opening.turn, expected
+            );
+        }
+    }
+    let replay = GameRecord::from_transcript(PASS_GAME)
+        .and_then(|record| record.replay())
+        .unwrap_or_else(|err| panic!("pass game didn't replay: {}", err));
+    let stuck = &replay.boards[56];
+    if !stuck.legal_moves().is_empty() || !matches!(greedy_agent(stuck), Move::Pass) {
+        panic!("greedy_agent didn't pass with no legal moves\n{}", stuck);
+    }
+}
+
+/// greedy_agent as an AgentFn, for games that only ask it when it has a move
+fn greedy_fn(board: &Board) -> Posn {
+    match greedy_agent(board) {
+        Move::Place(posn) => posn,
+        Move::Pass => panic!("greedy_agent passed with legal moves\n{}", board),
+    }
+}
+
+/// b4 leaves White 6 replies where every other Black move leaves at least 14, so
+/// minimize_opponent_mobility_agent plays it; and it passes where Black has no move in PASS_GAME
+#[test]
+fn mobility_agent_leaves_the_fewest_replies() {
+    let board = Board::from_position_string(
+        "---------OX-------OXXX-X--OOOOX-XXXXXXO----X-O------O----------- X",
+    )
+    .unwrap();
+    match minimize_opponent_mobility_agent(&board) {
+        Move::Place(posn) if posn.to_string() == "b4" => {}
+        other => {
+            panic!(
+                "played {} rather than b4, which leaves White {} replies\n{}",
+                other,
+                board
+                    .mobility_after(&Posn::try_from_alphanumeric("b4").unwrap())
+                    .1,
+                board
+            )
+        }
+    }
+
+    let replay = GameRecord::from_transcript(PASS_GAME)
+        .and_then(|record| record.replay())
+        .unwrap_or_else(|err| panic!("pass game didn't replay: {}", err));
+    let stuck = &replay.boards[56];
+    if !stuck.legal_moves().is_empty()
+        || !matches!(minimize_opponent_mobility_agent(stuck), Move::Pass)
+    {
+        panic!("didn't pass at ply 57 of the pass game\n{}", stuck);
+    }
+}
+
+/// Black well ahead with every move but c7 in the top five handing White a corner: the adaptive
+/// agent gives up its best move, c3, for one that concedes no corner. White well behind gets
+/// plain minimax's move. A whole game against greedy_agent only ever sees legal moves from it
+#[test]
+fn adaptive_agent_avoids_conceding_corners() {
+    const WINNING: &str = "-XXXXX----XXX------XX-----OXXX----XOOOX----OOXX-----X-X-----XXX- X";
+    const LOSING: &str = "-XXXXX----XXX------XX------XXX----XXO-X----OOX------X----------- O";
+    let adaptive = || AdaptiveAgent::new(3, edge_corner_heuristic, 8, 1);
+
+    let winning = Board::from_position_string(WINNING).unwrap();
+    let best = MinimaxAgent::new(3, edge_corner_heuristic).choose_move(&winning);
+    let eased = adaptive().choose_move(&winning);
+    if !winning.is_legal(&eased)
+        || eased.index() == best.index()
+        || winning.concedes_corner(&eased).is_some()
+    {
+        panic!(
+            "well ahead, the adaptive agent played {} against minimax's {}\n{}",
+            eased, best, winning
+        );
+    }
+
+    let losing = Board::from_position_string(LOSING).unwrap();
+    let best = MinimaxAgent::new(3, edge_corner_heuristic).choose_move(&losing);
+    let played = adaptive().choose_move(&losing);
+    if played.index() != best.index() {
+        panic!(
+            "behind, the adaptive agent played {} rather than {}\n{}",
+            played, best, losing
+        );
+    }
+
+    let mut agent = AdaptiveAgent::new(2, edge_corner_heuristic, 4, 3);
+    let mut board = Board::new();
+    while !board.is_over() {
+        board = if board.legal_moves().is_empty() {
+            board.change_turn()
+        } else if board.turn == Color::Black {
+            let posn = agent.choose_move(&board);
+            if !board.is_legal(&posn) {
+                panic!("the adaptive agent played illegal {}\n{}", posn, board);
+            }
+            board.play_move(&posn)
+        } else {
+            board.play_move(&greedy_fn(&board))
+        };
+    }
+}
+
+/// solve's library path on a 12-empty position: Black's only winning-big move is b8, worth 22
+/// discs exactly. The same position written with dots and lowercase letters reads the same, and
+/// a short board, unknown side or depth below 1 is refused
+#[test]
+fn solve_finds_the_winning_move() {
+    const SQUARES: &str = "X-X-XO-OOXXXOOO---XXXX-XX-OOXXXXXXOOXXXXXOOOOOXXXXOXXO-XX-OOOO--";
+    let board = parse_ffo_position(SQUARES, "black").unwrap_or_else(|err| panic!("{}", err));
+    let analysis = solve_position(&board, 1);
+    if analysis.best_move.to_string() != "b8" || analysis.value != -22 {
+        panic!(
+            "solved {} as {} for {}",
+            SQUARES, analysis.best_move, analysis.value
+        );
+    }
+    let dotted = SQUARES.replace('-', ".").to_lowercase();
+    if parse_ffo_position(&dotted, "X").ok() != Some(board) {
+        panic!("{} X didn't read as {}", dotted, SQUARES);
+    }
+    if parse_ffo_position(&SQUARES[1..], "black").is_ok()
+        || parse_ffo_position(SQUARES, "red").is_ok()
+    {
+        panic!("a short board or a red side to move parsed");
+    }
+    for depth in ["0", "-3"] {
+        let args = [SQUARES, "black", "--depth", depth].map(String::from);
+        if !matches!(solve_command(&args), Err(OthelloError::Usage(_))) {
+            panic!("solve accepted --depth {}", depth);
+        }
+    }
+}
+
+/// Seeded games between two deterministic agents must have the same winner whether or not they
+/// stop once the winner is decided, and some of them must actually stop early
+#[cfg(feature = "rand")]
+#[test]
+fn adjudication_keeps_the_winner() {
+    let rng = &mut SeededRng::new(0);
+    const GAMES: usize = 50;
+    let mut adjudicated = 0;
+    for game in 0..GAMES {
+        let start = Board::random_set_up_with(rng);
+        let mut minimax = MinimaxAgent::new(2, edge_corner_heuristic);
+        let mut greedy: AgentFn = greedy_fn;
+        let full = play_from(start.clone(), &mut minimax, &mut greedy);
+        minimax.new_game();
+        let (stopped, _) = play_until_decided(start.clone(), &mut minimax, &mut greedy, &mut [])
+            .unwrap_or_else(|err| panic!("adjudicated game failed: {}", err));
+        let early_winner = if stopped.is_over() {
+            stopped.winner()
+        } else {
+            adjudicated += 1;
+            stopped.decided_winner()
+        };
+        if early_winner != full.winner {
+            panic!(
+                "game {} from {} was adjudicated for {:?} but {:?} won\n{}",
+                game,
+                start.to_position_string(),
+                early_winner,
+                full.winner,
+                stopped
+            );
+        }
+    }
+    if adjudicated == 0 {
+        panic!("none of {} games was adjudicated early", GAMES);
+    }
+}
+
+/// A seeded tournament must come out the same game for game whether it's played on one thread or
+/// spread over many
+#[cfg(feature = "sim")]
+#[test]
+fn parallel_tournament_matches_sequential() {
+    let run = |parallel| {
+        let config = TournamentConfig {
+            games: 8,
+            parallel,
+            seed: Some(11),
+            ..TournamentConfig::default()
+        };
+        play_tournament(&config, &mut []).unwrap_or_else(|err| panic!("tournament: {}", err))
+    };
+    let (sequential, parallel) = (run(false), run(true));
+    let tallies = |outcome: &TournamentOutcome| {
+        (
+            outcome.minimax_wins,
+            outcome.standard_wins,
+            outcome.ties,
+            outcome.minimax_results.clone(),
+        )
+    };
+    if tallies(&sequential) != tallies(&parallel) {
+        panic!(
+            "sequential tournament tallied {:?} but parallel {:?}",
+            tallies(&sequential),
+            tallies(&parallel)
+        );
+    }
+    let same_finals = sequential
+        .finals
+        .iter()
+        .zip(&parallel.finals)
+        .all(|(a, b)| a.fingerprint() == b.fingerprint());
+    if !same_finals {
+        panic!("sequential and parallel tournaments ended differently");
+    }
+}
+
+/// Saving a posterior and loading it back must give the same credible interval, a missing file
+/// must load as the fresh Beta(2, 2) prior, and a tournament resumed from a saved posterior must
+/// add its games on top of it
+#[cfg(feature = "sim")]
+#[test]
+fn posterior_saves_loads_and_resumes() {
+    let interval = |beta: &Beta| (beta.inverse_cdf(0.05), beta.inverse_cdf(0.95));
+    let path = std::env::temp_dir().join(format!(
+        "othello-posterior-check-{}.json",
+        std::process::id()
+    ));
+    let path = path.to_str().expect("temp path isn't UTF-8");
+    let _ = std::fs::remove_file(path);
+    let missing = SavedPosterior::load(path).unwrap_or_else(|err| panic!("load missing: {}", err));
+    if missing != SavedPosterior::default() {
+        panic!("a missing file loaded as {:?}", missing);
+    }
+
+    let posterior = Beta::new(7.0, 12.5).unwrap_or_else(|err| panic!("{}", err));
+    let loaded = SavedPosterior::of(&posterior)
+        .save(path)
+        .and_then(|()| SavedPosterior::load(path))
+        .and_then(SavedPosterior::to_beta);
+    let loaded = loaded.unwrap_or_else(|err| panic!("save and load: {}", err));
+    if interval(&loaded) != interval(&posterior) {
+        panic!(
+            "saved interval {:?} loaded as {:?}",
+            interval(&posterior),
+            interval(&loaded)
+        );
+    }
+
+    let config = TournamentConfig {
+        games: 2,
+        seed: Some(5),
+        prior: SavedPosterior::of(&posterior),
+        ..TournamentConfig::default()
+    };
+    let outcome =
+        play_tournament(&config, &mut []).unwrap_or_else(|err| panic!("tournament: {}", err));
+    let _ = std::fs::remove_file(path);
+    let expected = (
+        7.0 + outcome.standard_wins as f64,
+        12.5 + outcome.minimax_wins as f64,
+    );
+    let resumed = (outcome.posterior.shape_a(), outcome.posterior.shape_b());
+    if resumed != expected {
+        panic!(
+            "resumed posterior was Beta{:?}, not Beta{:?}",
+            resumed, expected
+        );
+    }
+}
+
+/// With White on c3 and e5 around Black's d4, Black's b2 lets White take a1 along the diagonal,
+/// while f6 gives White nothing but g7
+#[test]
+fn concedes_corner_along_the_diagonal() {
+    let board = Board::from_position_string(
+        "------------------O--------X--------O--------------------------- X",
+    )
+    .unwrap();
+    let square = |name| Posn::try_from_alphanumeric(name).unwrap();
+    let conceded = |name| {
+        board
+            .concedes_corner(&square(name))
+            .map(|corner| corner.to_string())
+    };
+    if conceded("b2").as_deref() != Some("a1") || conceded("f6").is_some() {
+        panic!(
+            "b2 concedes {:?} and f6 concedes {:?}, expected a1 and nothing\n{}",
+            conceded("b2"),
+            conceded("f6"),
+            board
+        );
+    }
+}
+
+/// In this middlegame a depth-2 search without quiescence plays g8, which hands White a8, while
+/// resolving corner fights past the horizon finds h4, which concedes nothing
+#[test]
+fn quiescence_sees_past_the_horizon() {
+    let board = Board::from_position_string(
+        "-----------O--------O----XXXOOO--X-XOOOO-XXOO-OO-XOX-O-O-O--X--O X",
+    )
+    .unwrap();
+    let search = |quiescence_depth| {
+        let options = SearchOptions {
+            max_depth: 2,
+            quiescence_depth,
+            ..SearchOptions::default()
+        };
+        let (posn, _) = iterative_deepening(&board, &options, &mut SearchStats::default());
+        (
+            posn.to_string(),
+            board
+                .concedes_corner(&posn)
+                .map(|corner| corner.to_string()),
+        )
+    };
+    let (plain, resolved) = (search(None), search(Some(QUIESCENCE_DEPTH)));
+    if plain != ("g8".to_string(), Some("a8".to_string())) || resolved != ("h4".to_string(), None) {
+        panic!(
+            "without quiescence the search played {:?} and with it {:?}, conceding the corners \
+             shown\n{}",
+            plain, resolved, board
+        );
+    }
+}
+
+/// After Black's b2 in concedes_corner_along_the_diagonal's position, White's best move is the a1 corner it was
+/// handed, and the explanation must say it secures the corner
+#[test]
+fn explanation_names_the_corner() {
+    let board = Board::from_position_string(
+        "------------------O--------X--------O--------------------------- X",
+    )
+    .unwrap()
+    .play_move(&Posn::try_from_alphanumeric("b2").unwrap());
+    let explanation = MinimaxAgent::new(3, edge_corner_heuristic).explain(&board);
+    if !explanation.starts_with("White plays a1 to secure the corner") {
+        panic!("a1 was explained as \"{}\"\n{}", explanation, board);
+    }
+}
+
+/// Every form parse_move accepts, and a pile it rejects with the reason it gives
+#[test]
+fn parse_move_accepts_and_rejects() {
+    let accepted = [
+        ("f5", "f5"),
+        ("F5", "f5"),
+        ("  e3\n", "e3"),
+        ("a1", "a1"),
+        ("H8", "h8"),
+        ("0", "a1"),
+        ("37", "f5"),
+        ("11", "d2"),
+        ("63", "h8"),
+        ("007", "h1"),
+        ("pass", "pass"),
+        ("PASS", "pass"),
+        ("--", "pass"),
+        ("PA", "pass"),
+    ];
+    for (text, expected) in accepted {
+        match parse_move(text) {
+            Ok(mv) if mv.to_string() == expected => {}
+            other => {
+                panic!(
+                    "{:?} parsed as {:?}, not {}",
+                    text,
+                    other.map(|mv| mv.to_string()),
+                    expected
+                )
+            }
+        }
+    }
+    let rejected = [
+        ("", "Empty move"),
+        ("   ", "Empty move"),
+        ("64", "Square index 64 is off the board"),
+        ("99999999999999999999999", "is off the board"),
+        ("i1", "i1 is off the board"),
+        ("a9", "a9 is off the board"),
+        ("j10", "j10 is off the board"),
+        // Row 4294967299 would wrap to row 3 if cast to i32 before the range check
+        ("e4294967299", "e4294967299 is off the board"),
+        ("a0", "Unrecognized move"),
+        ("e", "Unrecognized move"),
+        ("5f", "Unrecognized move"),
+        ("e3e", "Unrecognized move"),
+        ("-3", "Unrecognized move"),
+        ("e-3", "Unrecognized move"),
+        ("passs", "Unrecognized move"),
+        ("é3", "Unrecognized move"),
+    ];
+    for (text, reason) in rejected {
+        match parse_move(text) {
+            Err(err) if err.to_string().contains(reason) => {}
+            other => {
+                panic!(
+                    "{:?} should be rejected with \"{}\" but gave {:?}",
+                    text,
+                    reason,
+                    other.map(|mv| mv.to_string())
+                )
+            }
+        }
+    }
+    if let Some(posn) = Posn::try_from_alphanumeric("e4294967299") {
+        panic!("e4294967299 was read as {}", posn);
+    }
+    // Transcripts go through the same parser, but only separated moves may be indices
+    let transcript = |text| {
+        parse_transcript(text)
+            .map(|moves| {
+                moves
+                    .iter()
+                    .map(Move::to_string)
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .map_err(|err| err.to_string())
+    };
+    let expected = Ok("f5 d6 c3 pass e3".to_string());
+    for text in ["f5d6c3--e3", "F5 D6, 18 pass 20", "f5d6 c3pae3"] {
+        if transcript(text) != expected {
+            panic!("transcript {:?} parsed as {:?}", text, transcript(text));
+        }
+    }
+    if transcript("a10").is_ok() || transcript("f5 37d6").is_ok() {
+        panic!("a run of moves was read with an index in it");
+    }
+}
+
+/// The principal variation must open with the move the agent would play and replay legally, passes
+/// included, on positions a few random moves into seeded games
+#[cfg(feature = "rand")]
+#[test]
+fn principal_variation_replays_legally() {
+    let rng = &mut SeededRng::new(0);
+    const DEPTH: i32 = 4;
+    for _ in 0..20 {
+        let mut board = Board::random_set_up_with(rng);
+        for _ in 0..rng.index(30) {
+            if board.is_over() {
+                break;
+            }
+            board = match board.legal_moves().is_empty() {
+                true => board.change_turn(),
+                false => board.play_move(&random_agent_with(&board, rng)),
+            };
+        }
+        if board.legal_moves().is_empty() {
+            continue;
+        }
+        let line = MinimaxAgent::new(DEPTH, edge_corner_heuristic).principal_variation(&board);
+        let chosen = MinimaxAgent::new(DEPTH, edge_corner_heuristic).choose_move(&board);
+        let describe = || {
+            line.iter()
+                .map(Move::to_string)
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        match line.first() {
+            Some(Move::Place(first)) if first.to_tuple() == chosen.to_tuple() => {}
+            _ => {
+                panic!(
+                    "best line {} doesn't start with the chosen {}\n{}",
+                    describe(),
+                    chosen,
+                    board
+                )
+            }
+        }
+        let mut replayed = board.clone();
+        for mv in &line {
+            replayed = match mv {
+                Move::Place(posn) if replayed.is_legal(posn) => replayed.play_move(posn),
+                Move::Pass if replayed.legal_moves().is_empty() => replayed.change_turn(),
+                _ => {
+                    panic!("best line {} has illegal {}\n{}", describe(), mv, board)
+                }
+            };
+        }
+    }
+}
+
+/// rank_moves must list every legal move exactly once, best first, and lead with the move
+/// choose_move plays, on positions a few random moves into seeded games
+#[cfg(feature = "rand")]
+#[test]
+fn rank_moves_lists_every_move_best_first() {
+    let rng = &mut SeededRng::new(0);
+    const DEPTH: i32 = 3;
+    for _ in 0..20 {
+        let mut board = Board::random_set_up_with(rng);
+        for _ in 0..rng.index(30) {
+            if board.is_over() {
+                break;
+            }
+            board = match board.legal_moves().is_empty() {
+                true => board.change_turn(),
+                false => board.play_move(&random_agent_with(&board, rng)),
+            };
+        }
+        if board.legal_moves().is_empty() {
+            continue;
+        }
+        let ranked = MinimaxAgent::new(DEPTH, edge_corner_heuristic).rank_moves(&board);
+        let chosen = MinimaxAgent::new(DEPTH, edge_corner_heuristic).choose_move(&board);
+        let mut ranked_moves: Vec<String> = ranked.iter().map(|(mv, _)| mv.to_string()).collect();
+        ranked_moves.sort();
+        let mut legal: Vec<String> = board.legal_moves().iter().map(Posn::to_string).collect();
+        legal.sort();
+        if ranked_moves != legal {
+            panic!(
+                "ranked {:?} but the legal moves are {:?}\n{}",
+                ranked_moves, legal, board
+            );
+        }
+        // Scores are White's, so Black's best is the lowest
+        let out_of_order = |pair: &[(Move, i32)]| match board.turn {
+            Color::White => pair[0].1 < pair[1].1,
+            Color::Black => pair[0].1 > pair[1].1,
+        };
+        if ranked.windows(2).any(out_of_order) {
+            panic!("ranking {:?} isn't best first\n{}", ranked, board);
+        }
+        if ranked[0].0.to_string() != chosen.to_string() {
+            panic!(
+                "ranking {:?} leads with {}, but the agent plays {}\n{}",
+                ranked, ranked[0].0, chosen, board
+            );
+        }
+    }
+}
+
+/// With endgame_threshold at 12, a depth-1 agent solves 12-empty positions from seeded games
+/// exactly: every root move gets its perfect-play final score, and the move it plays is one with
+/// the best of them. With the threshold at 11 the same agent scores them by its heuristic instead
+#[cfg(feature = "rand")]
+#[test]
+fn endgame_threshold_solves_exactly() {
+    let rng = &mut SeededRng::new(0);
+    const EMPTIES: usize = 12;
+    let mut solved = 0;
+    while solved < 3 {
+        let mut board = Board::new();
+        while board.count_empty() > EMPTIES && !board.is_over() {
+            board = match board.legal_moves().is_empty() {
+                true => board.change_turn(),
+                false => board.play_move(&random_agent_with(&board, rng)),
+            };
+        }
+        if board.count_empty() != EMPTIES || board.legal_moves().is_empty() {
+            continue;
+        }
+        solved += 1;
+
+        let exact: Vec<(String, i32)> = board
+            .legal_moves()
+            .iter()
+            .map(|posn| {
+                let score = solve_exact(&board.play_move(posn), SCORE_LOSS, SCORE_WIN);
+                (posn.to_string(), score)
+            })
+            .collect();
+        let best = match board.turn {
+            Color::White => exact.iter().map(|(_, score)| *score).max(),
+            Color::Black => exact.iter().map(|(_, score)| *score).min(),
+        };
+        let agent = |endgame_threshold| MinimaxAgent {
+            endgame_threshold,
+            ..MinimaxAgent::new(1, edge_corner_heuristic)
+        };
+        let sorted = |ranked: Vec<(Move, i32)>| {
+            let mut ranked: Vec<(String, i32)> = ranked
+                .iter()
+                .map(|(mv, score)| (mv.to_string(), *score))
+                .collect();
+            ranked.sort();
+            ranked
+        };
+        let mut expected = exact.clone();
+        expected.sort();
+        let ranked = sorted(agent(EMPTIES).rank_moves(&board));
+        if ranked != expected {
+            panic!(
+                "solving exactly scored {:?}, not {:?}\n{}",
+                ranked, expected, board
+            );
+        }
+        let chosen = agent(EMPTIES).choose_move(&board).to_string();
+        let chosen_score = exact
+            .iter()
+            .find(|(posn, _)| *posn == chosen)
+            .map(|(_, score)| *score);
+        if chosen_score != best {
+            panic!(
+                "the solver played {} scoring {:?}, but the best scores {:?}\n{}",
+                chosen, chosen_score, best, board
+            );
+        }
+        let mut heuristic: Vec<(String, i32)> = board
+            .legal_moves()
+            .iter()
+            .map(|posn| {
+                (
+                    posn.to_string(),
+                    edge_corner_heuristic(&board.play_move(posn)),
+                )
+            })
+            .collect();
+        heuristic.sort();
+        if sorted(agent(EMPTIES - 1).rank_moves(&board)) != heuristic {
+            panic!(
+                "below its threshold the agent didn't score by its heuristic\n{}",
+                board
+            );
+        }
+    }
+}
+
+/// An agent that searched a position two plies earlier must visit fewer nodes on the continuation
+/// than a fresh agent, summed over positions from seeded games. After new_game it must search the
+/// continuation exactly as a fresh agent does
+#[cfg(feature = "rand")]
+#[test]
+fn tree_reuse_saves_nodes() {
+    let rng = &mut SeededRng::new(0);
+    let (mut cold_nodes, mut warm_nodes) = (0, 0);
+    for _ in 0..20 {
+        let mut board = Board::random_set_up_with(rng);
+        for _ in 0..rng.index(30) {
+            if board.is_over() {
+                break;
+            }
+            board = match board.legal_moves().is_empty() {
+                true => board.change_turn(),
+                false => board.play_move(&random_agent_with(&board, rng)),
+            };
+        }
+        let mut continuation = board.clone();
+        for _ in 0..2 {
+            match continuation.legal_moves().first() {
+                Some(posn) => continuation = continuation.play_move(posn),
+                None => break,
+            }
+        }
+        if board.legal_moves().is_empty()
+            || continuation.legal_moves().is_empty()
+            || continuation.count_empty() + 2 != board.count_empty()
+        {
+            continue;
+        }
+
+        let mut warm = MinimaxAgent::new(4, edge_corner_heuristic);
+        warm.rank_moves(&board);
+        let before = warm.context.nodes;
+        warm.rank_moves(&continuation);
+        warm_nodes += warm.context.nodes - before;
+
+        let mut cold = MinimaxAgent::new(4, edge_corner_heuristic);
+        cold.rank_moves(&continuation);
+        cold_nodes += cold.context.nodes;
+
+        warm.new_game();
+        warm.rank_moves(&continuation);
+        if warm.context.nodes != cold.context.nodes {
+            panic!(
+                "after new_game the search took {} nodes where a fresh one took {}\n{}",
+                warm.context.nodes, cold.context.nodes, continuation
+            );
+        }
+    }
+    if warm_nodes >= cold_nodes {
+        panic!(
+            "continuing searches took {} nodes, no fewer than the {} of cold ones",
+            warm_nodes, cold_nodes
+        );
+    }
+}
+
+/// With six empties White wins whether it plays c6 or e8, both filling the board, by 6 and by 10
+/// discs. Searching to the end, the agent must play e8 and score it as the 10-disc win
+#[test]
+fn search_prefers_the_wider_margin() {
+    let board = Board::from_position_string(
+        "XOOOOOOOXOXXXOOOXOXXOOOOXOXXXOOOXOXXXOOOXX-OXOOOXOOO-XO--O-X-OX- O",
+    )
+    .unwrap();
+    let ranked = MinimaxAgent::new(12, edge_corner_heuristic).rank_moves(&board);
+    let (best, score) = ranked[0];
+    if best.to_string() != "e8" || decode_terminal(score) != Some((64, 10)) {
+        panic!(
+            "the agent ranked {} first with {:?}, not e8 winning by 10 on a full board\n{}",
+            best,
+            decode_terminal(score),
+            board
+        );
+    }
+}
+
+/// White's b2 forces a 17-disc win that ends with one square left empty, while b8 wins by 28 but
+/// only once the board is full. Searching to the end, the agent must take the sooner win
+#[test]
+fn search_prefers_the_sooner_win() {
+    let board = Board::from_position_string(
+        "OOOOOOOOO-OOOXXXOOXOXOXXOXOXOOXXXXXOOOXXXXOOOOXXXXXXXOX-----XXX- O",
+    )
+    .unwrap();
+    let ranked = MinimaxAgent::new(12, edge_corner_heuristic).rank_moves(&board);
+    let (best, score) = ranked[0];
+    let b8 = ranked.iter().find(|(mv, _)| mv.to_string() == "b8");
+    if best.to_string() != "b2"
+        || decode_terminal(score) != Some((63, 17))
+        || b8.and_then(|(_, score)| decode_terminal(*score)) != Some((64, 28))
+    {
+        panic!(
+            "the agent ranked {:?}, not b2 winning by 17 at 63 discs ahead of b8 winning by 28 \
+             at 64\n{}",
+            ranked
+                .iter()
+                .map(|(mv, score)| (mv.to_string(), decode_terminal(*score)))
+                .collect::<Vec<_>>(),
+            board
+        );
+    }
+}
+
+/// Played out by deep searchers from search_prefers_the_sooner_win's position, with White to move, White plays
+/// b2 and wins by 17 with one square left empty. The same discs with Black to move go another way
+#[test]
+fn play_from_plays_out_the_position() {
+    const POSITION: &str = "OOOOOOOOO-OOOXXXOOXOXOXXOXOXOOXXXXXOOOXXXXOOOOXXXXXXXOX-----XXX-";
+    let start = Board::from_position_string(&format!("{} O", POSITION)).unwrap();
+    let searcher = || MinimaxAgent::new(12, edge_corner_heuristic);
+    let outcome = play_from(start.clone(), &mut searcher(), &mut searcher());
+    let b2 = Posn::try_from_alphanumeric("b2").unwrap();
+    let (end, winner) = (&outcome.final_board, outcome.winner);
+    if winner != Some(Color::White)
+        || !end.is_over()
+        || end.score() != 17
+        || end.count_empty() != 1
+        || end.piece_at(&b2) != Square::Occupied(Color::White)
+    {
+        panic!(
+            "from\n{}the game ended won by {:?} on\n{}",
+            start, winner, end
+        );
+    }
+    let black_first = Board::from_position_string(&format!("{} X", POSITION)).unwrap();
+    let outcome = play_from(black_first.clone(), &mut searcher(), &mut searcher());
+    if !outcome.final_board.is_over() || outcome.final_board.squares == end.squares {
+        panic!(
+            "with Black to move the game ended on\n{}",
+            outcome.final_board
+        );
+    }
+}
+
+/// Win scores negate into the matching loss scores and back, for every final margin on a full
+/// board and for wipeouts. A sooner win outscores a later one whatever the margins, and analysis
+/// shows forced results by winner, margin and distance instead of as raw scores
+#[test]
+fn terminal_scores_encode_margin_and_distance() {
+    if SCORE_LOSS != -SCORE_WIN || SCORE_WIN.checked_mul(2).is_none() {
+        panic!(
+            "scores from {} to {} can't be negated and widened safely",
+            SCORE_LOSS, SCORE_WIN
+        );
+    }
+    let full = |black: usize| {
+        Board::from_position_string(&format!(
+            "{}{} X",
+            "X".repeat(black),
+            "O".repeat(64 - black)
+        ))
+        .unwrap()
+    };
+    let wipeout = |white: usize| {
+        Board::from_position_string(&format!(
+            "{}{} X",
+            "O".repeat(white),
+            "-".repeat(64 - white)
+        ))
+        .unwrap()
+    };
+    let finals = (0..=64)
+        .map(|black| (full(black), 64))
+        .chain((1..64).map(|white| (wipeout(white), white)));
+    for (board, filled) in finals {
+        let (score, margin) = (terminal_score(&board), board.score());
+        let swapped = terminal_score(&board.color_swapped());
+        let round_trip = if margin == 0 {
+            score == 0 && swapped == 0 && decode_terminal(score).is_none()
+        } else {
+            swapped == -score
+                && decode_terminal(score) == Some((filled, margin))
+                && decode_terminal(-score) == Some((filled, -margin))
+                && is_win_score(score) == (margin > 0)
+                && is_loss_score(-score) == (margin > 0)
+        };
+        if !round_trip {
+            panic!(
+                "the final position scores {}, {} with colors swapped, decoding to {:?}\n{}",
+                score,
+                swapped,
+                decode_terminal(score),
+                board
+            );
+        }
+    }
+    let (soon, late) = (terminal_score(&wipeout(5)), terminal_score(&full(0)));
+    if soon <= late {
+        panic!(
+            "winning by 5 with 5 discs placed scores {}, no more than {} for winning by 64 on a \
+             full board",
+            soon, late
+        );
+    }
+
+    let opening = Board::new();
+    let win = terminal_score(&full(27));
+    for (eval, expected) in [
+        (win, "W+10 in 60 plies"),
+        (-win, "B+10 in 60 plies"),
+        (37, "37"),
+    ] {
+        if analysis::format_eval(eval, &opening) != expected {
+            panic!(
+                "{} from the opening shows as {}, not {}",
+                eval,
+                analysis::format_eval(eval, &opening),
+                expected
+            );
+        }
+    }
+}
+
+/// Only a finished game with equal discs is a draw: not the level opening, and not a full board
+/// one side has more of
+#[test]
+fn only_a_finished_level_game_is_a_draw() {
+    let full = |black: usize| {
+        let position = "X".repeat(black) + &"O".repeat(64 - black);
+        Board::from_position_string(&format!("{} X", position)).unwrap()
+    };
+    if Board::new().is_draw() {
+        panic!("the opening counts as a draw");
+    }
+    if !full(32).is_draw() || full(32).winner().is_some() {
+        panic!("a full board split 32 to 32 isn't a draw");
+    }
+    if full(33).is_draw() {
+        panic!("a full board Black leads 33 to 31 counts as a draw");
+    }
+}
+
+/// Discs on b2 and g7 (X-squares), b1, h2 and g8 (C-squares), c3 and a4 (neither) count only
+/// where they should, for the color they are
+#[test]
+fn corner_squares_count_by_color() {
+    let mut squares = vec!['-'; 64];
+    for (name, disc) in [
+        ("b2", 'X'),
+        ("g7", 'O'),
+        ("b1", 'X'),
+        ("h2", 'X'),
+        ("g8", 'O'),
+        ("c3", 'X'),
+        ("a4", 'O'),
+    ] {
+        squares[Posn::try_from_alphanumeric(name).unwrap().index()] = disc;
+    }
+    let position: String = squares.into_iter().collect();
+    let board = Board::from_position_string(&format!("{} X", position)).unwrap();
+    let counts = [
+        board.x_square_count(Color::Black),
+        board.x_square_count(Color::White),
+        board.c_square_count(Color::Black),
+        board.c_square_count(Color::White),
+    ];
+    if counts != [1, 1, 2, 1] {
+        panic!(
+            "X-square and C-square counts (Black X, White X, Black C, White C) were {:?}",
+            counts
+        );
+    }
+    let x_squares = POSNS.iter().filter(|posn| posn.is_x_square()).count();
+    let c_squares = POSNS.iter().filter(|posn| posn.is_c_square()).count();
+    if (x_squares, c_squares) != (4, 8) {
+        panic!(
+            "the board has {} X-squares and {} C-squares",
+            x_squares, c_squares
+        );
+    }
+}
+
+/// A full board of Black but for White's b1 and empty a1, d8 and h8: Black can take a1 through
+/// b1, and nobody can move to d8 or h8, which are the quiet empties whichever side is to move
+#[test]
+fn quiet_empties_are_unreachable() {
+    let mut squares = vec!['X'; 64];
+    squares[Posn::try_from_alphanumeric("b1").unwrap().index()] = 'O';
+    for name in ["a1", "d8", "h8"] {
+        squares[Posn::try_from_alphanumeric(name).unwrap().index()] = '-';
+    }
+    let position: String = squares.into_iter().collect();
+    for side in ["X", "O"] {
+        let board = Board::from_position_string(&format!("{} {}", position, side)).unwrap();
+        let quiet: Vec<String> = board.quiet_empties().iter().map(Posn::to_string).collect();
+        if quiet != ["d8", "h8"] {
+            panic!(
+                "with {} to move the quiet empties were {:?}, not d8 and h8\n{}",
+                side, quiet, board
+            );
+        }
+    }
+}
+
+/// A full board but for a1, b1, b2 in one corner and g8, h8 in the other has exactly those two
+/// pockets as its regions; diagonal neighbors count as touching
+#[test]
+fn empty_regions_split_by_pocket() {
+    let mut squares = vec!['X'; 64];
+    for name in ["a1", "b1", "b2", "g8", "h8"] {
+        squares[Posn::try_from_alphanumeric(name).unwrap().index()] = '-';
+    }
+    let position: String = squares.into_iter().collect();
+    let board = Board::from_position_string(&format!("{} O", position)).unwrap();
+    let regions: Vec<Vec<String>> = board
+        .empty_regions()
+        .iter()
+        .map(|region| region.iter().map(Posn::to_string).collect())
+        .collect();
+    if regions != [vec!["a1", "b1", "b2"], vec!["g8", "h8"]] {
+        panic!("the two pockets came out as regions {:?}", regions);
+    }
+    let opening_regions = Board::new().empty_regions();
+    if opening_regions.len() != 1 || opening_regions[0].len() != 60 {
+        panic!(
+            "the opening's empties formed {} regions",
+            opening_regions.len()
+        );
+    }
+}
+
+/// Empties on every square whose row and column are both odd-numbered, a1, c1 ... g7, can never be
+/// filled: each direction from one reaches another within two steps, or the edge. With h8 empty
+/// behind Black's f8 and White's g8 too, Black still has h8 to play, and the 16 pocket squares are
+/// the dead ones while the game goes on and after it ends
+#[test]
+fn dead_empties_find_a_permanent_pocket() {
+    let mut squares = vec!['X'; 64];
+    let pocket: Vec<String> = POSNS
+        .into_iter()
+        .filter(|posn| {
+            let (row, col) = posn.to_tuple();
+            row % 2 == 0 && col % 2 == 0
+        })
+        .map(|posn| posn.to_string())
+        .collect();
+    for name in &pocket {
+        squares[square(name).index()] = '-';
+    }
+    squares[square("g8").index()] = 'O';
+    squares[square("h8").index()] = '-';
+    let position: String = squares.into_iter().collect();
+    let board = Board::from_position_string(&format!("{} X", position)).unwrap();
+    let legal: Vec<String> = board.legal_moves().iter().map(Posn::to_string).collect();
+    if board.is_over() || legal != ["h8"] {
+        panic!("Black's moves are {:?}, not just h8\n{}", legal, board);
+    }
+    for board in [board.clone(), board.play_move(&square("h8"))] {
+        let dead = square_names(board.dead_empties());
+        if dead != square_names(pocket.iter().map(|name| square(name))) {
+            panic!("the dead empties are {:?}\n{}", dead, board);
+        }
+    }
+}
+
+/// A file of one move per line and one with the moves run together both replay to the same
+/// position, a move for each ply; a file with e3 played twice stops at ply 2 naming the move
+#[test]
+fn replay_file_reads_both_layouts() {
+    let path =
+        std::env::temp_dir().join(format!("othello-replay-check-{}.txt", std::process::id()));
+    let path_name = path.to_string_lossy().into_owned();
+    let replay_text = |text: &str| {
+        std::fs::write(&path, text).unwrap_or_else(|err| panic!("{}", err));
+        let mut plies = 0;
+        let result = replay_file(&path_name, |_, _| plies += 1);
+        (result, plies)
+    };
+    let expected = Board::new()
+        .play_move(&Posn::try_from_alphanumeric("e3").unwrap())
+        .play_move(&Posn::try_from_alphanumeric("f3").unwrap())
+        .play_move(&Posn::try_from_alphanumeric("g3").unwrap());
+    for text in ["e3\nf3\ng3\n", "e3f3g3"] {
+        match replay_text(text) {
+            (Ok(replay), 3) if *replay.final_board() == expected => {}
+            (result, plies) => {
+                let _ = std::fs::remove_file(&path);
+                panic!(
+                    "replaying {:?} gave {:?} after {} plies",
+                    text,
+                    result.map(|replay| replay.final_board().clone()),
+                    plies
+                );
+            }
+        }
+    }
+    let illegal = replay_text("e3\ne3\n");
+    let _ = std::fs::remove_file(&path);
+    match illegal {
+        (Err(OthelloError::IllegalMove(message)), 1)
+            if message.contains("e3") && message.contains("ply 2") => {}
+        (result, plies) => {
+            panic!(
+                "replaying e3 twice gave {:?} after {} plies",
+                result.map(|replay| replay.final_board().clone()),
+                plies
+            )
+        }
+    }
+}
+
+/// A deep search capped at a few node counts must still play a legal move, never visit more
+/// positions than its cap, and choose the same move every time for the same cap
+#[cfg(feature = "rand")]
+#[test]
+fn node_cap_bounds_the_search() {
+    let rng = &mut SeededRng::new(0);
+    const DEPTH: i32 = 10;
+    for _ in 0..10 {
+        let board = arbitrary_board(rng);
+        if board.legal_moves().is_empty() {
+            continue;
+        }
+        for cap in [1, 100, 2000] {
+            let mut agent = MinimaxAgent::new(DEPTH, edge_corner_heuristic);
+            agent.max_nodes = Some(cap);
+            let posn = agent.choose_move(&board);
+            if !board.is_legal(&posn) {
+                panic!(
+                    "capped at {} nodes, played illegal {}\n{}",
+                    cap, posn, board
+                );
+            }
+            if agent.context.search_nodes > cap as u64 {
+                panic!(
+                    "capped at {} nodes, searched {}\n{}",
+                    cap, agent.context.search_nodes, board
+                );
+            }
+            let mut again = MinimaxAgent::new(DEPTH, edge_corner_heuristic);
+            again.max_nodes = Some(cap);
+            if again.choose_move(&board).index() != posn.index() {
+                panic!("capped at {} nodes, two searches disagreed\n{}", cap, board);
+            }
+        }
+    }
+}
+
+/// White to move can draw at once with c5 (four discs each, and nobody can move) or play on with
+/// d5. Seeking draws takes c5 and avoiding them takes d5, both in a plain search and for
+/// MinimaxAgent. With no contempt, deepening searches must match a plain full-window search
+/// exactly
+#[cfg(feature = "rand")]
+#[test]
+fn contempt_seeks_or_avoids_draws() {
+    let rng = &mut SeededRng::new(0);
+    let board = Board::from_position_string(
+        "-----X-----------OO-------X---------X-------X-----------X------- O",
+    )
+    .unwrap();
+    let c5 = Posn::try_from_alphanumeric("c5").unwrap();
+    let drawn = board.play_move(&c5);
+    if !drawn.is_over() || drawn.score() != 0 {
+        panic!("c5 doesn't draw at once\n{}", drawn);
+    }
+    let choose = |contempt| {
+        let options = SearchOptions {
+            max_depth: 1,
+            heuristic: standard_heuristic,
+            contempt,
+            ..SearchOptions::default()
+        };
+        iterative_deepening(&board, &options, &mut SearchStats::default())
+            .0
+            .to_string()
+    };
+    if (choose(-3).as_str(), choose(3).as_str()) != ("c5", "d5") {
+        panic!(
+            "contempt -3 played {} and contempt 3 played {}, not c5 and d5",
+            choose(-3),
+            choose(3)
+        );
+    }
+    let mut seeking = MinimaxAgent {
+        contempt: -3,
+        ..MinimaxAgent::new(1, standard_heuristic)
+    };
+    let mut avoiding = MinimaxAgent {
+        contempt: 3,
+        ..MinimaxAgent::new(1, standard_heuristic)
+    };
+    let (seeking_move, avoiding_move) = (seeking.choose_move(&board), avoiding.choose_move(&board));
+    if (
+        seeking_move.to_string().as_str(),
+        avoiding_move.to_string().as_str(),
+    ) != ("c5", "d5")
+    {
+        panic!(
+            "MinimaxAgents with contempt -3 and 3 played {} and {}, not c5 and d5",
+            seeking_move, avoiding_move
+        );
+    }
+    // Changing an agent's contempt mid-game takes effect at once
+    seeking.contempt = 3;
+    if seeking.choose_move(&board).to_string() != "d5" {
+        panic!("MinimaxAgent kept seeking the draw after its contempt changed");
+    }
+
+    for _ in 0..20 {
+        let board = arbitrary_board(rng);
+        if board.is_placing_center() || board.legal_moves().is_empty() {
+            continue;
+        }
+        let options = SearchOptions {
+            max_depth: 3,
+            aspiration_delta: None,
+            ..SearchOptions::default()
+        };
+        let (posn, score) = iterative_deepening(&board, &options, &mut SearchStats::default());
+        let (plain_posn, plain_score) =
+            alphabeta_root(&board, 3, SCORE_LOSS, SCORE_WIN, options.heuristic, 0);
+        if (posn.index(), score) != (plain_posn.index(), plain_score) {
+            panic!(
+                "without contempt the search chose {} ({}) but plain alpha-beta {} ({})\n{}",
+                posn, score, plain_posn, plain_score, board
+            );
+        }
+    }
+}
+
+/// On seeded random boards, an aspiration search at depth 4 around the depth-3 score picks the
+/// same move with the same score as a full-window search at depth 4
+#[cfg(feature = "rand")]
+#[test]
+fn aspiration_search_matches_full_window() {
+    let rng = &mut SeededRng::new(0);
+    let options = SearchOptions::default();
+    let delta = options
+        .aspiration_delta
+        .expect("aspiration is on by default");
+    for _ in 0..40 {
+        let board = arbitrary_board(rng);
+        if board.is_placing_center() || board.legal_moves().is_empty() {
+            continue;
+        }
+        let (_, expected) = alphabeta_root(&board, 3, SCORE_LOSS, SCORE_WIN, options.heuristic, 0);
+        let (posn, score) = aspiration_search(
+            &board,
+            4,
+            expected,
+            delta,
+            &options,
+            &mut SearchStats::default(),
+        );
+        let (full_posn, full_score) =
+            alphabeta_root(&board, 4, SCORE_LOSS, SCORE_WIN, options.heuristic, 0);
+        if (posn.index(), score) != (full_posn.index(), full_score) {
+            panic!(
+                "around {} the aspiration search chose {} ({}) but the full window {} ({})\n{}",
+                expected, posn, score, full_posn, full_score, board
+            );
+        }
+    }
+}
+
+/// An aspiration window one unit wide misses on both sides over a suite of seeded random boards,
+/// and the counters record it, yet each board's move and score still match the full window's
+#[cfg(feature = "rand")]
+#[test]
+fn tiny_aspiration_windows_fail_and_recover() {
+    let rng = &mut SeededRng::new(0);
+    let full_width = SearchOptions {
+        max_depth: 4,
+        aspiration_delta: None,
+        ..SearchOptions::default()
+    };
+    let tiny = SearchOptions {
+        aspiration_delta: Some(1),
+        ..full_width
+    };
+    let mut stats = SearchStats::default();
+    for _ in 0..30 {
+        let board = arbitrary_board(rng);
+        if board.is_placing_center() || board.legal_moves().is_empty() {
+            continue;
+        }
+        let (posn, score) = iterative_deepening(&board, &tiny, &mut stats);
+        let (full_posn, full_score) =
+            iterative_deepening(&board, &full_width, &mut SearchStats::default());
+        if (posn.index(), score) != (full_posn.index(), full_score) {
+            panic!(
+                "a one-unit window chose {} ({}) but the full window {} ({})\n{}",
+                posn, score, full_posn, full_score, board
+            );
+        }
+    }
+    if stats.fail_highs == 0 || stats.fail_lows == 0 {
+        panic!(
+            "one-unit windows failed high {} and low {} times over the suite",
+            stats.fail_highs, stats.fail_lows
+        );
+    }
+}
+
+thread_local! {
+    /// Heuristic calls made by counting_heuristic on this thread, so tests running side by side
+    /// don't count each other's
+    static HEURISTIC_CALLS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// standard_heuristic, counting its calls
+fn counting_heuristic(board: &Board) -> i32 {
+    HEURISTIC_CALLS.set(HEURISTIC_CALLS.get() + 1);
+    standard_heuristic(board)
+}
+
+/// The first position in seeded random games with a single legal move: a deep minimax agent
+/// must play it without evaluating a single position, and MCTS without a single playout
+#[cfg(feature = "rand")]
+#[test]
+fn forced_move_plays_without_searching() {
+    let rng = &mut SeededRng::new(0);
+    let mut board = Board::random_set_up_with(rng);
+    while board.legal_moves().len() != 1 {
+        if board.is_over() {
+            board = Board::random_set_up_with(rng);
+        } else if board.legal_moves().is_empty() {
+            board = board.change_turn();
+        } else {
+            board = board.play_move(&random_agent_with(&board, rng));
+        }
+    }
+    HEURISTIC_CALLS.set(0);
+    let mut agent = MinimaxAgent::new(6, counting_heuristic);
+    let posn = agent.choose_move(&board);
+    let calls = HEURISTIC_CALLS.get();
+    if posn.index() != board.legal_moves()[0].index() || calls > 0 || agent.context.nodes > 0 {
+        panic!(
+            "with one legal move the agent played {} after {} evaluations and {} nodes\n{}",
+            posn, calls, agent.context.nodes, board
+        );
+    }
+    // So many playouts would never finish, so this only returns if the agent skips the search
+    #[cfg(feature = "sim")]
+    {
+        let options = MctsOptions {
+            iterations: usize::MAX,
+            ..MctsOptions::default()
+        };
+        let posn = mcts::MctsAgent { options }.choose_move(&board);
+        if posn.index() != board.legal_moves()[0].index() {
+            panic!("with one legal move MCTS played {}\n{}", posn, board);
+        }
+    }
+}
+
+/// Every position of a few seeded random games gets a legal quick_hint, or the pass when there's
+/// no placement. A depth-2 search takes a few milliseconds, so the one-second bound only catches a
+/// hint that searches far deeper than it should, not a slow or busy machine
+#[cfg(feature = "rand")]
+#[test]
+fn quick_hint_is_legal_and_quick() {
+    let rng = &mut SeededRng::new(0);
+    const GAMES: usize = 4;
+    const BOUND: std::time::Duration = std::time::Duration::from_secs(1);
+    for _ in 0..GAMES {
+        let mut board = Board::random_set_up_with(rng);
+        while !board.is_over() {
+            let start = std::time::Instant::now();
+            let hint = quick_hint(&board);
+            let elapsed = start.elapsed();
+            let legal = match hint {
+                Move::Place(posn) => board.is_legal(&posn),
+                Move::Pass => board.legal_moves().is_empty(),
+            };
+            if !legal || elapsed > BOUND {
+                panic!("quick_hint gave {} after {:?}\n{}", hint, elapsed, board);
+            }
+            board = match hint {
+                Move::Place(_) => board.play_move(&random_agent_with(&board, rng)),
+                Move::Pass => board.change_turn(),
+            };
+        }
+    }
+}
+
+/// A depth-12 search from the position after e3, far too deep to finish, returns a legal move
+/// well within a second when another thread cancels it 10ms in, or before it starts
+#[test]
+fn cancelled_search_returns_quickly() {
+    let board = Board::new().play_move(&Posn::try_from_alphanumeric("e3").unwrap());
+    for delay in [Some(Duration::from_millis(10)), None] {
+        let flag = Arc::new(AtomicBool::new(delay.is_none()));
+        let mut agent = MinimaxAgent::new(12, edge_corner_heuristic);
+        agent.cancel_with(flag.clone());
+
+        let start = Instant::now();
+        let posn = std::thread::scope(|scope| {
+            let search = scope.spawn(|| agent.choose_move(&board));
+            if let Some(delay) = delay {
+                std::thread::sleep(delay);
+                flag.store(true, Ordering::Relaxed);
+            }
+            search.join().expect("search thread panicked")
+        });
+        let elapsed = start.elapsed();
+        if !board.is_legal(&posn) || elapsed > Duration::from_secs(1) {
+            panic!(
+                "cancelled after {:?}, the search returned {} in {:?}",
+                delay.unwrap_or_default(),
+                posn,
+                elapsed
+            );
+        }
+    }
+}
+
+/// exploit_agent at depth 2 from the position after e3: with a model that always names a move,
+/// each opponent node expands one child, so there's one evaluation per move of ours. A model that
+/// never knows searches every reply and picks minimax_agent's move. The opening's four moves each
+/// flip one disc, so greedy_model can't tell them apart
+#[test]
+fn exploit_agent_expands_modeled_replies() {
+    let board = Board::new().play_move(&Posn::try_from_alphanumeric("e3").unwrap());
+    let ours = board.legal_moves();
+    let replies: usize = ours
+        .iter()
+        .map(|posn| board.play_move(posn).legal_moves().len())
+        .sum();
+
+    HEURISTIC_CALLS.set(0);
+    exploit_agent(&board, 2, counting_heuristic, |board| {
+        Some(greedy_fn(board))
+    });
+    let calls = HEURISTIC_CALLS.get();
+    if calls != ours.len() {
+        panic!(
+            "a certain model made {} evaluations for {} moves",
+            calls,
+            ours.len()
+        );
+    }
+
+    HEURISTIC_CALLS.set(0);
+    let unsure = exploit_agent(&board, 2, counting_heuristic, |_| None);
+    let calls = HEURISTIC_CALLS.get();
+    let minimax = minimax_agent(&board, 2, standard_heuristic);
+    if calls != replies || unsure.index() != minimax.index() {
+        panic!(
+            "a model that never knows made {} evaluations for {} replies and played {}, not {}",
+            calls, replies, unsure, minimax
+        );
+    }
+
+    if let Some(posn) = greedy_model(&Board::new()) {
+        panic!("greedy_model picked {} among tied moves", posn);
+    }
+}
+
+/// From the same seeded openings, each played from both sides against the greedy agent, minimax
+/// that models its opponent as greedy must win at least as many games as plain minimax
+#[cfg(feature = "rand")]
+#[test]
+fn modeling_a_greedy_opponent_wins_as_often() {
+    const OPENINGS: usize = 50;
+    let mut rng = SeededRng::new(0);
+    let openings: Vec<Board> = (0..OPENINGS)
+        .map(|_| Board::random_set_up_with(&mut rng))
+        .collect();
+    let mut greedy: AgentFn = greedy_fn;
+    let mut minimax = MinimaxAgent::new(3, edge_corner_heuristic);
+    let mut biased = BiasedMinimaxAgent {
+        depth: 3,
+        heuristic: edge_corner_heuristic,
+        model: greedy_model,
+    };
+
+    let (mut minimax_wins, mut biased_wins) = (0, 0);
+    for opening in &openings {
+        for color in [Color::Black, Color::White] {
+            minimax.new_game();
+            let wins = |winner: Option<Color>| usize::from(winner == Some(color));
+            minimax_wins += wins(match color {
+                Color::Black => play_from(opening.clone(), &mut minimax, &mut greedy).winner,
+                Color::White => play_from(opening.clone(), &mut greedy, &mut minimax).winner,
+            });
+            biased_wins += wins(match color {
+                Color::Black => play_from(opening.clone(), &mut biased, &mut greedy).winner,
+                Color::White => play_from(opening.clone(), &mut greedy, &mut biased).winner,
+            });
+        }
+    }
+    if biased_wins < minimax_wins {
+        panic!(
+            "in {} games against greedy, greedy-modeling minimax won {} and plain minimax {}",
+            2 * OPENINGS,
+            biased_wins,
+            minimax_wins
+        );
+    }
+}
+
+/// analyze at depth 3 through PASS_GAME: the best move heads the ranking, carries the value and
+/// starts the principal variation, and is the move a fresh MinimaxAgent plays. Where Black has to
+/// pass, the best move is the pass and nothing is ranked
+#[test]
+fn analysis_leads_with_the_best_move() {
+    const DEPTH: i32 = 3;
+    let replay = GameRecord::from_transcript(PASS_GAME)
+        .and_then(|record| record.replay())
+        .unwrap_or_else(|err| panic!("pass game didn't replay: {}", err));
+    for board in replay.boards.iter().filter(|board| !board.is_over()) {
+        let analysis = analyze(board, DEPTH);
+        if board.legal_moves().is_empty() {
+            if !matches!(analysis.best_move, Move::Pass) || !analysis.ranked_moves.is_empty() {
+                panic!(
+                    "with no moves, analysis chose {} from {} ranked\n{}",
+                    analysis.best_move,
+                    analysis.ranked_moves.len(),
+                    board
+                );
+            }
+            continue;
+        }
+        let Some(&(top, top_score)) = analysis.ranked_moves.first() else {
+            panic!("nothing ranked\n{}", board);
+        };
+        if analysis.ranked_moves.len() != board.legal_moves().len() {
+            panic!(
+                "ranked {} of {} moves\n{}",
+                analysis.ranked_moves.len(),
+                board.legal_moves().len(),
+                board
+            );
+        }
+        if top.to_string() != analysis.best_move.to_string() || top_score != analysis.value {
+            panic!(
+                "best move {} ({}) isn't the top of the ranking, {} ({})\n{}",
+                analysis.best_move, analysis.value, top, top_score, board
+            );
+        }
+        let mover_best = analysis
+            .ranked_moves
+            .iter()
+            .map(|(_, score)| match board.turn {
+                Color::White => *score,
+                Color::Black => -*score,
+            })
+            .max();
+        if mover_best != Some(analysis.value_for(board.turn)) {
+            panic!(
+                "value {} for {} isn't the best of its ranking\n{}",
+                analysis.value_for(board.turn),
+                board.turn,
+                board
+            );
+        }
+        if analysis.principal_variation.first().map(Move::to_string)
+            != Some(analysis.best_move.to_string())
+        {
+            panic!(
+                "principal variation {} doesn't start with {}\n{}",
+                analysis
+                    .principal_variation
+                    .iter()
+                    .map(Move::to_string)
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                analysis.best_move,
+                board
+            );
+        }
+        let chosen = MinimaxAgent::new(DEPTH, engine_heuristic()).choose_move(board);
+        if analysis.best_move.to_string() != chosen.to_string() {
+            panic!(
+                "analysis picked {} but the agent plays {}\n{}",
+                analysis.best_move, chosen, board
+            );
+        }
+    }
+}
+
+/// Typing all but the last of PASS_GAME's moves into the REPL, then "save", writes a transcript of
+/// exactly the moves played, with the passes the REPL made by itself in their places
+#[test]
+fn repl_saves_the_moves_played() {
+    use crate::play_interactive;
+
+    let replay = GameRecord::from_transcript(PASS_GAME)
+        .and_then(|record| record.replay())
+        .unwrap_or_else(|err| panic!("pass game didn't replay: {}", err));
+    let played = &replay.moves[..replay.moves.len() - 1];
+    let path = std::env::temp_dir().join(format!("othello-save-check-{}.txt", std::process::id()));
+    let mut script: String = played
+        .iter()
+        .filter(|mv| matches!(mv, Move::Place(_)))
+        .map(|mv| format!("{}\n", mv))
+        .collect();
+    script.push_str(&format!("save {}\nquit\n", path.display()));
+
+    let board = play_interactive(Board::new(), None, &mut script.as_bytes(), false, &mut [])
+        .unwrap_or_else(|err| panic!("{}", err));
+    let saved = std::fs::read_to_string(&path);
+    let _ = std::fs::remove_file(&path);
+    let saved = parse_transcript(&saved.unwrap_or_else(|err| panic!("{}", err)))
+        .unwrap_or_else(|err| panic!("the saved transcript didn't parse: {}", err));
+    let as_strings = |moves: &[Move]| moves.iter().map(Move::to_string).collect::<Vec<_>>();
+    if as_strings(&saved) != as_strings(played) || board != replay.boards[played.len()] {
+        panic!(
+            "after typing {:?} the REPL saved {:?}\n{}",
+            as_strings(played),
+            as_strings(&saved),
+            board
+        );
+    }
+}
+
+/// Commands read from memory: input running out ends the game where it stands, blank lines are
+/// skipped, an invalid line stops a batch game but is retried interactively, and PASS_GAME's moves
+/// alone, one per line, play it to its finish
+#[test]
+fn scripted_input_plays_and_stops() {
+    use crate::play_interactive;
+
+    let play = |script: &str, batch: bool| {
+        play_interactive(Board::new(), None, &mut script.as_bytes(), batch, &mut [])
+            .unwrap_or_else(|err| panic!("{:?} failed: {}", script, err))
+    };
+    let after = |moves: &[&str]| {
+        moves.iter().fold(Board::new(), |board, square| {
+            board.play_move(&Posn::try_from_alphanumeric(square).unwrap())
+        })
+    };
+    for (script, batch, expected) in [
+        ("e3\nf3\n", true, after(&["e3", "f3"])),
+        ("", false, Board::new()),
+        ("\n\ne3\n  \n\nf3", false, after(&["e3", "f3"])),
+        ("e3\nzz\nf3\n", true, after(&["e3"])),
+        ("e3\na1\nf3\n", true, after(&["e3"])),
+        ("e3\nzz\na1\nf3\n", false, after(&["e3", "f3"])),
+    ] {
+        let board = play(script, batch);
+        if board != expected {
+            panic!(
+                "{:?} with batch {} ended on\n{}not\n{}",
+                script, batch, board, expected
+            );
+        }
+    }
+
+    let replay = GameRecord::from_transcript(PASS_GAME)
+        .and_then(|record| record.replay())
+        .unwrap_or_else(|err| panic!("pass game didn't replay: {}", err));
+    let script: String = replay
+        .moves
+        .iter()
+        .filter(|mv| matches!(mv, Move::Place(_)))
+        .map(|mv| format!("{}\n", mv))
+        .collect();
+    let board = play(&script, true);
+    if !board.is_over() || board != *replay.final_board() {
+        panic!(
+            "the scripted pass game ended on\n{}not\n{}",
+            board,
+            replay.final_board()
+        );
+    }
+}
+
+/// A middle game position whose potential mobility was counted by hand
+#[test]
+fn potential_mobility_counted_by_hand() {
+    let mut squares = vec!['-'; 64];
+    for (name, disc) in [
+        ("c3", 'X'),
+        ("d4", 'X'),
+        ("e4", 'X'),
+        ("e5", 'X'),
+        ("f5", 'X'),
+        ("d3", 'O'),
+        ("e3", 'O'),
+        ("f4", 'O'),
+        ("d5", 'O'),
+    ] {
+        squares[Posn::try_from_alphanumeric(name).unwrap().index()] = disc;
+    }
+    let position: String = squares.into_iter().collect();
+    let board = Board::from_position_string(&format!("{} X", position)).unwrap();
+    // Black: c2 d2 e2 f2 f3 g3 c4 g4 c5 g5 c6 d6 e6 touch White's discs
+    // White: b2 c2 d2 b3 f3 b4 c4 g4 c5 g5 d6 e6 f6 g6 touch Black's
+    let counts = [
+        board.potential_mobility(Color::Black),
+        board.potential_mobility(Color::White),
+    ];
+    if counts != [13, 14] {
+        panic!(
+            "potential mobility (Black, White) was {:?}\n{}",
+            counts, board
+        );
+    }
+    let opening = Board::new();
+    if opening.potential_mobility(Color::Black) != 10
+        || opening.potential_mobility(Color::White) != 10
+    {
+        panic!("the opening should give each side a potential mobility of 10");
+    }
+}
+
+/// From the opening, e3 adds a Black disc on e3 and turns e4 from White to Black, and nothing
+/// else; the diff back the other way removes e3 and turns e4 back
+#[test]
+fn board_diff_adds_and_turns_discs() {
+    let (e3, e4) = (
+        Posn::try_from_alphanumeric("e3").unwrap(),
+        Posn::try_from_alphanumeric("e4").unwrap(),
+    );
+    let before = Board::new();
+    let after = before.play_move(&e3);
+    let show = |squares: &[(Posn, Color)]| -> Vec<String> {
+        squares
+            .iter()
+            .map(|(posn, color)| format!("{} {}", posn, color))
+            .collect()
+    };
+    let forward = before.diff(&after);
+    if show(&forward.added) != [format!("{} Black", e3)]
+        || show(&forward.flipped) != [format!("{} Black", e4)]
+        || !forward.removed.is_empty()
+    {
+        panic!("e3 from the opening diffed as {:?}", forward);
+    }
+    let backward = after.diff(&before);
+    if !backward.added.is_empty()
+        || show(&backward.flipped) != [format!("{} White", e4)]
+        || backward
+            .removed
+            .iter()
+            .map(Posn::to_string)
+            .ne(["e3".to_string()])
+    {
+        panic!("undoing e3 diffed as {:?}", backward);
+    }
+}
+
+/// Boards compare and hash by their discs and side to move: a board equals its clone, and every
+/// position of PASS_GAME, each differing from the one before by a move or a pass, goes into a
+/// HashSet and can be found there again
+#[test]
+fn boards_compare_by_discs_and_turn() {
+    let replay = GameRecord::from_transcript(PASS_GAME)
+        .and_then(|record| record.replay())
+        .unwrap_or_else(|err| panic!("pass game didn't replay: {}", err));
+    let opening = Board::new();
+    if opening != opening.clone() {
+        panic!("the opening doesn't equal its clone");
+    }
+    if opening == opening.play_move(&opening.legal_moves()[0]) {
+        panic!("the opening equals the board after a move");
+    }
+    if opening == opening.change_turn() {
+        panic!("the opening equals itself with White to move");
+    }
+    let positions: HashSet<Board> = replay.boards.iter().cloned().collect();
+    if positions.len() != replay.boards.len() {
+        panic!(
+            "{} positions went into the set as {}",
+            replay.boards.len(),
+            positions.len()
+        );
+    }
+    if let Some(board) = replay
+        .boards
+        .iter()
+        .find(|board| !positions.contains(board))
+    {
+        panic!("the set lost\n{}", board);
+    }
+}
+
+/// The squares' names, sorted, to compare sets of squares by
+fn square_names(posns: impl IntoIterator<Item = Posn>) -> Vec<String> {
+    let mut names: Vec<String> = posns.into_iter().map(|posn| posn.to_string()).collect();
+    names.sort();
+    names
+}
+
+fn square(name: &str) -> Posn {
+    Posn::try_from_alphanumeric(name).expect("test squares are on the board")
+}
+
+/// While the center fills, the only legal moves are the empty center squares and none of them
+/// flips anything, whichever order they're taken in
+#[test]
+fn reversi_center_moves_place_without_flipping() {
+    let centers = ["d4", "e4", "d5", "e5"];
+    for order in [[0, 1, 2, 3], [3, 2, 1, 0], [1, 3, 0, 2]] {
+        let mut board = Board::new_reversi();
+        for (placed, &index) in order.iter().enumerate() {
+            let empty = square_names(
+                centers
+                    .iter()
+                    .map(|name| square(name))
+                    .filter(|posn| !board.piece_at(posn).is_occupied()),
+            );
+            let legal = board.legal_moves();
+            if !board.is_placing_center() || square_names(legal.iter().copied()) != empty {
+                panic!(
+                    "with {} centers placed the legal moves are {}, not {:?}\n{}",
+                    placed,
+                    format_moves(&board),
+                    empty,
+                    board
+                );
+            }
+            if let Some(posn) = legal.iter().find(|posn| {
+                board.flipped_count(posn) != 0 || !board.potential_flipped_pieces(posn).is_empty()
+            }) {
+                panic!("{} flips discs while the center fills\n{}", posn, board);
+            }
+            let next = board.play_move(&square(centers[index]));
+            if next.count_color_pieces(board.turn) != board.count_color_pieces(board.turn) + 1
+                || next.count_color_pieces(board.turn.opponent())
+                    != board.count_color_pieces(board.turn.opponent())
+            {
+                panic!(
+                    "placing {} changed other discs\n{}\n{}",
+                    centers[index], board, next
+                );
+            }
+            board = next;
+        }
+        if board.is_placing_center() {
+            panic!("the center is full but still being placed\n{}", board);
+        }
+    }
+}
+
+/// Filling the center the way Othello starts gives the Othello start, which then plays by the
+/// usual rules
+#[test]
+fn reversi_plays_normally_once_the_center_fills() {
+    let mut board = Board::new_reversi();
+    for name in ["d4", "e4", "e5", "d5"] {
+        board = board.play_move(&square(name));
+    }
+    let othello = Board::new();
+    if board != othello {
+        panic!("filling the center gave\n{}\nnot\n{}", board, othello);
+    }
+    if square_names(board.legal_moves()) != ["c5", "d6", "e3", "f4"] {
+        panic!(
+            "after the center fills Black's moves are {}",
+            format_moves(&board)
+        );
+    }
+    let e3 = square("e3");
+    if board.play_move(&e3) != othello.play_move(&e3) || board.flipped_count(&e3) != 1 {
+        panic!("e3 doesn't flip e4 as it would in Othello\n{}", board);
+    }
+}
+
+/// When Black places three of the four centers, White's one disc can be bracketed from all three
+/// of Black's, and White can bracket each of Black's in turn
+#[test]
+fn reversi_center_with_three_discs_of_one_color() {
+    let mut board = Board::new_reversi();
+    for name in ["d4", "e4", "d5"] {
+        board = board.play_move(&square(name)).with_turn(Color::Black);
+    }
+    board = board.with_turn(Color::White).play_move(&square("e5"));
+    if board.is_placing_center() || board.turn != Color::Black {
+        panic!("the center is full with Black to move, but\n{}", board);
+    }
+    let legal = board.legal_moves();
+    if square_names(legal.iter().copied()) != ["e6", "f5", "f6"] {
+        panic!("Black's moves are {}\n{}", format_moves(&board), board);
+    }
+    if let Some(posn) = legal
+        .iter()
+        .find(|posn| square_names(board.potential_flipped_pieces(posn)) != ["e5"])
+    {
+        panic!("{} doesn't flip just e5\n{}", posn, board);
+    }
+    let white = board.with_turn(Color::White).legal_moves();
+    if square_names(white.iter().copied()) != ["c3", "c5", "e3"] {
+        panic!(
+            "White's moves are {}\n{}",
+            format_moves(&board.with_turn(Color::White)),
+            board
+        );
+    }
+}
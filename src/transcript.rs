@@ -0,0 +1,464 @@
+//! Game transcripts: a string of coordinates like "f5d6c3d3c4", optionally separated by whitespace
+//! or commas. A turn with no legal placement may be written "pass", "--" or "PA", or left out
+//! entirely as WThor does, in which case replay puts it back. Separated moves may take any form
+//! parse_move reads, square indices included, but moves run together must be squares or passes.
+//! A game White opened starts with the word "white", as in "white d4 e4 e5 d5 c5".
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{OthelloError, Result};
+use crate::{parse_move, Board, Color, Move, StartRule, CENTER_POSNS};
+
+/// Player name for a record that doesn't say who played
+pub const UNKNOWN_PLAYER: &str = "?";
+
+pub fn parse_transcript(text: &str) -> Result<Vec<Move>> {
+    let mut moves = vec![];
+    for token in text
+        .split(|ch: char| ch.is_whitespace() || ch == ',')
+        .filter(|token| !token.is_empty())
+    {
+        // A token of digits is one square index; other tokens that aren't a single move are
+        // moves run together
+        match parse_move(token) {
+            Ok(mv) => moves.push(mv),
+            Err(err) if token.chars().all(|ch| ch.is_ascii_digit()) => {
+                return Err(invalid_move(token, moves.len(), err))
+            }
+            Err(_) => parse_run_together(token, &mut moves)?,
+        }
+    }
+    Ok(moves)
+}
+
+/// The color a transcript opens with to say who moved first, and the moves after it. Without one,
+/// Black moved first
+pub fn split_first_player(text: &str) -> (Color, &str) {
+    let text = text.trim_start();
+    let first = text
+        .split(|ch: char| ch.is_whitespace() || ch == ',')
+        .next()
+        .unwrap_or_default();
+    match first.parse() {
+        Ok(color) => (color, &text[first.len()..]),
+        Err(_) => (Color::Black, text),
+    }
+}
+
+fn invalid_move(token: &str, plies: usize, err: OthelloError) -> OthelloError {
+    OthelloError::Parse(format!(
+        "Invalid move \"{}\" at ply {}: {}",
+        token,
+        plies + 1,
+        err
+    ))
+}
+
+/// Split moves written without separators, like "f5d6--c3" or "f5passd6", into moves
+fn parse_run_together(token: &str, moves: &mut Vec<Move>) -> Result<()> {
+    let compact: Vec<char> = token.chars().map(|ch| ch.to_ascii_lowercase()).collect();
+    let mut i = 0;
+    while i < compact.len() {
+        let len = if compact[i..].starts_with(&['p', 'a', 's', 's']) {
+            4
+        } else {
+            2
+        };
+        let chunk: String = compact[i..(i + len).min(compact.len())].iter().collect();
+        if chunk.chars().all(|ch| ch.is_ascii_digit()) {
+            let err = OthelloError::Parse("square indices must be separated".to_string());
+            return Err(invalid_move(&chunk, moves.len(), err));
+        }
+        let mv = parse_move(&chunk).map_err(|err| invalid_move(&chunk, moves.len(), err))?;
+        moves.push(mv);
+        i += len;
+    }
+    Ok(())
+}
+
+/// A replayed game: boards[i] is the position before moves[i], and the last board is the final
+/// position. Passes the transcript left out are filled in, so moves and boards always line up
+pub struct Replay {
+    pub moves: Vec<Move>,
+    pub boards: Vec<Board>,
+}
+
+impl Replay {
+    pub fn final_board(&self) -> &Board {
+        self.boards.last().unwrap()
+    }
+
+    /// White's disc count minus Black's after each ply, one entry per move
+    pub fn score_history(&self) -> Vec<i32> {
+        self.boards[1..].iter().map(|board| board.score()).collect()
+    }
+}
+
+/// A transcript that opens on a center square can only be a Reversi game, since Othello starts
+/// with the center already filled
+fn start_rule_for(moves: &[Move]) -> StartRule {
+    match moves.first() {
+        Some(Move::Place(posn))
+            if CENTER_POSNS
+                .iter()
+                .any(|center| center.to_tuple() == posn.to_tuple()) =>
+        {
+            StartRule::Reversi
+        }
+        _ => StartRule::Othello,
+    }
+}
+
+pub fn replay(moves: &[Move], first_player: Color) -> Result<Replay> {
+    replay_with(moves, first_player, |_, _| {})
+}
+
+/// Replay a game first_player opened, calling on_ply with each move and the position it led to
+/// as it's applied
+pub fn replay_with(
+    moves: &[Move],
+    first_player: Color,
+    mut on_ply: impl FnMut(&Move, &Board),
+) -> Result<Replay> {
+    let mut board = Board::with_rules(start_rule_for(moves), first_player);
+    let mut replay = Replay {
+        moves: vec![],
+        boards: vec![board.clone()],
+    };
+
+    for mv in moves {
+        match mv {
+            Move::Pass => {
+                if !board.legal_moves().is_empty() {
+                    return Err(OthelloError::IllegalMove(format!(
+                        "Illegal pass at ply {}: {} has legal moves\n{}",
+                        replay.moves.len() + 1,
+                        board.turn,
+                        board
+                    )));
+                }
+                board = board.change_turn();
+            }
+            Move::Place(posn) => {
+                if board.legal_moves().is_empty() && !board.is_over() {
+                    replay.moves.push(Move::Pass);
+                    board = board.change_turn();
+                    replay.boards.push(board.clone());
+                    on_ply(&Move::Pass, &board);
+                }
+                if !board.is_legal(posn) {
+                    return Err(OthelloError::IllegalMove(format!(
+                        "Illegal move {} at ply {} for {}\n{}",
+                        posn,
+                        replay.moves.len() + 1,
+                        board.turn,
+                        board
+                    )));
+                }
+                board = board.play_move(posn);
+            }
+        }
+        replay.moves.push(*mv);
+        replay.boards.push(board.clone());
+        on_ply(mv, &board);
+    }
+
+    Ok(replay)
+}
+
+/// Read a transcript from a file if the argument names one, otherwise treat it as the transcript
+pub fn load_transcript(arg: &str) -> Result<GameRecord> {
+    match std::fs::read_to_string(arg) {
+        Ok(text) => GameRecord::from_transcript(&text),
+        Err(_) => GameRecord::from_transcript(arg),
+    }
+}
+
+/// How a transcript writes passes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TranscriptStyle {
+    /// Leave passes out, as WThor does; replay infers them
+    Dense,
+    /// Write every pass as "--"
+    Explicit,
+}
+
+/// How a recorded game came to an end
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Termination {
+    /// Played until neither side could move. The winner, or None for a draw
+    Finished { winner: Option<Color> },
+    /// Stopped once early adjudication decided the winner (see play_until_decided)
+    Adjudicated { winner: Option<Color> },
+    /// Stopped with the result still open, like a game saved part way through
+    Unfinished,
+}
+
+impl Termination {
+    /// Finished with the final board's result if the game is over there, otherwise Unfinished
+    pub fn of(last: &Board) -> Self {
+        if last.is_over() {
+            Termination::Finished {
+                winner: last.winner(),
+            }
+        } else {
+            Termination::Unfinished
+        }
+    }
+}
+
+/// A recorded game with who played it and how it ended. Its moves always include every pass,
+/// whichever style the transcript was written in. As JSON the moves are one string in the form
+/// of to_notation:
+///
+/// ```json
+/// {"black_agent": "human", "white_agent": "minimax", "first_player": "Black",
+///  "result": {"Finished": {"winner": "Black"}}, "moves": "f5 d6 c3 ... -- a5", "date": "2024-06-01"}
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameRecord {
+    pub black_agent: String,
+    pub white_agent: String,
+    /// Who made the first move. Records written before it was kept say nothing, and were Black's
+    #[serde(default = "black")]
+    pub first_player: Color,
+    pub result: Termination,
+    #[serde(with = "notation")]
+    pub moves: Vec<Move>,
+    pub date: Option<String>,
+}
+
+fn black() -> Color {
+    Color::Black
+}
+
+/// GameRecord's moves as a single to_notation string
+mod notation {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::parse_transcript;
+    use crate::Move;
+
+    pub fn serialize<S: Serializer>(moves: &[Move], serializer: S) -> Result<S::Ok, S::Error> {
+        let tokens: Vec<String> = moves
+            .iter()
+            .map(|mv| match mv {
+                Move::Place(posn) => posn.to_string(),
+                Move::Pass => "--".to_string(),
+            })
+            .collect();
+        serializer.serialize_str(&tokens.join(" "))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Move>, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        parse_transcript(&text).map_err(serde::de::Error::custom)
+    }
+}
+
+impl GameRecord {
+    /// A record of moves by unknown players, Black first, its result read from where the moves
+    /// lead. Moves that aren't a legal game count as Unfinished
+    pub fn from_moves(moves: Vec<Move>) -> Self {
+        Self::with_first_player(moves, Color::Black)
+    }
+
+    /// Like from_moves, for a game first_player opened
+    pub fn with_first_player(moves: Vec<Move>, first_player: Color) -> Self {
+        let result = replay(&moves, first_player).map_or(Termination::Unfinished, |replay| {
+            Termination::of(replay.final_board())
+        });
+        Self {
+            black_agent: UNKNOWN_PLAYER.to_string(),
+            white_agent: UNKNOWN_PLAYER.to_string(),
+            first_player,
+            result,
+            moves,
+            date: None,
+        }
+    }
+
+    /// Parse a transcript in either style, checking that it's a legal game
+    pub fn from_transcript(text: &str) -> Result<Self> {
+        let (first_player, text) = split_first_player(text);
+        let moves = replay(&parse_transcript(text)?, first_player)?.moves;
+        Ok(Self::with_first_player(moves, first_player))
+    }
+
+    /// Read a record written by to_json, checking that its moves are a legal game
+    pub fn from_json(text: &str) -> Result<Self> {
+        let record: Self = serde_json::from_str(text)?;
+        record.replay()?;
+        Ok(record)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// The moves in style, after "white " when White moved first
+    pub fn to_transcript(&self, style: TranscriptStyle) -> String {
+        let moves: String = self
+            .moves
+            .iter()
+            .filter_map(|mv| match (mv, style) {
+                (Move::Place(posn), _) => Some(posn.to_string()),
+                (Move::Pass, TranscriptStyle::Explicit) => Some("--".to_string()),
+                (Move::Pass, TranscriptStyle::Dense) => None,
+            })
+            .collect();
+        match self.first_player {
+            Color::Black => moves,
+            Color::White => format!("white {}", moves),
+        }
+    }
+
+    /// Every move in standard coordinates, separated by spaces, with "--" for each pass: easier
+    /// to read and to hand to other analysis tools than a transcript
+    pub fn to_notation(&self) -> String {
+        let tokens: Vec<String> = self
+            .moves
+            .iter()
+            .map(|mv| match mv {
+                Move::Place(posn) => posn.to_string(),
+                Move::Pass => "--".to_string(),
+            })
+            .collect();
+        tokens.join(" ")
+    }
+
+    pub fn replay(&self) -> Result<Replay> {
+        replay(&self.moves, self.first_player)
+    }
+}
+
+/// Read a file holding one transcript per line, skipping blank lines
+pub fn load_records(path: &str) -> Result<Vec<GameRecord>> {
+    std::fs::read_to_string(path)?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(GameRecord::from_transcript)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::PASS_GAME;
+
+    /// A game's score history has an entry per ply, passes included, ending on the final score. After
+    /// e3 from the opening White has 1 disc to Black's 4, so a short game's first entry is -3
+    #[test]
+    fn score_history_has_an_entry_per_ply() {
+        for transcript in ["e3 f3 g3", PASS_GAME] {
+            let replay = GameRecord::from_transcript(transcript)
+                .and_then(|record| record.replay())
+                .unwrap_or_else(|err| panic!("{} didn't replay: {}", transcript, err));
+            let history = replay.score_history();
+            if history.len() != replay.moves.len()
+                || history.last() != Some(&replay.final_board().score())
+                || history[0] != -3
+            {
+                panic!(
+                    "{} over {} plies ending {} has score history {:?}",
+                    transcript,
+                    replay.moves.len(),
+                    replay.final_board().score(),
+                    history
+                );
+            }
+        }
+    }
+
+    /// A game with passes at plies 57 and 59 writes each as "--" in its place
+    #[test]
+    fn passes_write_as_dashes() {
+        let record = GameRecord::from_transcript(PASS_GAME)
+            .unwrap_or_else(|err| panic!("pass game didn't replay: {}", err));
+        let notation = record.to_notation();
+        let tokens: Vec<&str> = notation.split(' ').collect();
+        if tokens.len() != 60 || tokens[56] != "--" || tokens[58] != "--" || tokens[57] != "a2" {
+            panic!("pass game's notation is {}", notation);
+        }
+    }
+
+    /// PASS_GAME with its players, result and date filled in reads back from JSON unchanged, passes
+    /// included, and JSON whose moves aren't a legal game is refused
+    #[test]
+    fn game_record_round_trips_through_json() {
+        let mut record = GameRecord::from_transcript(PASS_GAME)
+            .unwrap_or_else(|err| panic!("pass game didn't replay: {}", err));
+        if record.result
+            != (Termination::Finished {
+                winner: Some(Color::Black),
+            })
+        {
+            panic!("pass game's result was read as {:?}", record.result);
+        }
+        record.black_agent = "minimax depth 4".to_string();
+        record.white_agent = "human".to_string();
+        record.date = Some("2024-06-01".to_string());
+        let json = record.to_json().unwrap_or_else(|err| panic!("{}", err));
+        let read = GameRecord::from_json(&json).unwrap_or_else(|err| panic!("{}\n{}", err, json));
+        if read.black_agent != record.black_agent
+            || read.white_agent != record.white_agent
+            || read.result != record.result
+            || read.date != record.date
+            || read.to_notation() != record.to_notation()
+            || !read.moves.iter().any(|mv| matches!(mv, Move::Pass))
+        {
+            panic!("record changed on its way through JSON:\n{}", json);
+        }
+        let illegal = json.replacen("\"moves\": \"", "\"moves\": \"a1 ", 1);
+        if GameRecord::from_json(&illegal).is_ok() {
+            panic!("an illegal game read back from JSON:\n{}", illegal);
+        }
+    }
+
+    /// A game White opened writes its first player into the transcript and the JSON, reads back
+    /// as White's and replays to the same position, and isn't a legal game for Black to open
+    #[test]
+    fn white_first_games_replay() {
+        let mut board = Board::with_rules(StartRule::Othello, Color::White);
+        let mut moves = vec![];
+        for _ in 0..12 {
+            let posn = board.legal_moves()[0];
+            moves.push(Move::Place(posn));
+            board = board.play_move(&posn);
+        }
+        let record = GameRecord::with_first_player(moves, Color::White);
+        let transcript = record.to_transcript(TranscriptStyle::Explicit);
+        let read = GameRecord::from_transcript(&transcript)
+            .unwrap_or_else(|err| panic!("{} didn't replay: {}", transcript, err));
+        let replay = read
+            .replay()
+            .unwrap_or_else(|err| panic!("{} didn't replay: {}", transcript, err));
+        if read.first_player != Color::White
+            || read.to_notation() != record.to_notation()
+            || *replay.final_board() != board
+        {
+            panic!("{} read back as {:?}", transcript, read);
+        }
+        let moves_only = transcript.trim_start_matches("white ");
+        if GameRecord::from_transcript(moves_only).is_ok() {
+            panic!("{} replayed with Black to move first", moves_only);
+        }
+
+        let json = record.to_json().unwrap_or_else(|err| panic!("{}", err));
+        let read = GameRecord::from_json(&json).unwrap_or_else(|err| panic!("{}\n{}", err, json));
+        if read.first_player != Color::White || read.to_notation() != record.to_notation() {
+            panic!("White's game changed on its way through JSON:\n{}", json);
+        }
+        let mut unrecorded: serde_json::Value =
+            serde_json::from_str(&json).unwrap_or_else(|err| panic!("{}", err));
+        if let Some(fields) = unrecorded.as_object_mut() {
+            fields.remove("first_player");
+        }
+        if GameRecord::from_json(&unrecorded.to_string()).is_ok() {
+            panic!(
+                "a record without its first player replayed as White's:\n{}",
+                unrecorded
+            );
+        }
+    }
+}
@@ -0,0 +1,188 @@
+//! Exhaustive traversal of the game tree to a fixed depth, and counts built on it. A pass is a
+//! ply like any other, so a position where the side to move has no placement has exactly one
+//! child, and a finished game has none.
+
+use std::collections::HashSet;
+
+use crate::error::{OthelloError, Result};
+use crate::Board;
+
+/// The positions reachable in one ply: one per legal placement, or the pass if there are none
+fn children(board: &Board) -> Vec<Board> {
+    if board.is_over() {
+        return vec![];
+    }
+    let legal_moves = board.legal_moves();
+    if legal_moves.is_empty() {
+        return vec![board.change_turn()];
+    }
+    legal_moves
+        .iter()
+        .map(|posn| board.play_move(posn))
+        .collect()
+}
+
+/// Call visit on every position within depth plies of board, along with its ply, depth first.
+/// Positions reached by different move orders are visited once per path
+pub fn walk(board: &Board, depth: usize, visit: &mut impl FnMut(&Board, usize)) {
+    fn walk_from(board: &Board, ply: usize, depth: usize, visit: &mut impl FnMut(&Board, usize)) {
+        visit(board, ply);
+        if ply < depth {
+            for child in children(board) {
+                walk_from(&child, ply + 1, depth, visit);
+            }
+        }
+    }
+    walk_from(board, 0, depth, visit);
+}
+
+/// Paths of exactly depth plies from board (perft)
+pub fn count_positions(board: &Board, depth: usize) -> u64 {
+    let mut count = 0;
+    walk(board, depth, &mut |_, ply| {
+        if ply == depth {
+            count += 1;
+        }
+    });
+    count
+}
+
+/// Distinct positions exactly depth plies from board, optionally counting positions that are
+/// symmetric to each other once
+pub fn distinct_positions(board: &Board, depth: usize, symmetry_aware: bool) -> usize {
+    let mut seen: HashSet<Board> = HashSet::new();
+    walk(board, depth, &mut |position, ply| {
+        if ply == depth {
+            seen.insert(if symmetry_aware {
+                // The symmetric image with the smallest encoding stands for all eight
+                (0..8)
+                    .map(|symmetry| position.transformed(symmetry))
+                    .min_by_key(Board::packed)
+                    .unwrap()
+            } else {
+                position.clone()
+            });
+        }
+    });
+    seen.len()
+}
+
+/// The position within depth plies of board with the most legal moves, and how many it has
+pub fn max_branching(board: &Board, depth: usize) -> (Board, usize) {
+    let mut best = (board.clone(), board.legal_moves().len());
+    walk(board, depth, &mut |position, _| {
+        let branching = position.legal_moves().len();
+        if branching > best.1 {
+            best = (position.clone(), branching);
+        }
+    });
+    best
+}
+
+/// perft <depth>: path and position counts from the opening for each depth up to depth
+pub fn run(args: &[String]) -> Result<()> {
+    let Some(depth) = args.first() else {
+        return Err(OthelloError::Usage("perft <depth>".to_string()));
+    };
+    let depth: usize = depth.parse()?;
+    let start = Board::new();
+
+    println!(
+        "{:>5} {:>12} {:>10} {:>10}",
+        "depth", "paths", "distinct", "symmetric"
+    );
+    for d in 1..=depth {
+        println!(
+            "{:>5} {:>12} {:>10} {:>10}",
+            d,
+            count_positions(&start, d),
+            distinct_positions(&start, d, false),
+            distinct_positions(&start, d, true)
+        );
+    }
+    let (board, branching) = max_branching(&start, depth);
+    println!("Most legal moves within {} plies: {}", depth, branching);
+    println!("{}", board);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Posn, POSNS};
+
+    /// count_positions from the opening matches the published perft counts to depth 6.
+    /// distinct_positions to depth 4 matches a brute-force count that lists every path's position and
+    /// compares each pair directly, square by square under each of the eight symmetries
+    #[test]
+    fn position_counts_match_perft() {
+        const PERFT: [u64; 6] = [4, 12, 56, 244, 1396, 8200];
+        let start = Board::new();
+        for (depth, expected) in (1..).zip(PERFT) {
+            let count = count_positions(&start, depth);
+            if count != expected {
+                panic!("{} paths of {} plies, not {}", count, depth, expected);
+            }
+        }
+
+        fn positions_at(board: &Board, depth: usize, out: &mut Vec<Board>) {
+            if depth == 0 {
+                out.push(board.clone());
+                return;
+            }
+            for posn in board.legal_moves() {
+                positions_at(&board.play_move(&posn), depth - 1, out);
+            }
+        }
+        type Symmetry = fn(usize, usize) -> (usize, usize);
+        let symmetries: [Symmetry; 8] = [
+            |row, col| (row, col),
+            |row, col| (col, 7 - row),
+            |row, col| (7 - row, 7 - col),
+            |row, col| (7 - col, row),
+            |row, col| (row, 7 - col),
+            |row, col| (7 - row, col),
+            |row, col| (col, row),
+            |row, col| (7 - col, 7 - row),
+        ];
+        let same_under = |a: &Board, b: &Board, symmetry: Symmetry| {
+            a.turn == b.turn
+                && POSNS.iter().all(|posn| {
+                    let (row, col) = symmetry(posn.row, posn.col);
+                    a.piece_at(posn) == b.piece_at(&Posn { row, col })
+                })
+        };
+        for depth in 1..=4 {
+            let mut paths = vec![];
+            positions_at(&start, depth, &mut paths);
+            for symmetry_aware in [false, true] {
+                let matches: &[Symmetry] = if symmetry_aware {
+                    &symmetries
+                } else {
+                    &symmetries[..1]
+                };
+                let mut distinct: Vec<&Board> = vec![];
+                for board in &paths {
+                    if !distinct.iter().any(|seen| {
+                        matches
+                            .iter()
+                            .any(|symmetry| same_under(seen, board, *symmetry))
+                    }) {
+                        distinct.push(board);
+                    }
+                }
+                let counted = distinct_positions(&start, depth, symmetry_aware);
+                if counted != distinct.len() {
+                    panic!(
+                        "{} distinct positions after {} plies (symmetry aware {}), but brute force \
+                         finds {}",
+                        counted,
+                        depth,
+                        symmetry_aware,
+                        distinct.len()
+                    );
+                }
+            }
+        }
+    }
+}
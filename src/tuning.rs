@@ -0,0 +1,565 @@
+//! Tools for tuning heuristics: score a corpus of positions with several heuristics at once and
+//! see how closely they agree, hill-climb a weighted heuristic's weights through self-play, or
+//! evolve a positional table against a fixed opponent.
+
+use statrs::distribution::{Beta, ContinuousCDF};
+use statrs::statistics::{Data, OrderStatistics, RankTieBreaker, Statistics};
+
+use crate::config;
+use crate::error::{OthelloError, Result};
+use crate::rng::{MoveRng, SeededRng};
+use crate::transcript::load_records;
+use crate::weights::{TableAgent, WeightedAgent, WeightedHeuristic, WeightedTableHeuristic, TERMS};
+use crate::{
+    alphabeta_root, edge_corner_heuristic, play_from, random_agent_with, AgentFn, Board, Color,
+    HeuristicFn, Posn, HEURISTICS, SCORE_LOSS, SCORE_WIN,
+};
+
+/// Each tuning match plays this many openings, once with each color
+const MATCH_OPENINGS: usize = 4;
+
+/// Random plies from the start that make each opening
+const OPENING_PLIES: usize = 4;
+
+/// Search depth for both sides in tuning matches
+const TUNING_DEPTH: i32 = 2;
+
+/// Largest change a trial makes to a weight, relative to the weight, or to 1 for weights smaller
+/// than that
+const TUNING_STEP: f64 = 0.5;
+
+/// Largest change one generation makes to a positional table's weight
+const TABLE_STEP: i32 = 8;
+
+/// Scores of every position under every heuristic: scores[h][p] is heuristic h on position p
+#[derive(Debug, Clone)]
+pub struct EvalMatrix {
+    pub names: Vec<String>,
+    pub scores: Vec<Vec<i32>>,
+}
+
+pub fn evaluate_corpus(positions: &[Board], heuristics: &[(&str, HeuristicFn)]) -> EvalMatrix {
+    EvalMatrix {
+        names: heuristics
+            .iter()
+            .map(|(name, _)| name.to_string())
+            .collect(),
+        scores: heuristics
+            .iter()
+            .map(|(_, heuristic)| positions.iter().map(heuristic).collect())
+            .collect(),
+    }
+}
+
+/// Ranks of the scores, with ties sharing their average rank
+fn ranks(scores: &[i32]) -> Vec<f64> {
+    let mut data = Data::new(
+        scores
+            .iter()
+            .map(|score| *score as f64)
+            .collect::<Vec<f64>>(),
+    );
+    data.ranks(RankTieBreaker::Average)
+}
+
+impl EvalMatrix {
+    /// Spearman's rank correlation between two heuristics: 1 when they order the positions the
+    /// same way, -1 when exactly opposite. NaN if either scores every position the same
+    pub fn spearman(&self, a: usize, b: usize) -> f64 {
+        let (ranks_a, ranks_b) = (ranks(&self.scores[a]), ranks(&self.scores[b]));
+        let covariance = ranks_a.iter().covariance(ranks_b.iter());
+        covariance / (ranks_a.iter().std_dev() * ranks_b.iter().std_dev())
+    }
+
+    /// Indices of the count positions whose rank differs most between the two heuristics
+    pub fn most_disagreeing(&self, a: usize, b: usize, count: usize) -> Vec<usize> {
+        let (ranks_a, ranks_b) = (ranks(&self.scores[a]), ranks(&self.scores[b]));
+        let mut indices: Vec<usize> = (0..ranks_a.len()).collect();
+        indices.sort_by(|i, j| {
+            let gap = |k: &usize| (ranks_a[*k] - ranks_b[*k]).abs();
+            gap(j).total_cmp(&gap(i))
+        });
+        indices.truncate(count);
+        indices
+    }
+}
+
+/// compare-heuristics <transcript file> [ply] [heuristic] [heuristic]: sample each game at ply,
+/// print the rank correlation of every pair of heuristics, then the positions where the two named
+/// ones (the first two by default) disagree most
+pub fn compare_command(args: &[String]) -> Result<()> {
+    let usage = || {
+        OthelloError::Usage(
+            "compare-heuristics <transcript file> [ply] [heuristic] [heuristic]".to_string(),
+        )
+    };
+    let path = args.first().ok_or_else(usage)?;
+    let ply: usize = args.get(1).map(|p| p.parse()).transpose()?.unwrap_or(20);
+    let index_of = |name: Option<&String>, default: usize| match name {
+        Some(name) => HEURISTICS
+            .iter()
+            .position(|(known, _)| known == name)
+            .ok_or_else(|| OthelloError::Parse(format!("Unknown heuristic \"{}\"", name))),
+        None => Ok(default),
+    };
+    let (a, b) = (index_of(args.get(2), 0)?, index_of(args.get(3), 1)?);
+
+    let mut positions = vec![];
+    for record in load_records(path)? {
+        if let Some(board) = record.replay()?.boards.get(ply) {
+            positions.push(board.clone());
+        }
+    }
+    if positions.len() < 2 {
+        return Err(OthelloError::Usage(format!(
+            "Need at least two games that reach ply {}",
+            ply
+        )));
+    }
+
+    let matrix = evaluate_corpus(&positions, &HEURISTICS);
+    println!(
+        "Rank correlation over {} positions at ply {}:",
+        positions.len(),
+        ply
+    );
+    print!("{:>12}", "");
+    for name in &matrix.names {
+        print!(" {:>12}", name);
+    }
+    println!();
+    for (i, name) in matrix.names.iter().enumerate() {
+        print!("{:>12}", name);
+        for j in 0..matrix.names.len() {
+            print!(" {:>12.3}", matrix.spearman(i, j));
+        }
+        println!();
+    }
+
+    println!(
+        "Positions where {} and {} disagree most:",
+        matrix.names[a], matrix.names[b]
+    );
+    for index in matrix.most_disagreeing(a, b, 3) {
+        println!(
+            "{} {}, {} {}",
+            matrix.names[a], matrix.scores[a][index], matrix.names[b], matrix.scores[b][index]
+        );
+        println!("{}", positions[index]);
+    }
+    Ok(())
+}
+
+/// Positions OPENING_PLIES random moves into the game
+fn random_openings(count: usize, rng: &mut impl MoveRng) -> Vec<Board> {
+    (0..count)
+        .map(|_| {
+            let mut board = Board::new();
+            for _ in 0..OPENING_PLIES {
+                if board.legal_moves().is_empty() {
+                    break;
+                }
+                board = board.play_move(&random_agent_with(&board, rng));
+            }
+            board
+        })
+        .collect()
+}
+
+/// Points candidate scores against base, playing each opening once as each color: 1 for a win
+/// and half for a draw
+pub fn match_score(
+    candidate: &WeightedHeuristic,
+    base: &WeightedHeuristic,
+    openings: &[Board],
+) -> f64 {
+    let mut candidate_agent = WeightedAgent {
+        heuristic: *candidate,
+        depth: TUNING_DEPTH,
+    };
+    let mut base_agent = WeightedAgent {
+        heuristic: *base,
+        depth: TUNING_DEPTH,
+    };
+    let mut points = 0.0;
+    for opening in openings {
+        for candidate_color in [Color::Black, Color::White] {
+            let last = match candidate_color {
+                Color::Black => play_from(opening.clone(), &mut candidate_agent, &mut base_agent),
+                Color::White => play_from(opening.clone(), &mut base_agent, &mut candidate_agent),
+            };
+            points += last.points(candidate_color);
+        }
+    }
+    points
+}
+
+/// Hill climbing on the weights: each trial nudges one random weight and keeps the change if the
+/// nudged weights win a short match against the current ones
+pub fn tune_weights(
+    base: WeightedHeuristic,
+    trials: usize,
+    rng: &mut impl MoveRng,
+) -> WeightedHeuristic {
+    let mut best = base;
+    for _ in 0..trials {
+        let mut candidate = best;
+        let term = rng.index(TERMS.len());
+        let scale = best.weights[term].abs().max(1.0);
+        candidate.weights[term] += (rng.unit() * 2.0 - 1.0) * TUNING_STEP * scale;
+        let openings = random_openings(MATCH_OPENINGS, rng);
+        // More than half the points means more wins than losses
+        if match_score(&candidate, &best, &openings) > MATCH_OPENINGS as f64 {
+            best = candidate;
+        }
+    }
+    best
+}
+
+/// How tune_table changes the table from one generation to the next
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableStrategy {
+    /// (1+1) evolution strategy: nudge every weight at random, keeping the child if it does at
+    /// least as well as its parent
+    Evolution,
+    /// Coordinate-wise hill climbing: each generation tries the next weight a step up and a step
+    /// down, keeping whichever does better than the current table
+    HillClimb,
+}
+
+#[derive(Debug, Clone)]
+pub struct TableTuning {
+    pub strategy: TableStrategy,
+    pub generations: usize,
+    /// Games each table plays per generation, in pairs from the same opening with colors swapped
+    pub games_per_eval: usize,
+    pub seed: u64,
+    /// The best table so far is written here after every generation, so a long run can be
+    /// stopped and picked up again from the file
+    pub out: String,
+}
+
+/// The best table tune_table found, with its last evaluation against the reference
+#[derive(Debug, Clone, Copy)]
+pub struct TunedTable {
+    pub table: WeightedTableHeuristic,
+    /// 1 for each win and half for each draw
+    pub points: f64,
+    pub games: usize,
+}
+
+impl TunedTable {
+    /// The 90% credible interval for the table's score rate against the reference, from a
+    /// uniform prior with draws counted as half a win
+    pub fn credible_interval(&self) -> (f64, f64) {
+        let posterior = Beta::new(self.points + 1.0, self.games as f64 - self.points + 1.0)
+            .expect("the posterior's parameters are positive");
+        (posterior.inverse_cdf(0.05), posterior.inverse_cdf(0.95))
+    }
+}
+
+/// The fixed opponent for table tuning: edge_corner_heuristic searched to TUNING_DEPTH
+fn reference_agent(board: &Board) -> Posn {
+    alphabeta_root(
+        board,
+        TUNING_DEPTH,
+        SCORE_LOSS,
+        SCORE_WIN,
+        edge_corner_heuristic,
+        0,
+    )
+    .0
+}
+
+/// Points table scores against reference_agent, playing each opening once as each color
+fn table_score(table: &WeightedTableHeuristic, openings: &[Board]) -> f64 {
+    let mut table_agent = TableAgent {
+        heuristic: *table,
+        depth: TUNING_DEPTH,
+    };
+    let mut reference: AgentFn = reference_agent;
+    let mut points = 0.0;
+    for opening in openings {
+        for table_color in [Color::Black, Color::White] {
+            let last = match table_color {
+                Color::Black => play_from(opening.clone(), &mut table_agent, &mut reference),
+                Color::White => play_from(opening.clone(), &mut reference, &mut table_agent),
+            };
+            points += last.points(table_color);
+        }
+    }
+    points
+}
+
+/// Improve start against reference_agent over options.generations generations. Every table in a
+/// generation plays the same openings, so they're compared on equal terms, and on_generation sees
+/// the best table after each one
+pub fn tune_table(
+    options: &TableTuning,
+    start: WeightedTableHeuristic,
+    mut on_generation: impl FnMut(usize, &TunedTable),
+) -> Result<TunedTable> {
+    let mut rng = SeededRng::new(options.seed);
+    let openings_per_eval = options.games_per_eval.div_ceil(2).max(1);
+    let games = 2 * openings_per_eval;
+    let mut best = TunedTable {
+        table: start,
+        points: 0.0,
+        games: 0,
+    };
+    for generation in 0..options.generations {
+        let openings = random_openings(openings_per_eval, &mut rng);
+        let candidates = match options.strategy {
+            TableStrategy::Evolution => {
+                let mut child = best.table;
+                for weight in &mut child.weights {
+                    *weight += rng.index(2 * TABLE_STEP as usize + 1) as i32 - TABLE_STEP;
+                }
+                vec![child]
+            }
+            TableStrategy::HillClimb => {
+                let coordinate = generation % best.table.weights.len();
+                [TABLE_STEP, -TABLE_STEP]
+                    .into_iter()
+                    .map(|step| {
+                        let mut neighbor = best.table;
+                        neighbor.weights[coordinate] += step;
+                        neighbor
+                    })
+                    .collect()
+            }
+        };
+        best.points = table_score(&best.table, &openings);
+        best.games = games;
+        for candidate in candidates {
+            let points = table_score(&candidate, &openings);
+            let better = match options.strategy {
+                TableStrategy::Evolution => points >= best.points,
+                TableStrategy::HillClimb => points > best.points,
+            };
+            if better {
+                best = TunedTable {
+                    table: candidate,
+                    points,
+                    games,
+                };
+            }
+        }
+        std::fs::write(&options.out, best.table.to_toml())?;
+        on_generation(generation + 1, &best);
+    }
+    if best.games == 0 {
+        let openings = random_openings(openings_per_eval, &mut rng);
+        best.points = table_score(&best.table, &openings);
+        best.games = games;
+    }
+    Ok(best)
+}
+
+/// tune --table [--strategy es|hill] [--generations n] [--games-per-eval n] [--seed n]
+/// [--from table.toml] [--out table.toml]: evolve a positional table, starting from all zeros or
+/// from a table written by an earlier run
+fn tune_table_command(args: &[String]) -> Result<()> {
+    let usage = || {
+        OthelloError::Usage(
+            "tune --table [--strategy es|hill] [--generations n] [--games-per-eval n] [--seed n] \
+             [--from table.toml] [--out table.toml]"
+                .to_string(),
+        )
+    };
+    let mut options = TableTuning {
+        strategy: TableStrategy::Evolution,
+        generations: 20,
+        games_per_eval: 8,
+        seed: config::get().simulate.seed,
+        out: "tuned-table.toml".to_string(),
+    };
+    let mut start = WeightedTableHeuristic::default();
+    let mut args = args.iter().filter(|arg| *arg != "--table");
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or_else(usage)?;
+        match arg.as_str() {
+            "--strategy" => {
+                options.strategy = match value.as_str() {
+                    "es" => TableStrategy::Evolution,
+                    "hill" => TableStrategy::HillClimb,
+                    _ => return Err(usage()),
+                }
+            }
+            "--generations" => options.generations = value.parse()?,
+            "--games-per-eval" => options.games_per_eval = value.parse()?,
+            "--seed" => options.seed = value.parse()?,
+            "--from" => start = WeightedTableHeuristic::load(value)?,
+            "--out" => options.out = value.clone(),
+            _ => return Err(usage()),
+        }
+    }
+
+    let tuned = tune_table(&options, start, |generation, best| {
+        println!(
+            "Generation {}: {} of {} against the reference with {:?}",
+            generation, best.points, best.games, best.table.weights
+        );
+    })?;
+    let (low, high) = tuned.credible_interval();
+    print!("{}", tuned.table.render());
+    println!(
+        "Scored {} of {} ({:.1}%) against the reference, 90% credible interval {:.1}% to {:.1}%",
+        tuned.points,
+        tuned.games,
+        tuned.points * 100.0 / tuned.games as f64,
+        low * 100.0,
+        high * 100.0
+    );
+    println!("Saved the table to {}", options.out);
+    Ok(())
+}
+
+/// tune <weights.toml> [trials] [seed]: print the tuned weights as TOML. With --table, tune a
+/// positional table instead (see tune_table_command)
+pub fn tune_command(args: &[String]) -> Result<()> {
+    if args.iter().any(|arg| arg == "--table") {
+        return tune_table_command(args);
+    }
+    let Some(path) = args.first() else {
+        return Err(OthelloError::Usage(
+            "tune <weights.toml> [trials] [seed]".to_string(),
+        ));
+    };
+    let trials: usize = args.get(1).map(|t| t.parse()).transpose()?.unwrap_or(50);
+    let seed: u64 = args
+        .get(2)
+        .map(|s| s.parse())
+        .transpose()?
+        .unwrap_or(config::get().simulate.seed);
+    let base = WeightedHeuristic::load(path)?;
+    let tuned = tune_weights(base, trials, &mut SeededRng::new(seed));
+    print!("{}", tuned.to_toml());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{standard_heuristic, POSNS, ROWS};
+
+    /// Tuned from weights that value nothing, so the engine just plays its first legal move, the
+    /// weights win a majority of the points over seeded openings the tuning never saw
+    #[test]
+    fn tuned_weights_beat_the_untuned() {
+        const VERIFICATION_OPENINGS: usize = 8;
+        let weak = WeightedHeuristic::default();
+        let tuned = tune_weights(weak, 10, &mut SeededRng::new(0));
+        let mut rng = SeededRng::new(1);
+        let openings: Vec<Board> = (0..VERIFICATION_OPENINGS)
+            .map(|_| Board::random_set_up_with(&mut rng))
+            .collect();
+        let points = match_score(&tuned, &weak, &openings);
+        if points <= VERIFICATION_OPENINGS as f64 {
+            panic!(
+                "tuned weights ({}) scored only {} of {} against the untuned ones",
+                tuned.to_toml().trim().replace('\n', ", "),
+                points,
+                2 * VERIFICATION_OPENINGS
+            );
+        }
+    }
+
+    /// A two-generation tune of each kind runs end to end, writing a table that reads back the same
+    /// and takes the same weight on every square its rotations and reflections reach
+    #[test]
+    fn table_tuning_runs_end_to_end() {
+        let out =
+            std::env::temp_dir().join(format!("othello-table-check-{}.toml", std::process::id()));
+        for strategy in [TableStrategy::Evolution, TableStrategy::HillClimb] {
+            let options = TableTuning {
+                strategy,
+                generations: 2,
+                games_per_eval: 4,
+                seed: 7,
+                out: out.to_string_lossy().into_owned(),
+            };
+            let mut generations = 0;
+            let tuned = tune_table(&options, WeightedTableHeuristic::default(), |_, _| {
+                generations += 1
+            })
+            .unwrap_or_else(|err| panic!("{:?} tuning failed: {}", strategy, err));
+            let saved = WeightedTableHeuristic::load(&options.out);
+            let _ = std::fs::remove_file(&out);
+            if generations != 2 || tuned.games != 4 || tuned.points > 4.0 {
+                panic!(
+                    "{:?} tuning ran {} generations, ending on {} of {}",
+                    strategy, generations, tuned.points, tuned.games
+                );
+            }
+            if saved.ok() != Some(tuned.table) {
+                panic!("{:?} tuning didn't save its best table", strategy);
+            }
+            let last = ROWS - 1;
+            for posn in POSNS {
+                let (row, col) = posn.to_tuple();
+                let images = [
+                    (row, last - col),
+                    (last - row, col),
+                    (last - row, last - col),
+                    (col, row),
+                    (col, last - row),
+                    (last - col, row),
+                    (last - col, last - row),
+                ];
+                for (row, col) in images {
+                    let image = Posn { row, col };
+                    if tuned.table.weight_at(image) != tuned.table.weight_at(posn) {
+                        panic!(
+                            "{:?} tuning weighs {} and {} differently:\n{}",
+                            strategy,
+                            posn,
+                            image,
+                            tuned.table.render()
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Over positions from seeded random games, a heuristic's rank correlation with itself is 1 and
+    /// with its own negation -1, whichever heuristic it is
+    #[test]
+    fn heuristics_correlate_with_themselves() {
+        let rng = &mut SeededRng::new(0);
+        let positions: Vec<Board> = (0..200)
+            .map(|_| {
+                let mut board = Board::random_set_up_with(rng);
+                for _ in 0..rng.index(40) {
+                    if board.is_over() {
+                        break;
+                    }
+                    board = match board.legal_moves().is_empty() {
+                        true => board.change_turn(),
+                        false => board.play_move(&random_agent_with(&board, rng)),
+                    };
+                }
+                board
+            })
+            .collect();
+        let heuristics: [(&str, HeuristicFn); 4] = [
+            ("edge/corner", edge_corner_heuristic),
+            ("negated edge/corner", |board| -edge_corner_heuristic(board)),
+            ("standard", standard_heuristic),
+            ("negated standard", |board| -standard_heuristic(board)),
+        ];
+        let matrix = evaluate_corpus(&positions, &heuristics);
+        for (heuristic, negated) in [(0, 1), (2, 3)] {
+            for (a, b, expected) in [(heuristic, heuristic, 1.0), (heuristic, negated, -1.0)] {
+                let correlation = matrix.spearman(a, b);
+                if (correlation - expected).abs() > 1e-9 {
+                    panic!(
+                        "{} and {} correlate by {}, not {}",
+                        matrix.names[a], matrix.names[b], correlation, expected
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,383 @@
+//! A heuristic built from named terms with weights read from a TOML file, so the evaluation can be
+//! tuned without recompiling. Each term is White's count minus Black's (or +1/-1 for parity), and
+//! the heuristic is their weighted sum:
+//!
+//! ```toml
+//! disc = 1.0
+//! mobility = 2.5
+//! corners = 30
+//! frontier = -1.5
+//! ```
+//!
+//! Terms left out weigh nothing. `--weights <path>` makes it the engine's heuristic.
+//!
+//! WeightedTableHeuristic is the other kind of weights file: a positional table, as tuned by
+//! `tune --table`.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::error::{OthelloError, Result};
+use crate::{
+    alphabeta_root, edge_corner_heuristic, forced_move, Agent, Board, Color, Dir, HeuristicFn,
+    Posn, Square, COLS, DIRS, POSNS, ROWS, SCORE_LOSS, SCORE_WIN,
+};
+
+/// Every term a weights file may set, in the order of WeightedHeuristic::weights
+pub const TERMS: [&str; 6] = [
+    "disc",
+    "mobility",
+    "corners",
+    "stability",
+    "frontier",
+    "parity",
+];
+
+/// Pairs of opposite directions; a disc is stable once it can't be flipped along any of them
+const AXES: [(Dir, Dir); 4] = [
+    (Dir::Up, Dir::Down),
+    (Dir::Left, Dir::Right),
+    (Dir::UpLeft, Dir::DownRight),
+    (Dir::UpRight, Dir::DownLeft),
+];
+
+/// The weights chosen with --weights, if any
+static SELECTED: OnceLock<WeightedHeuristic> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct WeightedHeuristic {
+    /// One weight per entry of TERMS
+    pub weights: [f64; TERMS.len()],
+}
+
+impl WeightedHeuristic {
+    /// Read weights from TOML text: a float (or integer) for each term to use. Unknown term names
+    /// are an error, so a typo doesn't silently leave a term at zero
+    pub fn from_toml(text: &str) -> Result<Self> {
+        let table: HashMap<String, f64> = toml::from_str(text)?;
+        let mut heuristic = Self::default();
+        for (name, weight) in table {
+            let Some(index) = TERMS.iter().position(|term| *term == name) else {
+                return Err(OthelloError::Parse(format!(
+                    "Unknown heuristic term \"{}\" (expected one of {})",
+                    name,
+                    TERMS.join(", ")
+                )));
+            };
+            heuristic.weights[index] = weight;
+        }
+        Ok(heuristic)
+    }
+
+    pub fn load(path: &str) -> Result<Self> {
+        Self::from_toml(&std::fs::read_to_string(path)?)
+    }
+
+    /// Every term and its weight, one per line, in a form from_toml reads back
+    pub fn to_toml(self) -> String {
+        TERMS
+            .iter()
+            .zip(self.weights)
+            .map(|(term, weight)| format!("{} = {:?}\n", term, weight))
+            .collect()
+    }
+
+    /// The weighted sum of the terms, rounded to the nearest integer
+    pub fn evaluate(&self, board: &Board) -> i32 {
+        let sum: f64 = term_values(board)
+            .iter()
+            .zip(self.weights)
+            .map(|(value, weight)| *value as f64 * weight)
+            .sum();
+        sum.round() as i32
+    }
+}
+
+/// Plays the best move by a fixed-depth alpha-beta search under a weighted heuristic. Unlike
+/// weighted_heuristic this carries its own weights, so differently weighted agents can meet
+#[derive(Debug, Clone, Copy)]
+pub struct WeightedAgent {
+    pub heuristic: WeightedHeuristic,
+    pub depth: i32,
+}
+
+impl Agent for WeightedAgent {
+    fn choose_move(&mut self, board: &Board) -> Posn {
+        if let Some(posn) = forced_move(board) {
+            return posn;
+        }
+        let heuristic = &self.heuristic;
+        alphabeta_root(
+            board,
+            self.depth,
+            SCORE_LOSS,
+            SCORE_WIN,
+            |board: &Board| heuristic.evaluate(board),
+            0,
+        )
+        .0
+    }
+}
+
+/// The square standing for each of a positional table's weights. Every other square shares the
+/// weight of the one its rotations and reflections bring it to
+pub const TABLE_SQUARES: [&str; 10] = ["a1", "b1", "c1", "d1", "b2", "c2", "d2", "c3", "d3", "d4"];
+
+/// Which weight of a positional table posn takes, as an index into TABLE_SQUARES
+pub fn table_class(posn: Posn) -> usize {
+    let fold = |i: usize| i.min(ROWS - 1 - i);
+    let (row, col) = (fold(posn.row), fold(posn.col));
+    let (near, far) = (row.min(col), row.max(col));
+    // Folded to the triangle a1-d1-d4, whose rows hold 4, 3, 2 and 1 squares
+    [0, 4, 7, 9][near] + far - near
+}
+
+/// A positional table: each square is worth its weight to whoever holds it. Only the ten weights
+/// of TABLE_SQUARES are kept, so the table is the same from every side of the board. Read from
+/// and written as TOML:
+///
+/// ```toml
+/// table = [100, -20, 10, 5, -50, -2, -1, 5, 1, 0]
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WeightedTableHeuristic {
+    /// One weight per entry of TABLE_SQUARES
+    pub weights: [i32; TABLE_SQUARES.len()],
+}
+
+impl WeightedTableHeuristic {
+    pub fn from_toml(text: &str) -> Result<Self> {
+        let table: HashMap<String, Vec<i32>> = toml::from_str(text)?;
+        let weights = table
+            .get("table")
+            .ok_or_else(|| OthelloError::Parse("Missing \"table\" of weights".to_string()))?;
+        Ok(Self {
+            weights: weights.as_slice().try_into().map_err(|_| {
+                OthelloError::Parse(format!(
+                    "A table needs {} weights, one each for {}",
+                    TABLE_SQUARES.len(),
+                    TABLE_SQUARES.join(" ")
+                ))
+            })?,
+        })
+    }
+
+    pub fn load(path: &str) -> Result<Self> {
+        Self::from_toml(&std::fs::read_to_string(path)?)
+    }
+
+    /// The weights in a form from_toml reads back, under a comment naming their squares
+    pub fn to_toml(self) -> String {
+        format!(
+            "# {}\ntable = {:?}\n",
+            TABLE_SQUARES.join(" "),
+            self.weights
+        )
+    }
+
+    pub fn weight_at(&self, posn: Posn) -> i32 {
+        self.weights[table_class(posn)]
+    }
+
+    /// Every square's weight, indexed by Posn::index
+    pub fn square_weights(&self) -> [i32; ROWS * COLS] {
+        POSNS.map(|posn| self.weight_at(posn))
+    }
+
+    /// White's squares' weights minus Black's
+    pub fn evaluate(&self, board: &Board) -> i32 {
+        let table = self.square_weights();
+        POSNS
+            .into_iter()
+            .map(|posn| match board.piece_at(&posn) {
+                Square::Occupied(Color::White) => table[posn.index()],
+                Square::Occupied(Color::Black) => -table[posn.index()],
+                Square::Unoccupied => 0,
+            })
+            .sum()
+    }
+
+    /// The whole 8x8 table, with columns as wide as the widest weight
+    pub fn render(&self) -> String {
+        let width = self
+            .weights
+            .iter()
+            .map(|weight| weight.to_string().len())
+            .max()
+            .unwrap_or(1);
+        let mut text = " ".to_string();
+        for col in 0..COLS {
+            text.push_str(&format!(" {:>width$}", (b'a' + col as u8) as char));
+        }
+        text.push('\n');
+        for (row, weights) in self.square_weights().chunks(COLS).enumerate() {
+            text.push_str(&(row + 1).to_string());
+            for weight in weights {
+                text.push_str(&format!(" {:>width$}", weight));
+            }
+            text.push('\n');
+        }
+        text
+    }
+}
+
+/// WeightedAgent, searching with a positional table instead
+#[derive(Debug, Clone, Copy)]
+pub struct TableAgent {
+    pub heuristic: WeightedTableHeuristic,
+    pub depth: i32,
+}
+
+impl Agent for TableAgent {
+    fn choose_move(&mut self, board: &Board) -> Posn {
+        if let Some(posn) = forced_move(board) {
+            return posn;
+        }
+        let heuristic = &self.heuristic;
+        alphabeta_root(
+            board,
+            self.depth,
+            SCORE_LOSS,
+            SCORE_WIN,
+            |board: &Board| heuristic.evaluate(board),
+            0,
+        )
+        .0
+    }
+}
+
+/// Each term of TERMS for board, White positive
+pub fn term_values(board: &Board) -> [i32; TERMS.len()] {
+    let difference =
+        |count: &dyn Fn(Color) -> usize| count(Color::White) as i32 - count(Color::Black) as i32;
+    let (black_mobility, white_mobility) = board.mobilities();
+    let stable = stable_discs(board);
+    // Whoever is to move with an odd number of empties gets the last move, if nobody passes
+    let parity = match (board.count_empty() % 2, board.turn) {
+        (0, _) => 0,
+        (_, Color::White) => 1,
+        (_, Color::Black) => -1,
+    };
+    [
+        difference(&|color| board.count_color_pieces(color)),
+        white_mobility as i32 - black_mobility as i32,
+        difference(&|color| {
+            POSNS
+                .iter()
+                .filter(|posn| posn.is_corner() && board.piece_at(posn).is_color(color))
+                .count()
+        }),
+        difference(&|color| {
+            POSNS
+                .iter()
+                .filter(|posn| stable[posn.index()] && board.piece_at(posn).is_color(color))
+                .count()
+        }),
+        difference(&|color| {
+            POSNS
+                .iter()
+                .filter(|posn| {
+                    board.piece_at(posn).is_color(color)
+                        && DIRS.into_iter().any(|dir| {
+                            posn.neighbor(dir)
+                                .is_some_and(|next| !board.piece_at(&next).is_occupied())
+                        })
+                })
+                .count()
+        }),
+        parity,
+    ]
+}
+
+/// Discs that can never be flipped. Along each axis a stable disc must have the edge or a stable
+/// disc of its own color on one side, or sit in a full line. Checking until nothing changes finds
+/// every disc this rule can prove stable, though not every stable disc
+pub fn stable_discs(board: &Board) -> [bool; ROWS * COLS] {
+    let mut stable = [false; ROWS * COLS];
+    let line_full = |posn: &Posn, dir: Dir| {
+        let mut curr = posn.neighbor(dir);
+        while let Some(square) = curr {
+            if !board.piece_at(&square).is_occupied() {
+                return false;
+            }
+            curr = square.neighbor(dir);
+        }
+        true
+    };
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for posn in POSNS {
+            let Square::Occupied(color) = board.piece_at(&posn) else {
+                continue;
+            };
+            if stable[posn.index()] {
+                continue;
+            }
+            let anchored = |dir: Dir| match posn.neighbor(dir) {
+                None => true,
+                Some(next) => stable[next.index()] && board.piece_at(&next).is_color(color),
+            };
+            let holds = AXES.iter().all(|(one_way, other_way)| {
+                anchored(*one_way)
+                    || anchored(*other_way)
+                    || (line_full(&posn, *one_way) && line_full(&posn, *other_way))
+            });
+            if holds {
+                stable[posn.index()] = true;
+                changed = true;
+            }
+        }
+    }
+    stable
+}
+
+/// Make heuristic the engine's heuristic for the rest of the run. Only the first choice counts
+pub fn select(heuristic: WeightedHeuristic) {
+    let _ = SELECTED.set(heuristic);
+}
+
+/// The selected weighted heuristic, or 0 everywhere if none was selected
+pub fn weighted_heuristic(board: &Board) -> i32 {
+    SELECTED
+        .get()
+        .map_or(0, |heuristic| heuristic.evaluate(board))
+}
+
+/// The heuristic the engine plays and analyzes with: the one from --weights if given, and
+/// edge_corner_heuristic otherwise
+pub fn engine_heuristic() -> HeuristicFn {
+    if SELECTED.get().is_some() {
+        weighted_heuristic
+    } else {
+        edge_corner_heuristic
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A weights file evaluates a known position to its weighted sum. After e3, White to move has
+    /// disc -3, mobility 0 (3 moves each), corners 0, stability 0, frontier -3 and parity +1
+    #[test]
+    fn weights_file_scores_a_known_position() {
+        let sample = "disc = 1.5\nmobility = 2.0\nfrontier = -0.5\nparity = 4\n";
+        let heuristic =
+            WeightedHeuristic::from_toml(sample).unwrap_or_else(|err| panic!("{}", err));
+        let board = Board::new().play_move(&Posn::try_from_alphanumeric("e3").unwrap());
+        let values = term_values(&board);
+        if values != [-3, 0, 0, 0, -3, 1] || heuristic.evaluate(&board) != 1 {
+            panic!(
+                "after e3 the terms are {:?} and the sample weights give {}, not 1",
+                values,
+                heuristic.evaluate(&board)
+            );
+        }
+        if WeightedHeuristic::from_toml("dsic = 1.0").is_ok() {
+            panic!("a misspelled term was accepted");
+        }
+        WeightedHeuristic::from_toml(include_str!("../weights.toml"))
+            .unwrap_or_else(|err| panic!("weights.toml didn't load: {}", err));
+    }
+}